@@ -5,39 +5,37 @@ Build idiomatic Rust bindings for COM interfaces.
 # Examples
 
 ```
-# extern crate winapi; extern crate hresult as hr; #[macro_use] extern crate com_core; fn main() {
-use ::winapi::HRESULT;
-use ::hr::HResult;
-
-use ::com_core::ComResult;
-use ::com_core::com::Com;
+# #[macro_use] extern crate com_sys; #[macro_use] extern crate com_types; extern crate com_core; fn main() {
+use ::com_sys::HRESULT;
+use ::com_types::hr::HResult;
+use ::com_types::com::ComResult;
 
 // Define the COM FFI interface struct and its vtbl struct.
 
-use ::com_core::unknown::{IUnknown, IUnknownVtbl};
+use ::com_sys::unknown::{IUnknown, IUnknownVtbl};
 com_interface! {
 	/// Doc comment.
-	interface IInterface(IInterfaceVtbl) : IUnknown(IUnknownVtbl);
+	interface IInterface(IInterfaceVtbl): IUnknown(IUnknownVtbl);
 	{0x582cdb5a-0xe8b9-0x47bb-0xbff8-0x5a18da55bc99}
-	pub Foo: unsafe extern "stdcall" fn(
+	pub Foo: unsafe extern "system" fn(
 		This: *mut IInterface,
 	) -> HRESULT,
 }
 
 // Define and implement the Rust binding for this interface.
 
-use ::com_core::unknown::IUnknownPtr;
+use ::com_types::unknown::IUnknownPtr;
 com_ptr! {
 	/// Doc comment.
 	#[derive(Clone, Debug)]
-	pub struct IInterfacePtr(IInterface) : IUnknownPtr;
+	pub struct IInterfacePtr(IInterface): IUnknownPtr;
 }
 
 impl IInterfacePtr {
 	pub fn foo(&self) -> ComResult<()> {
 		unsafe {
-			let hr: HResult = com_call!(Foo(self)).into();
-			hr.result(())
+			let hr = com_call!(Foo(self));
+			HResult::result(hr, ())
 		}
 	}
 }
@@ -47,6 +45,8 @@ impl IInterfacePtr {
 com_class!(_extern Interface, {0xf08b1ee5-0xc953-0x4faf-0x813c-0x9bd256d6fe48}, IInterface);
 
 // Initialize COM and create an com pointer instance for this class.
+# #[cfg(windows)] {
+use ::com_core::com::Com;
 
 let com = Com::initialize().unwrap();
 let interface_ptr = com.create_instance::<IInterfacePtr, Interface>();
@@ -54,19 +54,24 @@ let interface_ptr = com.create_instance::<IInterfacePtr, Interface>();
 // The above will fail because there's really no such class, but you get the point.
 assert!(interface_ptr.is_err());
 # }
+# }
 ```
 */
 
-#![cfg(windows)]
+#![allow(clippy::tabs_in_doc_comments)]
 
+#[cfg(windows)]
 extern crate winapi;
+#[cfg(windows)]
 extern crate ole32;
+#[cfg(windows)]
 #[macro_use]
 extern crate bitflags;
-#[macro_use]
 extern crate com_sys;
 extern crate com_types;
 
+#[cfg(windows)]
 mod sys;
 
+#[cfg(windows)]
 pub mod com;
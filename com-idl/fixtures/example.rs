@@ -0,0 +1,67 @@
+// Generated from IDL, do not edit.
+
+pub type ShapeKind = i32;
+pub const SK_CIRCLE: ShapeKind = 1;
+pub const SK_RECT: ShapeKind = 2;
+pub const SK_ALL: ShapeKind = 3;
+
+#[repr(C)]
+pub struct Extent {
+	pub cx: i32,
+	pub cy: i32,
+}
+pub type LPEXTENT = *mut Extent;
+
+com_interface! {
+	/// A shape.
+	interface IShape(IShapeVtbl): IUnknown(IUnknownVtbl);
+	"6d5140c1-7436-11ce-8034-00aa006009fa"
+	pub get_Kind: unsafe extern "system" fn(
+		This: *mut IShape,
+		pKind: *mut ShapeKind,
+	) -> HRESULT,
+	pub get_Name: unsafe extern "system" fn(
+		This: *mut IShape,
+		pName: *mut BSTR,
+	) -> HRESULT,
+	pub put_Name: unsafe extern "system" fn(
+		This: *mut IShape,
+		name: BSTR,
+	) -> HRESULT,
+	pub GetExtent: unsafe extern "system" fn(
+		This: *mut IShape,
+		pExtent: *mut Extent,
+	) -> HRESULT,
+	pub Describe: unsafe extern "system" fn(
+		This: *mut IShape,
+		options: *const VARIANT,
+		pResult: *mut VARIANT,
+	) -> HRESULT,
+}
+com_ptr! {
+	/// A shape.
+	pub struct IShapePtr(IShape): IUnknownPtr;
+}
+
+com_interface! {
+	interface IShapes(IShapesVtbl): IShape(IShapeVtbl);
+	"6d5140c2-7436-11ce-8034-00aa006009fa"
+	pub Count: unsafe extern "system" fn(
+		This: *mut IShapes,
+		pCount: *mut ULONG,
+	) -> HRESULT,
+	pub Item: unsafe extern "system" fn(
+		This: *mut IShapes,
+		index: ULONG,
+		ppShape: *mut *mut IShape,
+	) -> HRESULT,
+	pub Add: unsafe extern "system" fn(
+		This: *mut IShapes,
+		shape: *mut IShape,
+	) -> HRESULT,
+}
+com_ptr! {
+	pub struct IShapesPtr(IShapes): IShapePtr;
+}
+
+com_class!(_extern Shapes, "6d5140c4-7436-11ce-8034-00aa006009fa", IShapes);
@@ -0,0 +1,32 @@
+// Generated from IDL, do not edit.
+
+pub type ShapeKind = i32;
+pub const SK_CIRCLE: ShapeKind = 1;
+pub const SK_RECT: ShapeKind = 2;
+pub const SK_ALL: ShapeKind = 3;
+
+#[repr(C)]
+pub struct Extent {
+	pub cx: i32,
+	pub cy: i32,
+}
+pub type LPEXTENT = *mut Extent;
+
+#[com_interface("6d5140c1-7436-11ce-8034-00aa006009fa")]
+/// A shape.
+pub trait IShape: IUnknown {
+	fn get_Kind(&self, #[retval] pKind: *mut ShapeKind) -> HRESULT;
+	fn get_Name(&self, #[retval] pName: *mut BSTR) -> HRESULT;
+	fn put_Name(&self, name: BSTR) -> HRESULT;
+	fn GetExtent(&self, #[out] pExtent: *mut Extent) -> HRESULT;
+	fn Describe(&self, options: *const VARIANT, #[out] pResult: *mut VARIANT) -> HRESULT;
+}
+
+#[com_interface("6d5140c2-7436-11ce-8034-00aa006009fa")]
+pub trait IShapes: IShape {
+	fn Count(&self, #[retval] pCount: *mut ULONG) -> HRESULT;
+	fn Item(&self, index: ULONG, #[retval] #[interface] ppShape: *mut *mut IShape) -> HRESULT;
+	fn Add(&self, #[interface] shape: *mut IShape) -> HRESULT;
+}
+
+com_class!(_extern Shapes, "6d5140c4-7436-11ce-8034-00aa006009fa", IShapes);
@@ -0,0 +1,171 @@
+/*!
+Syntax tree of the supported IDL subset.
+*/
+
+/// Top level definition.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Item {
+	Interface(Interface),
+	Typedef(Typedef),
+	Enum(Enum),
+	Struct(Struct),
+	Coclass(Coclass),
+}
+
+/// Attribute in square brackets, eg. `uuid(...)` or `out`.
+///
+/// The arguments are kept as their source text with the whitespace removed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Attribute {
+	pub name: String,
+	pub args: Option<String>,
+}
+
+/// Finds an attribute by name.
+pub fn find<'a>(attrs: &'a [Attribute], name: &str) -> Option<&'a Attribute> {
+	attrs.iter().find(|attr| attr.name == name)
+}
+
+/// Interface definition, `[uuid(...)] interface IName : IBase { methods };`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Interface {
+	pub attrs: Vec<Attribute>,
+	pub name: String,
+	pub base: Option<String>,
+	pub methods: Vec<Method>,
+}
+impl Interface {
+	/// Returns the interface id from its `uuid` attribute.
+	pub fn uuid(&self) -> Option<&str> {
+		find(&self.attrs, "uuid").and_then(|attr| attr.args.as_deref())
+	}
+}
+
+/// Coclass definition, `[uuid(...)] coclass Name { [default] interface IName; };`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Coclass {
+	pub attrs: Vec<Attribute>,
+	pub name: String,
+	/// Implemented interfaces, `[source]` interfaces are called by the class rather than implemented.
+	pub interfaces: Vec<String>,
+}
+impl Coclass {
+	/// Returns the class id from its `uuid` attribute.
+	pub fn uuid(&self) -> Option<&str> {
+		find(&self.attrs, "uuid").and_then(|attr| attr.args.as_deref())
+	}
+}
+
+/// Method of an interface in vtbl order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Method {
+	pub attrs: Vec<Attribute>,
+	pub ret: Type,
+	pub name: String,
+	pub params: Vec<Param>,
+}
+impl Method {
+	/// Returns the name of its virtual function, properties are prefixed with `get_`, `put_` or `putref_`.
+	pub fn vfn_name(&self) -> String {
+		for &(attr, prefix) in &[("propget", "get_"), ("propput", "put_"), ("propputref", "putref_")] {
+			if find(&self.attrs, attr).is_some() {
+				return format!("{}{}", prefix, self.name);
+			}
+		}
+		self.name.clone()
+	}
+}
+
+/// Method parameter, `[in] long value`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Param {
+	pub attrs: Vec<Attribute>,
+	pub ty: Type,
+	/// Parameters may be unnamed.
+	pub name: Option<String>,
+}
+impl Param {
+	/// Returns if the parameter is `[out]` and not `[in]`.
+	pub fn is_out(&self) -> bool {
+		find(&self.attrs, "out").is_some() && find(&self.attrs, "in").is_none()
+	}
+	/// Returns if the parameter is `[retval]`.
+	pub fn is_retval(&self) -> bool {
+		find(&self.attrs, "retval").is_some()
+	}
+}
+
+/// Type reference.
+///
+/// C integer types are normalized to their canonical spelling, eg. `unsigned long` or `long long`.
+/// Struct and enum tags drop the keyword.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Type {
+	pub name: String,
+	/// The innermost pointee is `const`.
+	pub is_const: bool,
+	pub pointers: usize,
+}
+impl Type {
+	pub fn new(name: &str) -> Type {
+		Type { name: name.to_string(), is_const: false, pointers: 0 }
+	}
+	/// Returns if this is `void` without indirection.
+	pub fn is_void(&self) -> bool {
+		self.name == "void" && self.pointers == 0
+	}
+}
+
+/// Struct field, arrays keep their dimensions as source text.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Field {
+	pub ty: Type,
+	pub name: String,
+	pub dims: Vec<String>,
+}
+
+/// Struct definition, the tag is optional when typedef'd.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Struct {
+	pub attrs: Vec<Attribute>,
+	pub tag: Option<String>,
+	pub fields: Vec<Field>,
+}
+
+/// Enumerator with its evaluated value.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Enumerator {
+	pub name: String,
+	pub value: i64,
+}
+
+/// Enum definition, the tag is optional when typedef'd.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Enum {
+	pub attrs: Vec<Attribute>,
+	pub tag: Option<String>,
+	pub variants: Vec<Enumerator>,
+}
+
+/// What a typedef names.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Target {
+	Type(Type),
+	Struct(Struct),
+	Enum(Enum),
+}
+
+/// Name introduced by a typedef with its additional indirection, eg. `*LPNAME`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Declarator {
+	pub name: String,
+	pub pointers: usize,
+}
+
+/// Type definition, `typedef [attrs] target name, *pname;`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Typedef {
+	pub attrs: Vec<Attribute>,
+	pub target: Target,
+	pub names: Vec<Declarator>,
+}
@@ -0,0 +1,376 @@
+/*!
+Emits Rust source for the syntax tree.
+*/
+
+use ::std::collections::{HashMap, HashSet};
+use ::std::fmt::Write;
+
+use super::ast::*;
+
+/// Generates the Rust bindings.
+///
+/// Interfaces are emitted as `com_interface!` and `com_ptr!` invocations by default,
+/// or as [`#[com_interface]`](../com_types/com/attr.com_interface.html) traits which also generate the safe wrappers.
+///
+/// The generated code expects its names in scope, eg. `HRESULT`, `BSTR` and the base interfaces with their vtbl and com pointer.
+/// C types are translated to Rust primitives, other type names are kept as is.
+#[derive(Clone, Debug, Default)]
+pub struct Generator {
+	attribute: bool,
+}
+impl Generator {
+	pub fn new() -> Generator {
+		Generator::default()
+	}
+	/// Emits `#[com_interface]` traits instead of `com_interface!` and `com_ptr!`.
+	///
+	/// Trailing `[out]` and `[retval]` parameters and interface pointers are annotated for the safe wrappers.
+	pub fn attribute(mut self, attribute: bool) -> Generator {
+		self.attribute = attribute;
+		self
+	}
+	/// Generates the Rust source for the items.
+	pub fn generate(&self, items: &[Item]) -> String {
+		let cx = Context::new(items);
+		let mut out = String::from("// Generated from IDL, do not edit.\n");
+		for item in items {
+			out.push('\n');
+			match *item {
+				Item::Interface(ref iface) => {
+					if self.attribute {
+						cx.trait_interface(&mut out, iface);
+					}
+					else {
+						cx.macro_interface(&mut out, iface);
+					}
+				},
+				Item::Typedef(ref typedef) => cx.typedef(&mut out, typedef),
+				Item::Enum(ref item) => {
+					let name = item.tag.clone().unwrap_or_default();
+					cx.enumeration(&mut out, &item.attrs, &name, item);
+				},
+				Item::Struct(ref item) => {
+					let name = item.tag.clone().unwrap_or_default();
+					cx.structure(&mut out, &item.attrs, &name, item);
+				},
+				Item::Coclass(ref item) => cx.coclass(&mut out, item),
+			}
+		}
+		out
+	}
+}
+
+/// Rust keywords which cannot be used as names.
+const KEYWORDS: &[&str] = &[
+	"abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate", "do", "dyn", "else", "enum", "extern",
+	"false", "final", "fn", "for", "if", "impl", "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv",
+	"pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type", "typeof", "unsafe",
+	"unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Escapes names clashing with Rust keywords by appending an underscore.
+fn ident(name: &str) -> String {
+	if KEYWORDS.contains(&name) { format!("{}_", name) } else { name.to_string() }
+}
+
+/// Translates C types to Rust primitives.
+fn primitive(name: &str) -> Option<&'static str> {
+	Some(match name {
+		"void" => "::std::os::raw::c_void",
+		"char" | "small" => "i8",
+		"unsigned char" | "unsigned small" | "byte" | "boolean" => "u8",
+		"short" => "i16",
+		"unsigned short" | "wchar_t" => "u16",
+		"int" | "long" => "i32",
+		"unsigned int" | "unsigned long" => "u32",
+		"hyper" => "i64",
+		"unsigned hyper" => "u64",
+		"float" => "f32",
+		"double" => "f64",
+		_ => return None,
+	})
+}
+
+struct Context {
+	/// Struct and enum tags renamed to their typedef name.
+	tags: HashMap<String, String>,
+	/// Interfaces declared in the file, along with the imported `IUnknown` and `IDispatch`.
+	interfaces: HashSet<String>,
+}
+impl Context {
+	fn new(items: &[Item]) -> Context {
+		let mut tags = HashMap::new();
+		let mut interfaces: HashSet<String> = ["IUnknown", "IDispatch"].iter().map(|name| name.to_string()).collect();
+		for item in items {
+			if let Item::Interface(ref iface) = *item {
+				interfaces.insert(iface.name.clone());
+			}
+			if let Item::Typedef(ref typedef) = *item {
+				let tag = match typedef.target {
+					Target::Struct(ref item) => item.tag.as_ref(),
+					Target::Enum(ref item) => item.tag.as_ref(),
+					Target::Type(_) => None,
+				};
+				if let (Some(tag), Some(name)) = (tag, primary(typedef)) {
+					tags.insert(tag.clone(), name.to_string());
+				}
+			}
+		}
+		Context { tags, interfaces }
+	}
+
+	fn ty(&self, ty: &Type) -> String {
+		let name = match primitive(&ty.name) {
+			Some(name) => name.to_string(),
+			None => ident(self.tags.get(&ty.name).unwrap_or(&ty.name)),
+		};
+		let mut out = String::new();
+		for i in 0..ty.pointers {
+			out.push_str(if i + 1 == ty.pointers && ty.is_const { "*const " } else { "*mut " });
+		}
+		out.push_str(&name);
+		out
+	}
+
+	//----------------------------------------------------------------
+	// Interfaces
+
+	fn macro_interface(&self, out: &mut String, iface: &Interface) {
+		let name = &iface.name;
+		out.push_str("com_interface! {\n");
+		doc(out, "\t", &iface.attrs);
+		match iface.base {
+			Some(ref base) => writeln!(out, "\tinterface {}({}Vtbl): {}({}Vtbl);", name, name, base, base),
+			None => writeln!(out, "\tinterface {}({}Vtbl);", name, name),
+		}.unwrap();
+		writeln!(out, "\t\"{}\"", iface.uuid().unwrap_or_default()).unwrap();
+		for method in &iface.methods {
+			writeln!(out, "\tpub {}: unsafe extern \"system\" fn(", method.vfn_name()).unwrap();
+			writeln!(out, "\t\tThis: *mut {},", name).unwrap();
+			for (i, param) in method.params.iter().enumerate() {
+				writeln!(out, "\t\t{}: {},", param_name(param, i), self.ty(&param.ty)).unwrap();
+			}
+			out.push_str("\t)");
+			self.ret(out, &method.ret);
+			out.push_str(",\n");
+		}
+		out.push_str("}\n");
+		out.push_str("com_ptr! {\n");
+		doc(out, "\t", &iface.attrs);
+		match iface.base {
+			Some(ref base) => writeln!(out, "\tpub struct {}Ptr({}): {}Ptr;", name, name, base),
+			None => writeln!(out, "\tpub struct {}Ptr({});", name, name),
+		}.unwrap();
+		out.push_str("}\n");
+	}
+
+	fn trait_interface(&self, out: &mut String, iface: &Interface) {
+		writeln!(out, "#[com_interface(\"{}\")]", iface.uuid().unwrap_or_default()).unwrap();
+		doc(out, "", &iface.attrs);
+		match iface.base {
+			Some(ref base) => writeln!(out, "pub trait {}: {} {{", iface.name, base),
+			None => writeln!(out, "pub trait {} {{", iface.name),
+		}.unwrap();
+		for method in &iface.methods {
+			// Only the trailing out parameters are returned by the wrappers.
+			let outs = method.params.iter().rev().take_while(|param| param.is_out() && param.ty.pointers > 0).count();
+			write!(out, "\tfn {}(&self", method.vfn_name()).unwrap();
+			for (i, param) in method.params.iter().enumerate() {
+				out.push_str(", ");
+				if i >= method.params.len() - outs {
+					out.push_str(if param.is_retval() { "#[retval] " } else { "#[out] " });
+				}
+				if self.interfaces.contains(&param.ty.name) && param.ty.pointers == if param.is_out() { 2 } else { 1 } {
+					out.push_str("#[interface] ");
+				}
+				write!(out, "{}: {}", param_name(param, i), self.ty(&param.ty)).unwrap();
+			}
+			out.push(')');
+			self.ret(out, &method.ret);
+			out.push_str(";\n");
+		}
+		out.push_str("}\n");
+	}
+
+	fn ret(&self, out: &mut String, ret: &Type) {
+		if !ret.is_void() {
+			write!(out, " -> {}", self.ty(ret)).unwrap();
+		}
+	}
+
+	/// Declares the class as an uninhabited type implementing its interfaces.
+	fn coclass(&self, out: &mut String, item: &Coclass) {
+		write!(out, "com_class!(_extern {}, \"{}\"", ident(&item.name), item.uuid().unwrap_or_default()).unwrap();
+		for iface in &item.interfaces {
+			write!(out, ", {}", iface).unwrap();
+		}
+		out.push_str(");\n");
+	}
+
+	//----------------------------------------------------------------
+	// Type definitions
+
+	fn typedef(&self, out: &mut String, typedef: &Typedef) {
+		let (base, primary) = match typedef.target {
+			Target::Type(ref ty) => (ty.clone(), None),
+			Target::Struct(ref item) => {
+				let name = primary(typedef).or(item.tag.as_deref()).unwrap_or_default();
+				self.structure(out, &typedef.attrs, name, item);
+				(Type::new(name), Some(name))
+			},
+			Target::Enum(ref item) => {
+				let name = primary(typedef).or(item.tag.as_deref()).unwrap_or_default();
+				self.enumeration(out, &typedef.attrs, name, item);
+				(Type::new(name), Some(name))
+			},
+		};
+		for decl in &typedef.names {
+			if Some(decl.name.as_str()) == primary {
+				continue;
+			}
+			let ty = Type { pointers: base.pointers + decl.pointers, ..base.clone() };
+			doc(out, "", &typedef.attrs);
+			writeln!(out, "pub type {} = {};", ident(&decl.name), self.ty(&ty)).unwrap();
+		}
+	}
+
+	fn structure(&self, out: &mut String, attrs: &[Attribute], name: &str, item: &Struct) {
+		doc(out, "", attrs);
+		out.push_str("#[repr(C)]\n");
+		writeln!(out, "pub struct {} {{", ident(name)).unwrap();
+		for field in &item.fields {
+			let mut ty = self.ty(&field.ty);
+			for dim in field.dims.iter().rev() {
+				ty = format!("[{}; {}]", ty, dim);
+			}
+			writeln!(out, "\tpub {}: {},", ident(&field.name), ty).unwrap();
+		}
+		out.push_str("}\n");
+	}
+
+	/// C enums are plain integers, the enumerators become constants.
+	fn enumeration(&self, out: &mut String, attrs: &[Attribute], name: &str, item: &Enum) {
+		let name = ident(name);
+		doc(out, "", attrs);
+		writeln!(out, "pub type {} = i32;", name).unwrap();
+		// The parser bounds the values to 32 bits, unsigned ones wrap like in C.
+		for variant in &item.variants {
+			writeln!(out, "pub const {}: {} = {};", ident(&variant.name), name, variant.value as i32).unwrap();
+		}
+	}
+}
+
+/// Returns the name of the struct or enum defined by a typedef, the first declarator without indirection.
+fn primary(typedef: &Typedef) -> Option<&str> {
+	typedef.names.iter().find(|decl| decl.pointers == 0).map(|decl| decl.name.as_str())
+}
+
+fn param_name(param: &Param, index: usize) -> String {
+	match param.name {
+		Some(ref name) => ident(name),
+		None => format!("arg{}", index),
+	}
+}
+
+/// Turns the `helpstring` attribute into a doc comment.
+fn doc(out: &mut String, indent: &str, attrs: &[Attribute]) {
+	if let Some(text) = find(attrs, "helpstring").and_then(|attr| attr.args.as_ref()) {
+		writeln!(out, "{}/// {}", indent, text.trim_matches('"')).unwrap();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::super::parse;
+
+	#[test]
+	fn interface() {
+		let items = parse(r#"
+			[object, uuid(12345678-9ABC-DEF0-0123-456789ABCDEF), helpstring("Doc comment.")]
+			interface IFoo : IUnknown {
+				[propget] HRESULT Type([out, retval] long *pValue);
+				HRESULT Bar([in] const void *ref, [in, out] BSTR *name, [out] IFoo **ppFoo, [out] ULONG *pCount);
+				void Baz(unsigned char);
+			};
+		"#).unwrap();
+
+		assert_eq!(Generator::new().generate(&items), "\
+// Generated from IDL, do not edit.
+
+com_interface! {
+	/// Doc comment.
+	interface IFoo(IFooVtbl): IUnknown(IUnknownVtbl);
+	\"12345678-9ABC-DEF0-0123-456789ABCDEF\"
+	pub get_Type: unsafe extern \"system\" fn(
+		This: *mut IFoo,
+		pValue: *mut i32,
+	) -> HRESULT,
+	pub Bar: unsafe extern \"system\" fn(
+		This: *mut IFoo,
+		ref_: *const ::std::os::raw::c_void,
+		name: *mut BSTR,
+		ppFoo: *mut *mut IFoo,
+		pCount: *mut ULONG,
+	) -> HRESULT,
+	pub Baz: unsafe extern \"system\" fn(
+		This: *mut IFoo,
+		arg0: u8,
+	),
+}
+com_ptr! {
+	/// Doc comment.
+	pub struct IFooPtr(IFoo): IUnknownPtr;
+}
+");
+
+		assert_eq!(Generator::new().attribute(true).generate(&items), "\
+// Generated from IDL, do not edit.
+
+#[com_interface(\"12345678-9ABC-DEF0-0123-456789ABCDEF\")]
+/// Doc comment.
+pub trait IFoo: IUnknown {
+	fn get_Type(&self, #[retval] pValue: *mut i32) -> HRESULT;
+	fn Bar(&self, ref_: *const ::std::os::raw::c_void, name: *mut BSTR, #[out] #[interface] ppFoo: *mut *mut IFoo, #[out] pCount: *mut ULONG) -> HRESULT;
+	fn Baz(&self, arg0: u8);
+}
+");
+	}
+	#[test]
+	fn types() {
+		let items = parse(r#"
+			typedef [helpstring("Colors.")] enum tagColor { Red, Green = 0x80000000 } Color, *LPCOLOR;
+			typedef struct tagPoint { long x, y; enum tagColor color; unsigned char tag[4][2]; } Point, *LPPOINT;
+			typedef const wchar_t *LPCWSTR;
+			struct Rect { Point a, b; };
+		"#).unwrap();
+
+		assert_eq!(Generator::new().generate(&items), "\
+// Generated from IDL, do not edit.
+
+/// Colors.
+pub type Color = i32;
+pub const Red: Color = 0;
+pub const Green: Color = -2147483648;
+/// Colors.
+pub type LPCOLOR = *mut Color;
+
+#[repr(C)]
+pub struct Point {
+	pub x: i32,
+	pub y: i32,
+	pub color: Color,
+	pub tag: [[u8; 2]; 4],
+}
+pub type LPPOINT = *mut Point;
+
+pub type LPCWSTR = *const u16;
+
+#[repr(C)]
+pub struct Rect {
+	pub a: Point,
+	pub b: Point,
+}
+");
+	}
+}
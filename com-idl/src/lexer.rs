@@ -0,0 +1,151 @@
+/*!
+Splits IDL source text into tokens.
+*/
+
+use super::ParseError;
+
+/// Kind of token.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Kind {
+	/// Identifiers and keywords.
+	Ident,
+	/// Alphanumeric run starting with a digit, eg. `42`, `0x1F` or the groups of a uuid.
+	Number,
+	/// String literal without its quotes.
+	Str,
+	/// Single punctuation character.
+	Punct,
+}
+
+/// Token and where it starts.
+#[derive(Clone, Debug)]
+pub struct Token {
+	pub kind: Kind,
+	pub text: String,
+	pub line: usize,
+	pub column: usize,
+}
+impl Token {
+	/// Returns if the token is the given identifier or punctuation.
+	pub fn is(&self, text: &str) -> bool {
+		self.kind != Kind::Str && self.text == text
+	}
+}
+
+/// Tokenizes the source text.
+///
+/// Comments and preprocessor directives are skipped.
+pub fn tokenize(src: &str) -> Result<Vec<Token>, ParseError> {
+	let chars: Vec<char> = src.chars().collect();
+	let mut tokens = Vec::new();
+	let mut i = 0;
+	let mut line = 1;
+	let mut column = 1;
+	// Start of the current line, preprocessor directives only start lines.
+	let mut line_start = true;
+	while i < chars.len() {
+		let chr = chars[i];
+		let start = (line, column);
+		let advance = |i: &mut usize, line: &mut usize, column: &mut usize| {
+			if chars[*i] == '\n' {
+				*line += 1;
+				*column = 1;
+			}
+			else {
+				*column += 1;
+			}
+			*i += 1;
+		};
+		if chr == '\n' {
+			line_start = true;
+			advance(&mut i, &mut line, &mut column);
+		}
+		else if chr.is_whitespace() {
+			advance(&mut i, &mut line, &mut column);
+		}
+		else if chr == '#' && line_start {
+			// Skip the directive including escaped line breaks.
+			while i < chars.len() && chars[i] != '\n' {
+				if chars[i] == '\\' && chars.get(i + 1) == Some(&'\n') {
+					advance(&mut i, &mut line, &mut column);
+				}
+				advance(&mut i, &mut line, &mut column);
+			}
+		}
+		else if chr == '/' && chars.get(i + 1) == Some(&'/') {
+			while i < chars.len() && chars[i] != '\n' {
+				advance(&mut i, &mut line, &mut column);
+			}
+		}
+		else if chr == '/' && chars.get(i + 1) == Some(&'*') {
+			advance(&mut i, &mut line, &mut column);
+			advance(&mut i, &mut line, &mut column);
+			loop {
+				if i >= chars.len() {
+					return Err(ParseError::new(start.0, start.1, "unterminated comment"));
+				}
+				if chars[i] == '*' && chars.get(i + 1) == Some(&'/') {
+					advance(&mut i, &mut line, &mut column);
+					advance(&mut i, &mut line, &mut column);
+					break;
+				}
+				advance(&mut i, &mut line, &mut column);
+			}
+		}
+		else if chr == '"' {
+			line_start = false;
+			advance(&mut i, &mut line, &mut column);
+			let mut text = String::new();
+			loop {
+				match chars.get(i) {
+					None | Some(&'\n') => return Err(ParseError::new(start.0, start.1, "unterminated string literal")),
+					Some(&'"') => break,
+					Some(&'\\') if i + 1 < chars.len() => {
+						text.push(chars[i]);
+						advance(&mut i, &mut line, &mut column);
+					},
+					_ => (),
+				}
+				text.push(chars[i]);
+				advance(&mut i, &mut line, &mut column);
+			}
+			advance(&mut i, &mut line, &mut column);
+			tokens.push(Token { kind: Kind::Str, text, line: start.0, column: start.1 });
+		}
+		else if chr.is_alphanumeric() || chr == '_' {
+			line_start = false;
+			let kind = if chr.is_ascii_digit() { Kind::Number } else { Kind::Ident };
+			let mut text = String::new();
+			while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+				text.push(chars[i]);
+				advance(&mut i, &mut line, &mut column);
+			}
+			tokens.push(Token { kind, text, line: start.0, column: start.1 });
+		}
+		else {
+			line_start = false;
+			advance(&mut i, &mut line, &mut column);
+			tokens.push(Token { kind: Kind::Punct, text: chr.to_string(), line: start.0, column: start.1 });
+		}
+	}
+	Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn units() {
+		let tokens = tokenize("#include \"foo.h\"\n[uuid(0000000C-0000)] // comment\n/* multi\nline */ \"a\\\"b\" 0x1F;").unwrap();
+		let texts: Vec<_> = tokens.iter().map(|token| (token.kind.clone(), token.text.as_str())).collect();
+		assert_eq!(texts, [
+			(Kind::Punct, "["), (Kind::Ident, "uuid"), (Kind::Punct, "("), (Kind::Number, "0000000C"), (Kind::Punct, "-"),
+			(Kind::Number, "0000"), (Kind::Punct, ")"), (Kind::Punct, "]"), (Kind::Str, "a\\\"b"), (Kind::Number, "0x1F"), (Kind::Punct, ";"),
+		]);
+		assert_eq!((tokens[8].line, tokens[8].column), (4, 9));
+
+		let err = tokenize("\n  /* open").unwrap_err();
+		assert_eq!(err.to_string(), "2:3: unterminated comment");
+	}
+}
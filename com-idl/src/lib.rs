@@ -0,0 +1,147 @@
+/*!
+Generates COM interface definitions from IDL.
+
+Parses the subset of MIDL describing vtbl interfaces and emits the equivalent `com_interface!` and `com_ptr!` definitions,
+keeping the methods in their vtbl order.
+
+Supported are interfaces with their `uuid` attribute and base interface, `[in]`, `[out]` and `[retval]` parameters,
+properties, `typedef`s, `enum`s, `struct`s and coclasses, which become `com_class!` declarations.
+Imports, `cpp_quote`, preprocessor directives and dispinterfaces are skipped, libraries are flattened.
+
+Use it from a build script or run the `com-idl` binary on an IDL file and check in its output.
+
+# Examples
+
+```
+extern crate com_idl;
+
+let rust = com_idl::translate(r#"
+	[object, uuid(582cdb5a-e8b9-47bb-bff8-5a18da55bc99)]
+	interface IInterface : IUnknown {
+		HRESULT Foo([in] long value, [out, retval] BSTR *pResult);
+	};
+"#).unwrap();
+
+assert!(rust.contains("interface IInterface(IInterfaceVtbl): IUnknown(IUnknownVtbl);"));
+assert!(rust.contains("pub Foo: unsafe extern \"system\" fn("));
+assert!(rust.contains("pub struct IInterfacePtr(IInterface): IUnknownPtr;"));
+```
+*/
+
+#![allow(clippy::tabs_in_doc_comments)]
+
+#[cfg(test)]
+#[macro_use]
+extern crate com_sys;
+#[cfg(test)]
+#[macro_use]
+extern crate com_types;
+
+use ::std::{error, fmt};
+
+pub mod ast;
+mod generate;
+mod lexer;
+mod parser;
+
+pub use generate::Generator;
+pub use parser::parse;
+
+/// Parses the IDL and generates the Rust bindings with the default [`Generator`](struct.Generator.html).
+pub fn translate(src: &str) -> Result<String, ParseError> {
+	Ok(Generator::new().generate(&parse(src)?))
+}
+
+/// Error parsing IDL, with the line and column where it occurred.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseError {
+	pub line: usize,
+	pub column: usize,
+	pub message: String,
+}
+impl ParseError {
+	fn new(line: usize, column: usize, message: &str) -> ParseError {
+		ParseError { line, column, message: message.to_string() }
+	}
+}
+impl fmt::Display for ParseError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}:{}: {}", self.line, self.column, self.message)
+	}
+}
+impl error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const EXAMPLE_IDL: &str = include_str!("../fixtures/example.idl");
+
+	#[test]
+	fn fixtures() {
+		assert_eq!(translate(EXAMPLE_IDL).unwrap(), include_str!("../fixtures/example.rs"));
+		let items = parse(EXAMPLE_IDL).unwrap();
+		assert_eq!(Generator::new().attribute(true).generate(&items), include_str!("../fixtures/example_attribute.rs"));
+	}
+
+	// The generated code compiles given the names it expects in scope.
+	#[allow(dead_code, non_camel_case_types, clippy::upper_case_acronyms)]
+	mod example {
+		use ::com_sys::HRESULT;
+		use ::com_sys::unknown::{IUnknown, IUnknownVtbl};
+		use ::com_types::unknown::IUnknownPtr;
+		type ULONG = u32;
+		type BSTR = *mut u16;
+		#[repr(C)]
+		pub struct VARIANT([u64; 3]);
+
+		include!("../fixtures/example.rs");
+
+		#[test]
+		fn layout() {
+			use ::std::mem;
+			use ::com_sys::{ComClass, ComInterface};
+			assert_eq!(<IShapes as ComInterface>::IID, "6d5140c2-7436-11ce-8034-00aa006009fa".parse().unwrap());
+			assert_eq!(<Shapes as ComClass>::CLSID, "6d5140c4-7436-11ce-8034-00aa006009fa".parse().unwrap());
+			// The vtbls hold IUnknown's three functions followed by the methods in IDL order.
+			assert_eq!(mem::size_of::<IShapeVtbl<IShape>>(), 8 * mem::size_of::<usize>());
+			assert_eq!(mem::size_of::<IShapesVtbl<IShapes>>(), 11 * mem::size_of::<usize>());
+		}
+		#[test]
+		fn slots() {
+			use ::com_sys::ComVtbl;
+			assert_eq!(IShapesVtbl::<IShapes>::slot_names(), [
+				"QueryInterface", "AddRef", "Release",
+				"get_Kind", "get_Name", "put_Name", "GetExtent", "Describe",
+				"Count", "Item", "Add",
+			]);
+		}
+		com_assert_slots!(IShape, 8);
+		com_assert_slots!(IShapes, 11);
+	}
+
+	#[allow(dead_code, non_camel_case_types, non_snake_case, clippy::upper_case_acronyms)]
+	mod example_attribute {
+		use ::com_sys::{HRESULT, VARIANT};
+		use ::com_sys::unknown::{IUnknown, IUnknownVtbl};
+		use ::com_types::com::com_interface;
+		type ULONG = u32;
+		type BSTR = *mut u16;
+
+		include!("../fixtures/example_attribute.rs");
+
+		#[test]
+		fn wrappers() {
+			use ::com_sys::{ComClass, ComInterface};
+			use ::com_types::com::ComResult;
+			assert_eq!(<IShapes as ComInterface>::IID, "6d5140c2-7436-11ce-8034-00aa006009fa".parse().unwrap());
+			assert_eq!(<Shapes as ComClass>::CLSID, "6d5140c4-7436-11ce-8034-00aa006009fa".parse().unwrap());
+			// The annotated arguments get the safe wrappers.
+			let _: fn(&IShapesPtr, ULONG) -> ComResult<IShapePtr> = IShapesPtr::item;
+			let _: fn(&IShapesPtr, &IShapePtr) -> ComResult<()> = IShapesPtr::add;
+			let _: fn(&IShapePtr) -> ComResult<::com_types::bstr::BString> = IShapePtr::get_name;
+		}
+		com_assert_slots!(IShape, 8);
+		com_assert_slots!(IShapes, 11);
+	}
+}
@@ -0,0 +1,38 @@
+/*!
+Translates an IDL file to Rust.
+
+Usage: `com-idl [--attribute] <input.idl>`, writes the Rust source to stdout.
+*/
+
+extern crate com_idl;
+
+use ::std::{env, fs, process};
+
+fn main() {
+	let mut attribute = false;
+	let mut path = None;
+	for arg in env::args().skip(1) {
+		match arg.as_str() {
+			"--attribute" => attribute = true,
+			_ if path.is_none() => path = Some(arg),
+			_ => usage(),
+		}
+	}
+	let path = path.unwrap_or_else(|| usage());
+	let src = fs::read_to_string(&path).unwrap_or_else(|err| {
+		eprintln!("{}: {}", path, err);
+		process::exit(1);
+	});
+	match com_idl::parse(&src) {
+		Ok(items) => print!("{}", com_idl::Generator::new().attribute(attribute).generate(&items)),
+		Err(err) => {
+			eprintln!("{}:{}", path, err);
+			process::exit(1);
+		},
+	}
+}
+
+fn usage() -> ! {
+	eprintln!("usage: com-idl [--attribute] <input.idl>");
+	process::exit(2);
+}
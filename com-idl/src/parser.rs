@@ -0,0 +1,698 @@
+/*!
+Parses the tokens into the syntax tree.
+*/
+
+use ::std::convert::TryFrom;
+
+use super::ParseError;
+use super::ast::*;
+use super::lexer::{self, Kind, Token};
+
+/// C integer keywords which combine into a single type, eg. `unsigned long int`.
+const INTEGER_WORDS: &[&str] = &["unsigned", "signed", "long", "short", "int", "char", "small", "hyper", "__int8", "__int16", "__int32", "__int64"];
+
+pub fn parse(src: &str) -> Result<Vec<Item>, ParseError> {
+	let tokens = lexer::tokenize(src)?;
+	let mut parser = Parser { tokens, pos: 0, items: Vec::new() };
+	while !parser.at_end() {
+		parser.item()?;
+	}
+	Ok(parser.items)
+}
+
+struct Parser {
+	tokens: Vec<Token>,
+	pos: usize,
+	/// Definitions nested in interfaces and libraries are flattened.
+	items: Vec<Item>,
+}
+impl Parser {
+	//----------------------------------------------------------------
+	// Token helpers
+
+	fn at_end(&self) -> bool {
+		self.pos >= self.tokens.len()
+	}
+	fn peek(&self) -> Option<&Token> {
+		self.tokens.get(self.pos)
+	}
+	fn peek_is(&self, text: &str) -> bool {
+		self.peek().map(|token| token.is(text)).unwrap_or(false)
+	}
+	fn peek_at_is(&self, offset: usize, text: &str) -> bool {
+		self.tokens.get(self.pos + offset).map(|token| token.is(text)).unwrap_or(false)
+	}
+	fn next(&mut self) -> Result<Token, ParseError> {
+		match self.tokens.get(self.pos) {
+			Some(token) => {
+				self.pos += 1;
+				Ok(token.clone())
+			},
+			None => Err(self.error("unexpected end of input")),
+		}
+	}
+	fn eat(&mut self, text: &str) -> bool {
+		if self.peek_is(text) {
+			self.pos += 1;
+			true
+		}
+		else {
+			false
+		}
+	}
+	fn expect(&mut self, text: &str) -> Result<(), ParseError> {
+		if self.eat(text) {
+			Ok(())
+		}
+		else {
+			Err(self.error(&format!("expected `{}`", text)))
+		}
+	}
+	fn ident(&mut self) -> Result<String, ParseError> {
+		match self.peek() {
+			Some(token) if token.kind == Kind::Ident => (),
+			_ => return Err(self.error("expected an identifier")),
+		}
+		self.next().map(|token| token.text)
+	}
+	fn error(&self, msg: &str) -> ParseError {
+		match self.peek().or_else(|| self.tokens.last()) {
+			Some(token) => ParseError::new(token.line, token.column, msg),
+			None => ParseError::new(1, 1, msg),
+		}
+	}
+	/// Skips a balanced `(...)`, `[...]` or `{...}` group starting at the current token.
+	fn skip_group(&mut self) -> Result<(), ParseError> {
+		// The closers of the open brackets.
+		let mut closers = Vec::new();
+		loop {
+			let token = self.next()?;
+			if token.kind == Kind::Punct {
+				match token.text.as_str() {
+					"(" => closers.push(")"),
+					"[" => closers.push("]"),
+					"{" => closers.push("}"),
+					text @ (")" | "]" | "}") if closers.pop() != Some(text) => {
+						return Err(ParseError::new(token.line, token.column, &format!("unmatched `{}`", text)));
+					},
+					_ => (),
+				}
+			}
+			if closers.is_empty() {
+				return Ok(());
+			}
+		}
+	}
+
+	//----------------------------------------------------------------
+	// Items
+
+	fn item(&mut self) -> Result<(), ParseError> {
+		let attrs = self.attributes()?;
+		let token = self.next()?;
+		match token.text.as_str() {
+			_ if token.kind != Kind::Ident => Err(ParseError::new(token.line, token.column, "expected a definition")),
+			"import" => {
+				while !self.eat(";") {
+					self.next()?;
+				}
+				Ok(())
+			},
+			"importlib" | "cpp_quote" | "midl_pragma" => {
+				self.skip_group()?;
+				self.eat(";");
+				Ok(())
+			},
+			"interface" => self.interface(attrs),
+			"library" => {
+				self.ident()?;
+				self.expect("{")?;
+				while !self.eat("}") {
+					self.item()?;
+				}
+				self.eat(";");
+				Ok(())
+			},
+			"coclass" => self.coclass(attrs),
+			// Only the vtbl interfaces are of interest.
+			"dispinterface" | "module" => {
+				self.ident()?;
+				if self.peek_is("{") {
+					self.skip_group()?;
+				}
+				self.eat(";");
+				Ok(())
+			},
+			"typedef" => self.typedef(),
+			"struct" if self.peek_is("{") || self.peek_at_is(1, "{") => {
+				let item = self.struct_body(attrs)?;
+				self.items.push(Item::Struct(item));
+				self.expect(";")
+			},
+			"enum" if self.peek_is("{") || self.peek_at_is(1, "{") => {
+				let item = self.enum_body(attrs)?;
+				self.items.push(Item::Enum(item));
+				self.expect(";")
+			},
+			// Forward declarations.
+			"struct" | "enum" => {
+				self.ident()?;
+				self.expect(";")
+			},
+			_ => Err(ParseError::new(token.line, token.column, &format!("unsupported definition `{}`", token.text))),
+		}
+	}
+
+	fn attributes(&mut self) -> Result<Vec<Attribute>, ParseError> {
+		let mut attrs = Vec::new();
+		while self.eat("[") {
+			if self.eat("]") {
+				continue;
+			}
+			loop {
+				let name = self.ident()?;
+				let args = if self.peek_is("(") {
+					let start = self.pos + 1;
+					self.skip_group()?;
+					let text: String = self.tokens[start..self.pos - 1].iter().map(|token| match token.kind {
+						Kind::Str => format!("\"{}\"", token.text),
+						_ => token.text.clone(),
+					}).collect();
+					Some(text)
+				}
+				else {
+					None
+				};
+				if name == "uuid" && !args.as_deref().map(is_guid).unwrap_or(false) {
+					let token = &self.tokens[self.pos - 1];
+					return Err(ParseError::new(token.line, token.column, "expected a guid in `uuid`"));
+				}
+				attrs.push(Attribute { name, args });
+				if self.eat("]") {
+					break;
+				}
+				self.expect(",")?;
+			}
+		}
+		Ok(attrs)
+	}
+
+	fn interface(&mut self, attrs: Vec<Attribute>) -> Result<(), ParseError> {
+		let at = self.tokens[self.pos - 1].clone();
+		let name = self.ident()?;
+		// Forward declaration.
+		if self.eat(";") {
+			return Ok(());
+		}
+		let base = if self.eat(":") { Some(self.ident()?) } else { None };
+		self.expect("{")?;
+		let mut methods = Vec::new();
+		while !self.eat("}") {
+			let attrs = self.attributes()?;
+			if self.peek_is("typedef") || self.is_definition() {
+				self.item_with(attrs)?;
+				continue;
+			}
+			methods.push(self.method(attrs)?);
+		}
+		self.eat(";");
+		if find(&attrs, "uuid").is_none() {
+			return Err(ParseError::new(at.line, at.column, &format!("interface `{}` is missing its `uuid` attribute", name)));
+		}
+		self.items.push(Item::Interface(Interface { attrs, name, base, methods }));
+		Ok(())
+	}
+	fn coclass(&mut self, attrs: Vec<Attribute>) -> Result<(), ParseError> {
+		let at = self.tokens[self.pos - 1].clone();
+		let name = self.ident()?;
+		// Forward declaration.
+		if self.eat(";") {
+			return Ok(());
+		}
+		self.expect("{")?;
+		let mut interfaces = Vec::new();
+		while !self.eat("}") {
+			let attrs = self.attributes()?;
+			if !self.eat("interface") && !self.eat("dispinterface") {
+				return Err(self.error("expected `interface`"));
+			}
+			let iface = self.ident()?;
+			self.expect(";")?;
+			if find(&attrs, "source").is_none() {
+				interfaces.push(iface);
+			}
+		}
+		self.eat(";");
+		if find(&attrs, "uuid").is_none() {
+			return Err(ParseError::new(at.line, at.column, &format!("coclass `{}` is missing its `uuid` attribute", name)));
+		}
+		self.items.push(Item::Coclass(Coclass { attrs, name, interfaces }));
+		Ok(())
+	}
+	/// Returns if a struct or enum definition follows, rather than a type reference.
+	fn is_definition(&self) -> bool {
+		(self.peek_is("struct") || self.peek_is("enum")) && (self.peek_at_is(1, "{") || self.peek_at_is(2, "{"))
+	}
+	/// Parses a nested definition whose attributes were already parsed.
+	fn item_with(&mut self, attrs: Vec<Attribute>) -> Result<(), ParseError> {
+		if self.eat("typedef") {
+			return self.typedef();
+		}
+		let is_struct = self.eat("struct");
+		if !is_struct {
+			self.expect("enum")?;
+		}
+		if is_struct {
+			let item = self.struct_body(attrs)?;
+			self.items.push(Item::Struct(item));
+		}
+		else {
+			let item = self.enum_body(attrs)?;
+			self.items.push(Item::Enum(item));
+		}
+		self.expect(";")
+	}
+
+	fn method(&mut self, attrs: Vec<Attribute>) -> Result<Method, ParseError> {
+		let ret = self.ty()?;
+		// Calling conventions are implied.
+		while self.peek_is("__stdcall") || self.peek_is("STDMETHODCALLTYPE") {
+			self.pos += 1;
+		}
+		let name = self.ident()?;
+		self.expect("(")?;
+		let mut params = Vec::new();
+		if self.peek_is("void") && self.peek_at_is(1, ")") {
+			self.pos += 1;
+		}
+		if !self.eat(")") {
+			loop {
+				params.push(self.param()?);
+				if self.eat(")") {
+					break;
+				}
+				self.expect(",")?;
+			}
+		}
+		self.expect(";")?;
+		Ok(Method { attrs, ret, name, params })
+	}
+	fn param(&mut self) -> Result<Param, ParseError> {
+		let attrs = self.attributes()?;
+		let mut ty = self.ty()?;
+		let name = match self.peek() {
+			Some(token) if token.kind == Kind::Ident => Some(self.ident()?),
+			_ => None,
+		};
+		// Array parameters decay to pointers.
+		while self.peek_is("[") {
+			self.skip_group()?;
+			ty.pointers += 1;
+		}
+		Ok(Param { attrs, ty, name })
+	}
+
+	//----------------------------------------------------------------
+	// Types
+
+	fn ty(&mut self) -> Result<Type, ParseError> {
+		let mut is_const = false;
+		loop {
+			if self.eat("const") {
+				is_const = true;
+			}
+			else if !self.eat("volatile") {
+				break;
+			}
+		}
+		let name = if self.peek_is("struct") || self.peek_is("enum") || self.peek_is("union") {
+			self.pos += 1;
+			self.ident()?
+		}
+		else if self.peek().map(|token| INTEGER_WORDS.contains(&token.text.as_str())).unwrap_or(false) {
+			self.integer()
+		}
+		else if self.peek_is("SAFEARRAY") && self.peek_at_is(1, "(") {
+			// `SAFEARRAY(T)` is a typed `SAFEARRAY*`.
+			self.pos += 1;
+			self.skip_group()?;
+			return self.pointers(Type { name: String::from("SAFEARRAY"), is_const, pointers: 1 });
+		}
+		else {
+			self.ident()?
+		};
+		// Trailing `const` as in `char const*`.
+		if self.eat("const") {
+			is_const = true;
+		}
+		self.pointers(Type { name, is_const, pointers: 0 })
+	}
+	fn pointers(&mut self, mut ty: Type) -> Result<Type, ParseError> {
+		while self.eat("*") {
+			ty.pointers += 1;
+			// Constness of the pointer itself does not matter.
+			self.eat("const");
+		}
+		Ok(ty)
+	}
+	/// Combines C integer keywords into their canonical spelling.
+	fn integer(&mut self) -> String {
+		let mut unsigned = false;
+		let mut longs = 0;
+		let mut base = None;
+		while let Some(text) = self.peek().filter(|token| token.kind == Kind::Ident).map(|token| token.text.clone()) {
+			match text.as_str() {
+				"unsigned" => unsigned = true,
+				"signed" => (),
+				"long" => longs += 1,
+				"int" => base = base.or(Some("int")),
+				"short" => base = Some("short"),
+				"char" => base = Some("char"),
+				"small" => base = Some("small"),
+				"hyper" | "__int64" => base = Some("hyper"),
+				"__int8" => base = Some("char"),
+				"__int16" => base = Some("short"),
+				"__int32" => base = Some("int"),
+				_ => break,
+			}
+			self.pos += 1;
+		}
+		let base = match (longs, base) {
+			(0, Some(base)) => base,
+			(0, None) => "int",
+			(1, _) => "long",
+			(_, _) => "hyper",
+		};
+		if unsigned { format!("unsigned {}", base) } else { String::from(base) }
+	}
+
+	//----------------------------------------------------------------
+	// Type definitions
+
+	fn typedef(&mut self) -> Result<(), ParseError> {
+		let attrs = self.attributes()?;
+		let target = if self.is_definition() && self.eat("struct") {
+			Target::Struct(self.struct_body(Vec::new())?)
+		}
+		else if self.is_definition() && self.eat("enum") {
+			Target::Enum(self.enum_body(Vec::new())?)
+		}
+		else {
+			Target::Type(self.ty()?)
+		};
+		let mut names = Vec::new();
+		loop {
+			let mut pointers = 0;
+			while self.eat("*") {
+				pointers += 1;
+			}
+			names.push(Declarator { name: self.ident()?, pointers });
+			if self.eat(";") {
+				break;
+			}
+			self.expect(",")?;
+		}
+		self.items.push(Item::Typedef(Typedef { attrs, target, names }));
+		Ok(())
+	}
+	/// Parses the optional tag and the fields of a struct after the `struct` keyword.
+	fn struct_body(&mut self, attrs: Vec<Attribute>) -> Result<Struct, ParseError> {
+		let tag = if self.peek_is("{") { None } else { Some(self.ident()?) };
+		self.expect("{")?;
+		let mut fields = Vec::new();
+		while !self.eat("}") {
+			self.attributes()?;
+			if self.peek_is("union") || self.is_definition() {
+				return Err(self.error("nested structs and unions are not supported"));
+			}
+			let ty = self.ty()?;
+			loop {
+				let mut ty = ty.clone();
+				while self.eat("*") {
+					ty.pointers += 1;
+				}
+				let name = self.ident()?;
+				let mut dims = Vec::new();
+				while self.eat("[") {
+					let start = self.pos;
+					while !self.eat("]") {
+						self.next()?;
+					}
+					dims.push(self.tokens[start..self.pos - 1].iter().map(|token| token.text.as_str()).collect());
+				}
+				fields.push(Field { ty, name, dims });
+				if self.eat(";") {
+					break;
+				}
+				self.expect(",")?;
+			}
+		}
+		Ok(Struct { attrs, tag, fields })
+	}
+	/// Parses the optional tag and the enumerators of an enum after the `enum` keyword.
+	fn enum_body(&mut self, attrs: Vec<Attribute>) -> Result<Enum, ParseError> {
+		let tag = if self.peek_is("{") { None } else { Some(self.ident()?) };
+		self.expect("{")?;
+		let mut variants: Vec<Enumerator> = Vec::new();
+		while !self.eat("}") {
+			let at = self.pos;
+			let name = self.ident()?;
+			let value = if self.eat("=") {
+				Some(self.expr(&variants)?)
+			}
+			else {
+				match variants.last() {
+					Some(prev) => prev.value.checked_add(1),
+					None => Some(0),
+				}
+			};
+			// Enumerators are 32 bit, signed or unsigned.
+			let value = value.filter(|&value| value >= i64::from(i32::MIN) && value <= i64::from(u32::MAX)).ok_or_else(|| {
+				ParseError::new(self.tokens[at].line, self.tokens[at].column, &format!("enumerator `{}` overflows", name))
+			})?;
+			variants.push(Enumerator { name, value });
+			if !self.eat(",") {
+				self.expect("}")?;
+				break;
+			}
+		}
+		Ok(Enum { attrs, tag, variants })
+	}
+
+	//----------------------------------------------------------------
+	// Constant expressions
+
+	/// Evaluates `|`, `<<`, `+`, `-` and unary `-`, `~` on integers and previous enumerators.
+	fn expr(&mut self, scope: &[Enumerator]) -> Result<i64, ParseError> {
+		let mut value = self.shift_expr(scope)?;
+		while self.eat("|") {
+			value |= self.shift_expr(scope)?;
+		}
+		Ok(value)
+	}
+	fn shift_expr(&mut self, scope: &[Enumerator]) -> Result<i64, ParseError> {
+		let mut value = self.add_expr(scope)?;
+		while self.peek_is("<") && self.peek_at_is(1, "<") {
+			self.pos += 2;
+			let rhs = self.add_expr(scope)?;
+			// Bits shifted out overflow.
+			value = u32::try_from(rhs).ok()
+				.and_then(|rhs| value.checked_shl(rhs).filter(|&shifted| shifted >> rhs == value))
+				.ok_or_else(|| self.overflow())?;
+		}
+		Ok(value)
+	}
+	fn add_expr(&mut self, scope: &[Enumerator]) -> Result<i64, ParseError> {
+		let mut value = self.unary_expr(scope)?;
+		loop {
+			if self.eat("+") {
+				let rhs = self.unary_expr(scope)?;
+				value = value.checked_add(rhs).ok_or_else(|| self.overflow())?;
+			}
+			else if self.eat("-") {
+				let rhs = self.unary_expr(scope)?;
+				value = value.checked_sub(rhs).ok_or_else(|| self.overflow())?;
+			}
+			else {
+				return Ok(value);
+			}
+		}
+	}
+	fn unary_expr(&mut self, scope: &[Enumerator]) -> Result<i64, ParseError> {
+		if self.eat("-") {
+			let value = self.unary_expr(scope)?;
+			return value.checked_neg().ok_or_else(|| self.overflow());
+		}
+		if self.eat("+") {
+			return self.unary_expr(scope);
+		}
+		if self.eat("~") {
+			return Ok(!self.unary_expr(scope)?);
+		}
+		if self.eat("(") {
+			let value = self.expr(scope)?;
+			self.expect(")")?;
+			return Ok(value);
+		}
+		let token = self.next()?;
+		let value = match token.kind {
+			Kind::Number => parse_int(&token.text),
+			Kind::Ident => scope.iter().find(|variant| variant.name == token.text).map(|variant| variant.value),
+			_ => None,
+		};
+		value.ok_or_else(|| ParseError::new(token.line, token.column, &format!("expected an integer constant, found `{}`", token.text)))
+	}
+	/// Reports the constant expression ending before the current token overflowing `i64`.
+	fn overflow(&self) -> ParseError {
+		let token = &self.tokens[self.pos - 1];
+		ParseError::new(token.line, token.column, "integer constant overflows")
+	}
+}
+
+/// Returns if the text is a guid without braces, eg. `12345678-9ABC-DEF0-0123-456789ABCDEF`.
+fn is_guid(text: &str) -> bool {
+	text.len() == 36 && text.bytes().enumerate().all(|(i, byte)| match i {
+		8 | 13 | 18 | 23 => byte == b'-',
+		_ => byte.is_ascii_hexdigit(),
+	})
+}
+
+/// Parses decimal, hex and octal integer literals with optional `u` and `l` suffixes.
+fn parse_int(text: &str) -> Option<i64> {
+	let text = text.trim_end_matches(['u', 'U', 'l', 'L']);
+	if text.starts_with("0x") || text.starts_with("0X") {
+		i64::from_str_radix(&text[2..], 16).ok()
+	}
+	else if text.len() > 1 && text.starts_with('0') {
+		i64::from_str_radix(&text[1..], 8).ok()
+	}
+	else {
+		text.parse().ok()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn interface() {
+		let items = parse(r#"
+			import "unknwn.idl";
+			interface IForward;
+			[object, uuid(12345678-9ABC-DEF0-0123-456789ABCDEF), helpstring("Doc.")]
+			interface IFoo : IUnknown {
+				HRESULT Foo(void);
+				[propget] HRESULT Value([out, retval] long *pValue);
+				HRESULT Bar([in] unsigned long count, [in, size_is(count)] const BSTR names[], [out] IFoo **ppFoo);
+				ULONG Baz();
+			};
+			[uuid(12345678-9ABC-DEF0-0123-456789ABCDF0)]
+			coclass Foo {
+				[default] interface IFoo;
+				[source] dispinterface DFooEvents;
+				interface IUnknown;
+			};
+		"#).unwrap();
+		assert_eq!(items.len(), 2);
+		let iface = match items[0] {
+			Item::Interface(ref iface) => iface,
+			_ => panic!(),
+		};
+		assert_eq!(iface.name, "IFoo");
+		assert_eq!(iface.base.as_deref(), Some("IUnknown"));
+		assert_eq!(iface.uuid(), Some("12345678-9ABC-DEF0-0123-456789ABCDEF"));
+		assert_eq!(find(&iface.attrs, "helpstring").unwrap().args.as_ref().unwrap(), "\"Doc.\"");
+
+		let names: Vec<_> = iface.methods.iter().map(|m| m.vfn_name()).collect();
+		assert_eq!(names, ["Foo", "get_Value", "Bar", "Baz"]);
+		assert!(iface.methods[0].params.is_empty());
+		assert!(iface.methods[1].params[0].is_retval());
+
+		let bar = &iface.methods[2].params;
+		assert_eq!(bar[0].ty, Type::new("unsigned long"));
+		assert_eq!(bar[1].ty, Type { name: String::from("BSTR"), is_const: true, pointers: 1 });
+		assert_eq!(find(&bar[1].attrs, "size_is").unwrap().args.as_ref().unwrap(), "count");
+		assert_eq!(bar[2].ty, Type { name: String::from("IFoo"), is_const: false, pointers: 2 });
+		assert!(bar[2].is_out() && !bar[0].is_out());
+		assert_eq!(iface.methods[3].ret, Type::new("ULONG"));
+
+		match items[1] {
+			Item::Coclass(ref class) => {
+				assert_eq!(class.name, "Foo");
+				assert_eq!(class.uuid(), Some("12345678-9ABC-DEF0-0123-456789ABCDF0"));
+				assert_eq!(class.interfaces, ["IFoo", "IUnknown"]);
+			},
+			_ => panic!(),
+		}
+	}
+	#[test]
+	fn types() {
+		let items = parse(r#"
+			typedef [v1_enum] enum tagColor { Red, Green = 0x10, Blue, Mask = Green | Blue, Shift = 1 << 4, Neg = -1 } Color;
+			typedef struct tagPoint { long x, y; unsigned char tag[4]; struct tagPoint *next; } Point, *LPPOINT;
+			typedef unsigned long long Handle;
+			enum Flags { A = 1 };
+		"#).unwrap();
+		let color = match items[0] {
+			Item::Typedef(Typedef { target: Target::Enum(ref color), ref names, .. }) => {
+				assert_eq!(names, &[Declarator { name: String::from("Color"), pointers: 0 }]);
+				color
+			},
+			_ => panic!(),
+		};
+		let values: Vec<_> = color.variants.iter().map(|variant| (variant.name.as_str(), variant.value)).collect();
+		assert_eq!(values, [("Red", 0), ("Green", 16), ("Blue", 17), ("Mask", 17), ("Shift", 16), ("Neg", -1)]);
+
+		match items[1] {
+			Item::Typedef(Typedef { target: Target::Struct(ref point), ref names, .. }) => {
+				assert_eq!(point.tag.as_deref(), Some("tagPoint"));
+				let fields: Vec<_> = point.fields.iter().map(|field| (field.name.as_str(), field.ty.name.as_str(), field.ty.pointers, field.dims.clone())).collect();
+				assert_eq!(fields, [("x", "long", 0, vec![]), ("y", "long", 0, vec![]), ("tag", "unsigned char", 0, vec![String::from("4")]), ("next", "tagPoint", 1, vec![])]);
+				assert_eq!(names[1], Declarator { name: String::from("LPPOINT"), pointers: 1 });
+			},
+			_ => panic!(),
+		}
+		match items[2] {
+			Item::Typedef(Typedef { target: Target::Type(ref ty), .. }) => assert_eq!(ty, &Type::new("unsigned hyper")),
+			_ => panic!(),
+		}
+		match items[3] {
+			Item::Enum(ref flags) => assert_eq!(flags.tag.as_deref(), Some("Flags")),
+			_ => panic!(),
+		}
+	}
+	#[test]
+	fn errors() {
+		let err = parse("interface IFoo : IUnknown { HRESULT Foo(); };").unwrap_err();
+		assert_eq!(err.to_string(), "1:1: interface `IFoo` is missing its `uuid` attribute");
+		let err = parse("[uuid(12345678-9ABC-DEF0-0123-456789ABCDEF)]\ninterface IFoo : IUnknown {\n\tHRESULT Foo(int a b);\n};").unwrap_err();
+		assert_eq!(err.to_string(), "3:20: expected `,`");
+		let err = parse("enum E { A = B };").unwrap_err();
+		assert_eq!(err.to_string(), "1:14: expected an integer constant, found `B`");
+		let err = parse("union U { long a; };").unwrap_err();
+		assert_eq!(err.to_string(), "1:1: unsupported definition `union`");
+		let err = parse("[uuid(zzz)] interface IFoo : IUnknown {};").unwrap_err();
+		assert_eq!(err.to_string(), "1:10: expected a guid in `uuid`");
+		// Malformed input is reported rather than panicking.
+		let err = parse("importlib );").unwrap_err();
+		assert_eq!(err.to_string(), "1:11: unmatched `)`");
+		let err = parse("cpp_quote(\"a\"];").unwrap_err();
+		assert_eq!(err.to_string(), "1:14: unmatched `]`");
+		let err = parse("enum E { Shift = 1 << 64 };").unwrap_err();
+		assert_eq!(err.to_string(), "1:23: integer constant overflows");
+		let err = parse("enum E { A = 0xffffffff, B };").unwrap_err();
+		assert_eq!(err.to_string(), "1:26: enumerator `B` overflows");
+		let err = parse("enum E { A = 1 << 40 };").unwrap_err();
+		assert_eq!(err.to_string(), "1:10: enumerator `A` overflows");
+		let err = parse("enum E { A = -2147483648 - 1 };").unwrap_err();
+		assert_eq!(err.to_string(), "1:10: enumerator `A` overflows");
+		let err = parse("enum E { A = 3 << 63 };").unwrap_err();
+		assert_eq!(err.to_string(), "1:19: integer constant overflows");
+		assert!(parse("enum E { A = -2147483647 - 1, B = 0xffffffff, C = -1 << 4 };").is_ok());
+		let err = parse("enum E { A = -(-0x7fffffffffffffff - 1) };").unwrap_err();
+		assert_eq!(err.to_string(), "1:39: integer constant overflows");
+		let err = parse("enum E { A = 0x7fffffffffffffff + 1 };").unwrap_err();
+		assert_eq!(err.to_string(), "1:35: integer constant overflows");
+	}
+}
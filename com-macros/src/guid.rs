@@ -0,0 +1,99 @@
+/*!
+Compile time GUID parsing.
+*/
+
+use ::proc_macro2::{Span, TokenStream, TokenTree};
+use ::syn::LitStr;
+
+/// Expands `$crate, "literal"` to a `$crate::GUID` struct literal.
+pub fn expand(input: TokenStream) -> TokenStream {
+	let mut tokens = input.into_iter();
+	// The path to `com_sys` up to the comma, usually `$crate`.
+	let krate: TokenStream = tokens.by_ref()
+		.take_while(|tt| match *tt { TokenTree::Punct(ref p) => p.as_char() != ',', _ => true })
+		.collect();
+	let lit: LitStr = match ::syn::parse2(tokens.collect()) {
+		Ok(lit) => lit,
+		Err(err) => return compile_error(err.span(), &err.to_string()),
+	};
+	match parse(&lit.value()) {
+		Ok(bytes) => {
+			let (data1, data2, data3, data4) = fields(&bytes);
+			quote! {
+				#krate::GUID {
+					Data1: #data1,
+					Data2: #data2,
+					Data3: #data3,
+					Data4: [#(#data4),*],
+				}
+			}
+		},
+		Err(msg) => compile_error(lit.span(), &msg),
+	}
+}
+
+/// Reports an error at the given span.
+///
+/// Unlike `syn::Error::to_compile_error` this does not rely on `::core` being available in 2015 edition crates.
+pub fn compile_error(span: Span, msg: &str) -> TokenStream {
+	quote_spanned!(span=> compile_error! { #msg })
+}
+
+/// Splits the big-endian bytes into the `GUID` fields.
+pub fn fields(bytes: &[u8; 16]) -> (u32, u16, u16, [u8; 8]) {
+	let mut data4 = [0u8; 8];
+	data4.copy_from_slice(&bytes[8..]);
+	(
+		u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+		u16::from_be_bytes([bytes[4], bytes[5]]),
+		u16::from_be_bytes([bytes[6], bytes[7]]),
+		data4,
+	)
+}
+
+/// Parses a GUID in registry form, with or without braces, into its big-endian bytes.
+pub fn parse(s: &str) -> Result<[u8; 16], String> {
+	let (start, text) = match (s.starts_with('{'), s.ends_with('}')) {
+		(true, true) if s.len() >= 2 => (1, &s[1..s.len() - 1]),
+		(false, false) => (0, s),
+		_ => return Err(String::from("unmatched guid brace")),
+	};
+	if text.len() != 36 {
+		return Err(format!("invalid guid length {}, expected 36 characters or 38 with braces, eg. \"AAAAAAAA-BBBB-CCCC-DDDD-EEEEEEEEEEEE\"", s.len()));
+	}
+	let mut bytes = [0u8; 16];
+	let mut n = 0;
+	for (i, chr) in text.char_indices() {
+		let hyphen = i == 8 || i == 13 || i == 18 || i == 23;
+		let nibble = match chr.to_digit(16) {
+			_ if chr == '-' && hyphen => continue,
+			Some(nibble) if !hyphen && chr.is_ascii() => nibble as u8,
+			_ => {
+				let expected = if hyphen { "a hyphen" } else { "a hex digit" };
+				return Err(format!("invalid guid character {:?} at offset {}, expected {}", chr, start + i, expected));
+			},
+		};
+		bytes[n / 2] |= nibble << if n % 2 == 0 { 4 } else { 0 };
+		n += 1;
+	}
+	Ok(bytes)
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn units() {
+		let bytes = [0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE, 0xF0, 0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF];
+		assert_eq!(parse("12345678-9ABC-DEF0-0123-456789ABCDEF"), Ok(bytes));
+		assert_eq!(parse("{12345678-9abc-def0-0123-456789abcdef}"), Ok(bytes));
+
+		assert_eq!(parse("{12345678-9ABC-DEF0-0123-456789ABCDEF"), Err(String::from("unmatched guid brace")));
+		assert!(parse("12345678-9ABC-DEF0-0123-456789ABCDE").unwrap_err().starts_with("invalid guid length 35"));
+		assert!(parse("{12345678-9ABC-DEF0-0123+456789ABCDEF}").unwrap_err().starts_with("invalid guid character '+' at offset 24, expected a hyphen"));
+		assert!(parse("12345678-9ABC-DEF0-0123-456789ABCDEX").unwrap_err().starts_with("invalid guid character 'X' at offset 35, expected a hex digit"));
+	}
+}
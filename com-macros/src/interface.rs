@@ -0,0 +1,417 @@
+/*!
+The `#[com_interface]` attribute.
+*/
+
+use ::proc_macro2::{Span, TokenStream};
+use ::syn::{self, FnArg, Ident, ItemTrait, LitStr, Pat, Path, ReturnType, TraitItem, TraitItemFn, Type, TypeParamBound};
+use ::syn::spanned::Spanned;
+
+use ::guid;
+use ::wrapper;
+
+/// Virtual function parsed from a trait method.
+pub struct Method {
+	pub attrs: Vec<syn::Attribute>,
+	pub unsafety: Option<syn::token::Unsafe>,
+	pub ident: Ident,
+	pub args: Vec<Arg>,
+	pub output: ReturnType,
+}
+
+/// Virtual function argument.
+pub struct Arg {
+	pub ident: Ident,
+	pub ty: Type,
+	/// Marked `#[out]` or `#[retval]`, returned by the wrapper.
+	pub out: bool,
+	/// Interface pointer marked `#[interface]`, taken and returned as its com pointer by the wrapper.
+	pub interface: bool,
+	/// Element type of a `SAFEARRAY` given by `#[array(T)]`.
+	pub array: Option<Type>,
+}
+
+pub fn expand(attr: TokenStream, item: TokenStream) -> TokenStream {
+	let lit: LitStr = match syn::parse2(attr) {
+		Ok(lit) => lit,
+		Err(err) => return guid::compile_error(err.span(), &format!("{}, expected the interface id as a string literal, eg. `#[com_interface(\"AAAAAAAA-BBBB-CCCC-DDDD-EEEEEEEEEEEE\")]`", err)),
+	};
+	let iid = match guid::parse(&lit.value()) {
+		Ok(bytes) => bytes,
+		Err(msg) => return guid::compile_error(lit.span(), &msg),
+	};
+	let item: ItemTrait = match syn::parse2(item) {
+		Ok(item) => item,
+		Err(err) => return guid::compile_error(err.span(), &err.to_string()),
+	};
+	match generate(&iid, &item) {
+		Ok(tokens) => tokens,
+		Err((span, msg)) => guid::compile_error(span, &msg),
+	}
+}
+
+fn generate(iid: &[u8; 16], item: &ItemTrait) -> Result<TokenStream, (Span, String)> {
+	if !item.generics.params.is_empty() || item.generics.where_clause.is_some() {
+		return Err((item.generics.span(), String::from("com interfaces cannot be generic")));
+	}
+	let base = base(item)?;
+	let methods = item.items.iter().map(method).collect::<Result<Vec<_>, _>>()?;
+
+	let vis = &item.vis;
+	// Derives only apply to the com pointer, the other attributes to all of the generated types.
+	let (derives, attrs): (Vec<_>, Vec<_>) = item.attrs.iter().partition(|attr| attr.path().is_ident("derive"));
+	let iface = &item.ident;
+	let iface_vtbl = Ident::new(&format!("{}Vtbl", iface), iface.span());
+	let iface_ptr = Ident::new(&format!("{}Ptr", iface), iface.span());
+
+	let (data1, data2, data3, data4) = guid::fields(iid);
+	let iid = quote!(::com_types::Guid::new(#data1, #data2, #data3, [#(#data4),*]));
+
+	let fields = methods.iter().map(|m| {
+		let attrs = &m.attrs;
+		let ident = &m.ident;
+		let args = m.args.iter().map(|arg| { let (name, ty) = (&arg.ident, &arg.ty); quote!(#name: #ty) });
+		let output = &m.output;
+		quote! {
+			#(#attrs)*
+			pub #ident: unsafe extern "system" fn(This: *mut #iface, #(#args),*) #output,
+		}
+	});
+	let shims: Vec<_> = methods.iter().map(|m| {
+		let attrs = &m.attrs;
+		let ident = &m.ident;
+		let args = m.args.iter().map(|arg| { let (name, ty) = (&arg.ident, &arg.ty); quote!(#name: #ty) });
+		let names = m.args.iter().map(|arg| &arg.ident);
+		let output = &m.output;
+		// Pointers, also behind aliases like `BSTR` or `REFIID`, cannot be checked, the caller is responsible for them.
+		let unsafety = if m.unsafety.is_some() || !m.args.iter().all(|arg| is_plain_data(&arg.ty)) {
+			Some(quote!(unsafe))
+		}
+		else {
+			None
+		};
+		quote! {
+			#(#attrs)*
+			#[allow(non_snake_case, unused_unsafe)]
+			#[inline]
+			pub #unsafety fn #ident(&self, #(#args),*) #output {
+				unsafe {
+					let this = ::com_types::com::AsComPtr::as_ptr(self);
+					(::com_types::ComInterface::vtbl(&*this).#ident)(this, #(#names),*)
+				}
+			}
+		}
+	}).collect();
+	let wrappers: Vec<_> = methods.iter().map(wrapper::generate).collect();
+	let slot_names: Vec<_> = methods.iter().map(|m| m.ident.to_string()).collect();
+	// The methods are also available on the interface itself, eg. through `ComRc<#iface>`.
+	let iface_methods = quote! {
+		impl ::com_types::com::AsComPtr for #iface {
+			type Interface = #iface;
+			#[inline(always)] fn as_ptr(&self) -> *mut #iface { self as *const #iface as *mut #iface }
+		}
+		impl #iface {
+			#(#shims)*
+			#(#wrappers)*
+		}
+	};
+
+	let tokens = match base {
+		Some(ibase) => {
+			let ibase_vtbl = sibling(&ibase, "Vtbl");
+			let ibase_ptr = quote!(<#ibase as ::com_types::com::ComInterfacePtr>::Ptr);
+			quote! {
+				#[repr(C)]
+				#[allow(non_snake_case)]
+				#vis struct #iface_vtbl<#iface: ::com_types::ComInterface> {
+					pub base: #ibase_vtbl<#iface>,
+					#(#fields)*
+				}
+				impl<#iface: ::com_types::ComInterface> ::com_types::ComVtbl for #iface_vtbl<#iface> {
+					const METHODS: &'static [&'static str] = &[#(#slot_names),*];
+					const SLOTS: usize = <#ibase_vtbl<#iface> as ::com_types::ComVtbl>::SLOTS + Self::METHODS.len();
+					fn slot_names() -> Vec<&'static str> {
+						let mut names = <#ibase_vtbl<#iface> as ::com_types::ComVtbl>::slot_names();
+						names.extend_from_slice(Self::METHODS);
+						names
+					}
+					fn slot(name: &str) -> Option<usize> {
+						let base = <#ibase_vtbl<#iface> as ::com_types::ComVtbl>::SLOTS;
+						<#ibase_vtbl<#iface> as ::com_types::ComVtbl>::slot(name).or_else(|| Self::METHODS.iter().position(|&method| method == name).map(|index| base + index))
+					}
+				}
+				impl<#iface: ::com_types::ComInterface> ::std::ops::Deref for #iface_vtbl<#iface> {
+					type Target = #ibase_vtbl<#iface>;
+					#[inline(always)] fn deref(&self) -> &#ibase_vtbl<#iface> { &self.base }
+				}
+
+				#(#attrs)*
+				#[repr(C)]
+				#vis struct #iface { vtbl: *const #iface_vtbl<#iface> }
+				impl ::com_types::ComInterface for #iface {
+					type Vtbl = #iface_vtbl<#iface>;
+					const IID: ::com_types::Guid = #iid;
+					#[inline(always)] fn vtbl(&self) -> &Self::Vtbl { unsafe { &*self.vtbl } }
+					#[inline]
+					fn matches_iid(iid: &::com_types::GUID) -> bool {
+						*::com_types::Guid::from_raw(iid) == Self::IID || <#ibase as ::com_types::ComInterface>::matches_iid(iid)
+					}
+				}
+				impl ::com_types::ComInherit for #iface {
+					type Super = #ibase;
+				}
+				impl ::com_types::ComExtends<#iface> for #iface {}
+				impl<B: ::com_types::ComInterface> ::com_types::ComExtends<B> for #iface where #ibase: ::com_types::ComExtends<B> {}
+				#iface_methods
+
+				#(#attrs)*
+				#(#derives)*
+				#[repr(transparent)]
+				#vis struct #iface_ptr(#ibase_ptr);
+				impl ::com_types::com::AsComPtr for #iface_ptr {
+					type Interface = #iface;
+					#[inline(always)]
+					fn as_ptr(&self) -> *mut #iface { ::com_types::com::AsComPtr::as_ptr(&self.0) as *mut #iface }
+				}
+				impl ::com_types::com::ComPtr for #iface_ptr {
+					#[inline(always)]
+					unsafe fn new(iface: ::com_types::LPVOID) -> Self {
+						debug_assert!(!iface.is_null());
+						#iface_ptr(<#ibase_ptr as ::com_types::com::ComPtr>::new(iface))
+					}
+					#[inline(always)]
+					unsafe fn from_ptr(iface: *mut #iface) -> Self {
+						<Self as ::com_types::com::ComPtr>::new(iface as ::com_types::LPVOID)
+					}
+				}
+				impl ::com_types::com::ComCast<#iface_ptr> for #iface_ptr {
+					#[inline(always)] fn cast(&self) -> &#iface_ptr { self }
+				}
+				// Bounding on the base interface rather than only its com pointer keeps this apart from the identity cast.
+				impl<T: ::com_types::com::ComPtr> ::com_types::com::ComCast<T> for #iface_ptr
+					where #ibase_ptr: ::com_types::com::ComCast<T>, #ibase: ::com_types::ComExtends<T::Interface>
+				{
+					#[inline(always)] fn cast(&self) -> &T { ::com_types::com::ComCast::cast(&self.0) }
+				}
+				impl ::std::ops::Deref for #iface_ptr {
+					type Target = #ibase_ptr;
+					fn deref(&self) -> &#ibase_ptr { &self.0 }
+				}
+				impl ::com_types::com::ComInterfacePtr for #iface {
+					type Ptr = #iface_ptr;
+				}
+				impl #iface_ptr {
+					#(#shims)*
+					#(#wrappers)*
+				}
+			}
+		},
+		None => {
+			quote! {
+				#[repr(C)]
+				#[allow(non_snake_case)]
+				#vis struct #iface_vtbl<#iface: ::com_types::ComInterface> {
+					#(#fields)*
+				}
+				impl<#iface: ::com_types::ComInterface> ::com_types::ComVtbl for #iface_vtbl<#iface> {
+					const METHODS: &'static [&'static str] = &[#(#slot_names),*];
+					const SLOTS: usize = Self::METHODS.len();
+				}
+
+				#(#attrs)*
+				#[repr(C)]
+				#vis struct #iface { vtbl: *const #iface_vtbl<#iface> }
+				impl ::com_types::ComInterface for #iface {
+					type Vtbl = #iface_vtbl<#iface>;
+					const IID: ::com_types::Guid = #iid;
+					#[inline(always)] fn vtbl(&self) -> &Self::Vtbl { unsafe { &*self.vtbl } }
+				}
+				impl ::com_types::ComExtends<#iface> for #iface {}
+				#iface_methods
+
+				#(#attrs)*
+				#(#derives)*
+				#[repr(transparent)]
+				#vis struct #iface_ptr(*mut #iface);
+				impl ::com_types::com::AsComPtr for #iface_ptr {
+					type Interface = #iface;
+					#[inline(always)] fn as_ptr(&self) -> *mut #iface { self.0 }
+				}
+				impl ::com_types::com::ComPtr for #iface_ptr {
+					#[inline(always)]
+					unsafe fn new(iface: ::com_types::LPVOID) -> Self {
+						<Self as ::com_types::com::ComPtr>::from_ptr(iface as *mut #iface)
+					}
+					#[inline(always)]
+					unsafe fn from_ptr(iface: *mut #iface) -> Self {
+						debug_assert!(!iface.is_null());
+						#iface_ptr(iface)
+					}
+				}
+				impl ::com_types::com::ComCast<#iface_ptr> for #iface_ptr {
+					#[inline(always)] fn cast(&self) -> &#iface_ptr { self }
+				}
+				impl ::com_types::com::ComInterfacePtr for #iface {
+					type Ptr = #iface_ptr;
+				}
+				impl #iface_ptr {
+					#(#shims)*
+					#(#wrappers)*
+				}
+			}
+		},
+	};
+	Ok(tokens)
+}
+
+/// The base interface is the one supertrait.
+fn base(item: &ItemTrait) -> Result<Option<Path>, (Span, String)> {
+	let mut bases = item.supertraits.iter();
+	let base = match bases.next() {
+		Some(TypeParamBound::Trait(bound)) if bound.lifetimes.is_none() => bound.path.clone(),
+		Some(bound) => return Err((bound.span(), String::from("expected the base interface"))),
+		None => return Ok(None),
+	};
+	if let Some(bound) = bases.next() {
+		return Err((bound.span(), String::from("com interfaces inherit from a single base interface")));
+	}
+	if base.segments.iter().any(|seg| !seg.arguments.is_empty()) {
+		return Err((base.span(), String::from("com interfaces cannot be generic")));
+	}
+	Ok(Some(base))
+}
+
+pub fn method(item: &TraitItem) -> Result<Method, (Span, String)> {
+	let m: &TraitItemFn = match *item {
+		TraitItem::Fn(ref m) => m,
+		ref item => return Err((item.span(), String::from("com interfaces only contain virtual functions"))),
+	};
+	let sig = &m.sig;
+	if let Some(ref body) = m.default {
+		return Err((body.span(), String::from("virtual functions cannot have a default implementation")));
+	}
+	if !sig.generics.params.is_empty() || sig.asyncness.is_some() || sig.constness.is_some() || sig.abi.is_some() || sig.variadic.is_some() {
+		return Err((sig.span(), String::from("virtual functions are plain `fn`s")));
+	}
+	let mut inputs = sig.inputs.iter();
+	match inputs.next() {
+		Some(FnArg::Receiver(recv)) if recv.reference.is_some() && recv.mutability.is_none() && recv.colon_token.is_none() => (),
+		_ => return Err((sig.ident.span(), String::from("virtual functions take `&self` as their first argument"))),
+	}
+	let args = inputs.map(|arg| match *arg {
+		FnArg::Typed(ref arg) => match *arg.pat {
+			Pat::Ident(ref pat) if pat.by_ref.is_none() && pat.mutability.is_none() && pat.subpat.is_none() => {
+				let mut out = false;
+				let mut interface = false;
+				let mut array = None;
+				for attr in &arg.attrs {
+					if attr.path().is_ident("out") || attr.path().is_ident("retval") {
+						attr.meta.require_path_only().map_err(|err| (err.span(), err.to_string()))?;
+						out = true;
+					}
+					else if attr.path().is_ident("interface") {
+						attr.meta.require_path_only().map_err(|err| (err.span(), err.to_string()))?;
+						interface = true;
+					}
+					else if attr.path().is_ident("array") {
+						array = Some(attr.parse_args::<Type>().map_err(|err| (err.span(), err.to_string()))?);
+					}
+					else {
+						return Err((attr.span(), String::from("expected `#[out]`, `#[retval]`, `#[interface]` or `#[array(T)]`")));
+					}
+				}
+				Ok(Arg { ident: pat.ident.clone(), ty: (*arg.ty).clone(), out, interface, array })
+			},
+			ref pat => Err((pat.span(), String::from("expected an argument name"))),
+		},
+		FnArg::Receiver(ref recv) => Err((recv.span(), String::from("unexpected receiver"))),
+	}).collect::<Result<Vec<_>, _>>()?;
+	// The wrapper returns the trailing out arguments.
+	if let Some(pos) = args.iter().position(|arg| arg.out) {
+		if let Some(arg) = args[pos..].iter().find(|arg| !arg.out) {
+			return Err((arg.ident.span(), String::from("out arguments must come last")));
+		}
+	}
+	if let Some(arg) = args.iter().find(|arg| arg.out && pointee(&arg.ty).map(|(mutable, _)| !mutable).unwrap_or(true)) {
+		return Err((arg.ty.span(), String::from("out arguments must be `*mut` pointers")));
+	}
+	// Interfaces are passed as `*mut I` and returned as `*mut *mut I`.
+	if let Some(arg) = args.iter().find(|arg| arg.interface && pointee(&arg.ty).and_then(|(_, elem)| if arg.out { pointee(elem) } else { Some((true, elem)) }).is_none()) {
+		let msg = if arg.out { "out interface arguments must be `*mut *mut I` pointers" } else { "interface arguments must be `*mut I` pointers" };
+		return Err((arg.ty.span(), String::from(msg)));
+	}
+	Ok(Method {
+		attrs: m.attrs.clone(),
+		unsafety: sig.unsafety,
+		ident: sig.ident.clone(),
+		args,
+		output: sig.output.clone(),
+	})
+}
+
+/// Path to the type next to `path` named with `suffix` appended, eg. its vtbl.
+fn sibling(path: &Path, suffix: &str) -> Path {
+	let mut path = path.clone();
+	if let Some(last) = path.segments.last_mut() {
+		last.ident = Ident::new(&format!("{}{}", last.ident, suffix), last.ident.span());
+	}
+	path
+}
+
+/// Types without pointers, passing any of their values is safe.
+const PLAIN_DATA: &[&str] = &[
+	"bool", "char", "i8", "u8", "i16", "u16", "i32", "u32", "i64", "u64", "i128", "u128", "isize", "usize", "f32", "f64",
+	"CHAR", "UCHAR", "BYTE", "WCHAR", "OLECHAR", "SHORT", "USHORT", "WORD", "INT", "UINT", "LONG", "ULONG", "DWORD",
+	"LONGLONG", "ULONGLONG", "LONG64", "ULONG64", "DWORD64", "INT64", "UINT64", "FLOAT", "DOUBLE",
+	"BOOL", "BOOLEAN", "VARIANT_BOOL", "HRESULT", "SCODE", "LCID", "LANGID", "DISPID", "MEMBERID", "VARTYPE",
+	"DATE", "CY", "DECIMAL", "GUID", "IID", "CLSID",
+];
+
+/// Returns if the type is known to be plain data, other types may hide pointers.
+pub fn is_plain_data(ty: &Type) -> bool {
+	match *ty {
+		Type::Path(ref path) if path.qself.is_none() && path.path.segments.iter().all(|seg| seg.arguments.is_empty()) => {
+			let name = path.path.segments.last().map(|seg| seg.ident.to_string());
+			name.map(|name| PLAIN_DATA.contains(&&*name)).unwrap_or(false)
+		},
+		Type::Group(ref ty) => is_plain_data(&ty.elem),
+		Type::Paren(ref ty) => is_plain_data(&ty.elem),
+		_ => false,
+	}
+}
+
+/// Returns if the raw pointer is `*mut` and the type it points to.
+pub fn pointee(ty: &Type) -> Option<(bool, &Type)> {
+	match *ty {
+		Type::Ptr(ref ptr) => Some((ptr.mutability.is_some(), &ptr.elem)),
+		Type::Group(ref ty) => pointee(&ty.elem),
+		Type::Paren(ref ty) => pointee(&ty.elem),
+		_ => None,
+	}
+}
+
+/// Returns the name of a type path, eg. `BSTR` for `::winapi::BSTR`.
+pub fn type_name(ty: &Type) -> Option<&Ident> {
+	match *ty {
+		Type::Path(ref path) if path.qself.is_none() => path.path.segments.last().map(|seg| &seg.ident),
+		Type::Group(ref ty) => type_name(&ty.elem),
+		Type::Paren(ref ty) => type_name(&ty.elem),
+		_ => None,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::is_plain_data;
+
+	#[test]
+	fn plain_data() {
+		assert!(is_plain_data(&::syn::parse_quote!(i32)));
+		assert!(is_plain_data(&::syn::parse_quote!(::winapi::DWORD)));
+		assert!(is_plain_data(&::syn::parse_quote!(GUID)));
+		assert!(!is_plain_data(&::syn::parse_quote!(*mut i32)));
+		assert!(!is_plain_data(&::syn::parse_quote!(BSTR)));
+		assert!(!is_plain_data(&::syn::parse_quote!(LPVOID)));
+		assert!(!is_plain_data(&::syn::parse_quote!(REFIID)));
+		assert!(!is_plain_data(&::syn::parse_quote!(Option<i32>)));
+	}
+}
@@ -0,0 +1,43 @@
+/*!
+Procedural macros for the COM FFI bits.
+
+These are implementation details of `com-sys`, use the macros it exports instead.
+*/
+
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use]
+extern crate quote;
+extern crate syn;
+
+use proc_macro::TokenStream;
+
+mod guid;
+mod interface;
+mod record;
+mod wrapper;
+
+/// Parses a GUID string literal at compile time.
+///
+/// Invoked by `com_sys::guid!` as `__guid!($crate, "...")`, expands to a `$crate::GUID` struct literal.
+#[doc(hidden)]
+#[proc_macro]
+pub fn __guid(input: TokenStream) -> TokenStream {
+	guid::expand(input.into()).into()
+}
+
+/// Defines a COM interface from a trait.
+///
+/// Re-exported by `com-types`, see its documentation.
+#[proc_macro_attribute]
+pub fn com_interface(attr: TokenStream, item: TokenStream) -> TokenStream {
+	interface::expand(attr.into(), item.into()).into()
+}
+
+/// Maps a `#[repr(C)]` struct to a COM record.
+///
+/// Re-exported by `com-types`, see its documentation.
+#[proc_macro_derive(RecordType, attributes(record))]
+pub fn record_type(input: TokenStream) -> TokenStream {
+	record::expand(input.into()).into()
+}
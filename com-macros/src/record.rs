@@ -0,0 +1,105 @@
+/*!
+The `#[derive(RecordType)]` macro.
+*/
+
+use ::proc_macro2::{Span, TokenStream};
+use ::syn::{self, Data, DeriveInput, Fields, LitStr};
+use ::syn::spanned::Spanned;
+
+use ::guid;
+
+pub fn expand(input: TokenStream) -> TokenStream {
+	let input: DeriveInput = match syn::parse2(input) {
+		Ok(input) => input,
+		Err(err) => return guid::compile_error(err.span(), &err.to_string()),
+	};
+	match generate(&input) {
+		Ok(tokens) => tokens,
+		Err((span, msg)) => guid::compile_error(span, &msg),
+	}
+}
+
+fn generate(input: &DeriveInput) -> Result<TokenStream, (Span, String)> {
+	if !input.generics.params.is_empty() || input.generics.where_clause.is_some() {
+		return Err((input.generics.span(), String::from("records cannot be generic")));
+	}
+	if !repr_c(input) {
+		return Err((input.ident.span(), String::from("records must be `#[repr(C)]`")));
+	}
+	let guid = record_guid(input)?;
+	let fields = match input.data {
+		Data::Struct(ref data) => match data.fields {
+			Fields::Named(ref fields) => &fields.named,
+			_ => return Err((input.ident.span(), String::from("records must have named fields"))),
+		},
+		_ => return Err((input.ident.span(), String::from("records must be structs"))),
+	};
+
+	let ident = &input.ident;
+	let name = ident.to_string();
+	let (data1, data2, data3, data4) = guid::fields(&guid);
+	let fields = fields.iter().map(|field| {
+		let ident = field.ident.as_ref().unwrap();
+		let name = ident.to_string();
+		let ty = &field.ty;
+		quote! {
+			::com_types::record::Field {
+				name: #name,
+				get: |this| ::com_types::variant::Variant::from(::std::clone::Clone::clone(&this.#ident)),
+				put: |this, value| {
+					this.#ident = ::com_types::variant::Variant::try_into::<#ty>(value)?;
+					Ok(())
+				},
+			}
+		}
+	});
+	Ok(quote! {
+		unsafe impl ::com_types::record::RecordType for #ident {
+			const GUID: ::com_types::Guid = ::com_types::Guid::new(#data1, #data2, #data3, [#(#data4),*]);
+			const NAME: &'static str = #name;
+			const FIELDS: &'static [::com_types::record::Field<Self>] = &[#(#fields),*];
+		}
+	})
+}
+
+/// Returns if the struct is `#[repr(C)]`, possibly among other representation hints.
+fn repr_c(input: &DeriveInput) -> bool {
+	input.attrs.iter()
+		.filter(|attr| attr.path().is_ident("repr"))
+		.any(|attr| {
+			let mut c = false;
+			let _ = attr.parse_nested_meta(|meta| {
+				c |= meta.path.is_ident("C");
+				Ok(())
+			});
+			c
+		})
+}
+
+/// Parses the `#[record("...")]` attribute.
+fn record_guid(input: &DeriveInput) -> Result<[u8; 16], (Span, String)> {
+	let attr = match input.attrs.iter().find(|attr| attr.path().is_ident("record")) {
+		Some(attr) => attr,
+		None => return Err((input.ident.span(), String::from("expected the record's guid as `#[record(\"AAAAAAAA-BBBB-CCCC-DDDD-EEEEEEEEEEEE\")]`"))),
+	};
+	let lit: LitStr = attr.parse_args().map_err(|err| (err.span(), format!("{}, expected the record's guid as a string literal", err)))?;
+	guid::parse(&lit.value()).map_err(|msg| (lit.span(), msg))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::generate;
+
+	fn error(input: ::syn::DeriveInput) -> String {
+		generate(&input).err().unwrap().1
+	}
+
+	#[test]
+	fn errors() {
+		assert_eq!(error(::syn::parse_quote!(#[record("5b1f6a3e-92c4-4d7b-8e05-c3a91f2d6b70")] struct S { x: i32 })), "records must be `#[repr(C)]`");
+		assert_eq!(error(::syn::parse_quote!(#[repr(C)] struct S { x: i32 })), "expected the record's guid as `#[record(\"AAAAAAAA-BBBB-CCCC-DDDD-EEEEEEEEEEEE\")]`");
+		assert_eq!(error(::syn::parse_quote!(#[repr(C)] #[record("5b1f6a3e-92c4-4d7b-8e05-c3a91f2d6b70")] struct S(i32);)), "records must have named fields");
+		assert_eq!(error(::syn::parse_quote!(#[repr(C)] struct S<T> { x: T })), "records cannot be generic");
+		assert!(generate(&::syn::parse_quote!(#[repr(C, align(8))] #[record("5b1f6a3e-92c4-4d7b-8e05-c3a91f2d6b70")] struct S { x: i32 })).is_ok());
+	}
+}
@@ -0,0 +1,270 @@
+/*!
+Safe wrappers around the virtual functions of `#[com_interface]`.
+*/
+
+use ::proc_macro2::TokenStream;
+use ::syn::{Ident, ReturnType, Type};
+
+use ::interface::{Arg, Method, is_plain_data, pointee, type_name};
+
+/// Generates the `ComResult` returning wrapper for a virtual function returning `HRESULT`.
+///
+/// Named after the virtual function in snake case, nothing is generated if that is the name of its shim.
+pub fn generate(m: &Method) -> Option<TokenStream> {
+	match m.output {
+		ReturnType::Type(_, ref ty) if is_named(ty, "HRESULT") => (),
+		_ => return None,
+	}
+	let name = snake_case(&m.ident.to_string());
+	if m.ident == name {
+		return None;
+	}
+	let attrs = &m.attrs;
+	let ident = &m.ident;
+	let wrapper = Ident::new(&name, m.ident.span());
+
+	let mut unsafety = m.unsafety.is_some();
+	let mut params = Vec::new();
+	let mut locals = Vec::new();
+	let mut passes = Vec::new();
+	let mut types = Vec::new();
+	let mut results = Vec::new();
+	for arg in &m.args {
+		if arg.out {
+			let name = &arg.ident;
+			let out = out_arg(arg);
+			let (local, init) = (out.local, out.init);
+			locals.push(quote!(let mut #name: #local = #init;));
+			passes.push(out.pass);
+			types.push(out.ty);
+			results.push(out.result);
+		}
+		else {
+			let (param, pass) = in_arg(arg, &mut unsafety);
+			let name = &arg.ident;
+			params.push(quote!(#name: #param));
+			passes.push(pass);
+		}
+	}
+
+	let (ty, result) = match types.len() {
+		1 => (types.remove(0), results.remove(0)),
+		_ => (quote!((#(#types),*)), quote!((#(#results),*))),
+	};
+	let unsafety = if unsafety { Some(quote!(unsafe)) } else { None };
+	Some(quote! {
+		#(#attrs)*
+		#[allow(non_snake_case, unused_mut, unused_unsafe)]
+		#[inline]
+		pub #unsafety fn #wrapper(&self, #(#params),*) -> ::com_types::com::ComResult<#ty> {
+			unsafe {
+				#(#locals)*
+				let this = ::com_types::com::AsComPtr::as_ptr(self);
+				let hr = (::com_types::ComInterface::vtbl(&*this).#ident)(this, #(#passes),*);
+				::com_types::hr::HResult::result(hr, ())?;
+				Ok(#result)
+			}
+		}
+	})
+}
+
+/// Maps an in argument to the wrapper's parameter type and the expression passing it to the virtual function.
+fn in_arg(arg: &Arg, unsafety: &mut bool) -> (TokenStream, TokenStream) {
+	let name = &arg.ident;
+	let ty = &arg.ty;
+	if is_named(ty, "BSTR") {
+		return (quote!(&::com_types::bstr::BStr), quote!(::com_types::bstr::AsRawBStr::as_raw(#name)));
+	}
+	if is_named(ty, "VARIANT") || is_named(ty, "VARIANTARG") {
+		// Passed by value, the caller keeps ownership.
+		return (quote!(&::com_types::variant::Variant), quote!(::std::ptr::read(#name.as_ptr())));
+	}
+	let (mutable, elem) = match pointee(ty) {
+		Some(ptr) => ptr,
+		None => {
+			// Like the shims, aliases may hide pointers.
+			if !is_plain_data(ty) {
+				*unsafety = true;
+			}
+			return (quote!(#ty), quote!(#name));
+		},
+	};
+	if is_named(elem, "VARIANT") || is_named(elem, "VARIANTARG") {
+		return if mutable {
+			(quote!(&mut ::com_types::variant::Variant), quote!(#name.as_mut_ptr()))
+		}
+		else {
+			(quote!(&::com_types::variant::Variant), quote!(#name.as_ptr()))
+		};
+	}
+	if is_named(elem, "SAFEARRAY") {
+		let arr = match arg.array {
+			Some(ref elem) => quote!(::com_types::array::TypedArr<#elem>),
+			None => quote!(::com_types::array::SafeArr),
+		};
+		return if mutable {
+			(quote!(&mut #arr), quote!(::com_types::array::AsRawArray::as_raw_mut(<#arr as ::std::convert::AsMut<::com_types::array::SafeArr>>::as_mut(#name))))
+		}
+		else {
+			(quote!(&#arr), quote!(::com_types::array::AsRawArray::as_raw(<#arr as ::std::convert::AsRef<::com_types::array::SafeArr>>::as_ref(#name))))
+		};
+	}
+	if arg.interface {
+		return (quote!(&<#elem as ::com_types::com::ComInterfacePtr>::Ptr), quote!(::com_types::com::AsComPtr::as_ptr(#name)));
+	}
+	// Raw pointers cannot be checked, the caller is responsible for them.
+	*unsafety = true;
+	(quote!(#ty), quote!(#name))
+}
+
+/// Out argument of the wrapper.
+struct Out {
+	/// Type of the local receiving the value.
+	local: TokenStream,
+	/// Initializer of the local.
+	init: TokenStream,
+	/// Expression passing the local to the virtual function.
+	pass: TokenStream,
+	/// Type returned by the wrapper.
+	ty: TokenStream,
+	/// Expression returning the local.
+	result: TokenStream,
+}
+
+/// Maps an out argument to a local passed to the virtual function and returned from the wrapper.
+fn out_arg(arg: &Arg) -> Out {
+	let name = &arg.ident;
+	let elem = match pointee(&arg.ty) {
+		Some((_, elem)) => elem,
+		None => unreachable!(),
+	};
+	let null_mut = quote!(::std::ptr::null_mut());
+	let pass = quote!(&mut #name);
+	if is_named(elem, "BSTR") {
+		return Out {
+			local: quote!(#elem),
+			init: null_mut,
+			pass,
+			ty: quote!(::com_types::bstr::BString),
+			result: quote!(::com_types::bstr::BString::from_raw(#name)),
+		};
+	}
+	if is_named(elem, "VARIANT") || is_named(elem, "VARIANTARG") {
+		let variant = quote!(::com_types::variant::Variant<'static>);
+		return Out {
+			local: variant.clone(),
+			init: quote!(::com_types::variant::Variant::new()),
+			pass: quote!(#name.as_mut_ptr()),
+			ty: variant,
+			result: quote!(#name),
+		};
+	}
+	if let Some((_, inner)) = pointee(elem) {
+		// Succeeding without returning anything is a broken implementation.
+		let null = quote!(if #name.is_null() { return Err(::com_types::hr::code::E_POINTER); });
+		let ty = if is_named(inner, "SAFEARRAY") {
+			match arg.array {
+				Some(ref ty) => Some(quote!(::com_types::array::TypedArray<#ty>)),
+				None => Some(quote!(::com_types::array::SafeArray)),
+			}
+		}
+		else if arg.interface {
+			Some(quote!(<#inner as ::com_types::com::ComInterfacePtr>::Ptr))
+		}
+		else {
+			None
+		};
+		if let Some(ty) = ty {
+			let from_raw = if is_named(inner, "SAFEARRAY") {
+				quote!(<#ty>::from_raw(#name))
+			}
+			else {
+				quote!(<#ty as ::com_types::com::ComPtr>::from_ptr(#name))
+			};
+			return Out {
+				local: quote!(#elem),
+				init: null_mut,
+				pass,
+				ty,
+				result: quote!({ #null #from_raw }),
+			};
+		}
+	}
+	// Only read once the virtual function succeeded, which must have written it.
+	Out {
+		local: quote!(::std::mem::MaybeUninit<#elem>),
+		init: quote!(::std::mem::MaybeUninit::uninit()),
+		pass: quote!(#name.as_mut_ptr()),
+		ty: quote!(#elem),
+		result: quote!(#name.assume_init()),
+	}
+}
+
+fn is_named(ty: &Type, name: &str) -> bool {
+	type_name(ty).map(|ident| ident == name).unwrap_or(false)
+}
+
+/// Converts `PascalCase` to `snake_case`, keeping acronyms together, eg. `GetIDsOfNames` becomes `get_ids_of_names`.
+pub fn snake_case(name: &str) -> String {
+	let chars: Vec<char> = name.chars().collect();
+	let mut snake = String::with_capacity(name.len() + 4);
+	for (i, &chr) in chars.iter().enumerate() {
+		if chr.is_uppercase() && i > 0 {
+			let prev = chars[i - 1];
+			let next = chars.get(i + 1).cloned();
+			// The plural `s` of an acronym is not the start of a word.
+			let plural = next == Some('s') && chars.get(i + 2).map(|chr| !chr.is_lowercase()).unwrap_or(true);
+			let word = next.map(|chr| chr.is_lowercase()).unwrap_or(false) && !plural;
+			if prev.is_lowercase() || (prev.is_uppercase() && word) {
+				snake.push('_');
+			}
+		}
+		snake.extend(chr.to_lowercase());
+	}
+	snake
+}
+
+#[cfg(test)]
+mod tests {
+	use ::interface::method;
+	use super::{generate, snake_case};
+
+	fn expand(item: ::syn::TraitItem) -> String {
+		generate(&method(&item).unwrap()).unwrap().to_string()
+	}
+
+	#[test]
+	fn units() {
+		assert_eq!(snake_case("Foo"), "foo");
+		assert_eq!(snake_case("AddOut"), "add_out");
+		assert_eq!(snake_case("GetIDsOfNames"), "get_ids_of_names");
+		assert_eq!(snake_case("GetTypeInfoCount"), "get_type_info_count");
+		assert_eq!(snake_case("QueryInterface"), "query_interface");
+		assert_eq!(snake_case("ParseHTMLDocument"), "parse_html_document");
+		assert_eq!(snake_case("get_Value"), "get_value");
+		assert_eq!(snake_case("Item2D"), "item2d");
+	}
+
+	#[test]
+	fn interfaces() {
+		// Named like an interface, but only `#[interface]` arguments are com pointers.
+		let iid = expand(::syn::parse_quote!(fn GetIID(&self, #[out] pIid: *mut IID) -> HRESULT;));
+		assert!(iid.contains("MaybeUninit"));
+		assert!(!iid.contains("ComInterfacePtr"));
+		assert!(!iid.contains("pub unsafe fn"));
+
+		let out = expand(::syn::parse_quote!(fn Item(&self, #[retval] #[interface] ppItem: *mut *mut IItem) -> HRESULT;));
+		assert!(out.contains("ComInterfacePtr"));
+		assert!(!out.contains("MaybeUninit"));
+		let arg = expand(::syn::parse_quote!(fn Add(&self, #[interface] item: *mut IItem) -> HRESULT;));
+		assert!(arg.contains("ComInterfacePtr"));
+		assert!(!arg.contains("pub unsafe fn"));
+
+		// Aliases may hide pointers.
+		let refiid = expand(::syn::parse_quote!(fn Query(&self, riid: REFIID, #[out] pCount: *mut ULONG) -> HRESULT;));
+		assert!(refiid.contains("pub unsafe fn"));
+
+		assert!(method(&::syn::parse_quote!(fn Add(&self, #[interface] item: IItem) -> HRESULT;)).is_err());
+		assert!(method(&::syn::parse_quote!(fn Item(&self, #[out] #[interface] ppItem: *mut IItem) -> HRESULT;)).is_err());
+	}
+}
@@ -0,0 +1,149 @@
+/*!
+Automation, see `oaidl.h`.
+*/
+
+use ::winapi::{HRESULT, GUID, IID, REFIID, BOOL, WORD, UINT, ULONG, LCID, DISPID, PVOID, BSTR, LPOLESTR, LPCOLESTR, DISPPARAMS, VARIANT, EXCEPINFO};
+use ::unknown::{IUnknown, IUnknownVtbl};
+
+com_interface! {
+	interface IDispatch(IDispatchVtbl): IUnknown(IUnknownVtbl);
+	{0x00020400-0x0000-0x0000-0xC000-0x000000000046}
+	pub GetTypeInfoCount: unsafe extern "system" fn(
+		This: *mut IDispatch,
+		pctinfo: *mut UINT,
+	) -> HRESULT,
+	// Declared as `ITypeInfo **`, type information is not bound so it is passed as its `IUnknown`.
+	pub GetTypeInfo: unsafe extern "system" fn(
+		This: *mut IDispatch,
+		iTInfo: UINT,
+		lcid: LCID,
+		ppTInfo: *mut *mut IUnknown,
+	) -> HRESULT,
+	pub GetIDsOfNames: unsafe extern "system" fn(
+		This: *mut IDispatch,
+		riid: REFIID,
+		rgszNames: *mut LPOLESTR,
+		cNames: UINT,
+		lcid: LCID,
+		rgDispId: *mut DISPID,
+	) -> HRESULT,
+	pub Invoke: unsafe extern "system" fn(
+		This: *mut IDispatch,
+		dispIdMember: DISPID,
+		riid: REFIID,
+		lcid: LCID,
+		wFlags: WORD,
+		pDispParams: *mut DISPPARAMS,
+		pVarResult: *mut VARIANT,
+		pExcepInfo: *mut EXCEPINFO,
+		puArgErr: *mut UINT,
+	) -> HRESULT,
+}
+
+com_interface! {
+	interface IRecordInfo(IRecordInfoVtbl): IUnknown(IUnknownVtbl);
+	{0x0000002F-0x0000-0x0000-0xC000-0x000000000046}
+	pub RecordInit: unsafe extern "system" fn(
+		This: *mut IRecordInfo,
+		pvNew: PVOID,
+	) -> HRESULT,
+	pub RecordClear: unsafe extern "system" fn(
+		This: *mut IRecordInfo,
+		pvExisting: PVOID,
+	) -> HRESULT,
+	pub RecordCopy: unsafe extern "system" fn(
+		This: *mut IRecordInfo,
+		pvExisting: PVOID,
+		pvNew: PVOID,
+	) -> HRESULT,
+	pub GetGuid: unsafe extern "system" fn(
+		This: *mut IRecordInfo,
+		pguid: *mut GUID,
+	) -> HRESULT,
+	pub GetName: unsafe extern "system" fn(
+		This: *mut IRecordInfo,
+		pbstrName: *mut BSTR,
+	) -> HRESULT,
+	pub GetSize: unsafe extern "system" fn(
+		This: *mut IRecordInfo,
+		pcbSize: *mut ULONG,
+	) -> HRESULT,
+	// Declared as `ITypeInfo **`, see `IDispatch::GetTypeInfo`.
+	pub GetTypeInfo: unsafe extern "system" fn(
+		This: *mut IRecordInfo,
+		ppTypeInfo: *mut *mut IUnknown,
+	) -> HRESULT,
+	pub GetField: unsafe extern "system" fn(
+		This: *mut IRecordInfo,
+		pvData: PVOID,
+		szFieldName: LPCOLESTR,
+		pvarField: *mut VARIANT,
+	) -> HRESULT,
+	pub GetFieldNoCopy: unsafe extern "system" fn(
+		This: *mut IRecordInfo,
+		pvData: PVOID,
+		szFieldName: LPCOLESTR,
+		pvarField: *mut VARIANT,
+		ppvDataCArray: *mut PVOID,
+	) -> HRESULT,
+	pub PutField: unsafe extern "system" fn(
+		This: *mut IRecordInfo,
+		wFlags: ULONG,
+		pvData: PVOID,
+		szFieldName: LPCOLESTR,
+		pvarField: *mut VARIANT,
+	) -> HRESULT,
+	pub PutFieldNoCopy: unsafe extern "system" fn(
+		This: *mut IRecordInfo,
+		wFlags: ULONG,
+		pvData: PVOID,
+		szFieldName: LPCOLESTR,
+		pvarField: *mut VARIANT,
+	) -> HRESULT,
+	pub GetFieldNames: unsafe extern "system" fn(
+		This: *mut IRecordInfo,
+		pcNames: *mut ULONG,
+		rgBstrNames: *mut BSTR,
+	) -> HRESULT,
+	pub IsMatchingType: unsafe extern "system" fn(
+		This: *mut IRecordInfo,
+		pRecordInfo: *mut IRecordInfo,
+	) -> BOOL,
+	pub RecordCreate: unsafe extern "system" fn(
+		This: *mut IRecordInfo,
+	) -> PVOID,
+	pub RecordCreateCopy: unsafe extern "system" fn(
+		This: *mut IRecordInfo,
+		pvSource: PVOID,
+		ppvDest: *mut PVOID,
+	) -> HRESULT,
+	pub RecordDestroy: unsafe extern "system" fn(
+		This: *mut IRecordInfo,
+		pvRecord: PVOID,
+	) -> HRESULT,
+}
+
+/// Record held by a `VARIANT` tagged `VT_RECORD`, `__tagBRECORD` in `oaidl.h`.
+#[allow(non_snake_case)]
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct BRECORD {
+	pub pvRecord: PVOID,
+	pub pRecInfo: *mut IRecordInfo,
+}
+
+pub const DISPID_UNKNOWN: DISPID = -1;
+pub const DISPID_VALUE: DISPID = 0;
+pub const DISPID_PROPERTYPUT: DISPID = -3;
+
+pub const DISPATCH_METHOD: WORD = 0x1;
+pub const DISPATCH_PROPERTYGET: WORD = 0x2;
+pub const DISPATCH_PROPERTYPUT: WORD = 0x4;
+pub const DISPATCH_PROPERTYPUTREF: WORD = 0x8;
+
+pub const INVOKE_PROPERTYPUT: ULONG = 0x4;
+
+pub const LOCALE_USER_DEFAULT: LCID = 0x0400;
+
+/// Reserved `riid` argument of `GetIDsOfNames` and `Invoke`.
+pub const IID_NULL: IID = IID { Data1: 0, Data2: 0, Data3: 0, Data4: [0; 8] };
@@ -0,0 +1,215 @@
+/*!
+Globally unique identifiers.
+*/
+
+use ::std::{error, fmt, str};
+
+use ::winapi::GUID;
+
+/// Globally unique identifier.
+///
+/// Layout compatible with the raw `GUID` struct, adding comparison, hashing, formatting and parsing.
+///
+/// Formats in registry form `{AAAAAAAA-BBBB-CCCC-DDDD-EEEEEEEEEEEE}`, parses with or without the braces.
+///
+/// # Examples
+///
+/// ```
+/// use ::com_sys::Guid;
+///
+/// let guid: Guid = "{00000000-0000-0000-C000-000000000046}".parse().unwrap();
+/// assert_eq!(guid, "00000000-0000-0000-c000-000000000046".parse().unwrap());
+/// assert_eq!(guid.to_string(), "{00000000-0000-0000-C000-000000000046}");
+/// ```
+#[derive(Copy, Clone, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[repr(C)]
+pub struct Guid {
+	pub data1: u32,
+	pub data2: u16,
+	pub data3: u16,
+	pub data4: [u8; 8],
+}
+impl Guid {
+	/// Constructs a new instance from its fields.
+	pub const fn new(data1: u32, data2: u16, data3: u16, data4: [u8; 8]) -> Guid {
+		Guid { data1, data2, data3, data4 }
+	}
+	/// Constructs a new instance from the raw `GUID`.
+	///
+	/// Same as `From<GUID>` but usable in const contexts.
+	pub const fn from_guid(guid: GUID) -> Guid {
+		Guid::new(guid.Data1, guid.Data2, guid.Data3, guid.Data4)
+	}
+	/// Views the raw `GUID` as a `Guid`.
+	#[inline]
+	pub fn from_raw(guid: &GUID) -> &Guid {
+		unsafe { &*(guid as *const GUID as *const Guid) }
+	}
+	/// Views the `Guid` as a raw `GUID`.
+	#[inline]
+	pub fn as_raw(&self) -> &GUID {
+		unsafe { &*(self as *const Guid as *const GUID) }
+	}
+	/// Constructs a new instance from its 16-byte little-endian wire form.
+	///
+	/// The first three fields are little-endian, the last 8 bytes are kept as is.
+	pub const fn from_bytes_le(bytes: [u8; 16]) -> Guid {
+		Guid {
+			data1: u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+			data2: u16::from_le_bytes([bytes[4], bytes[5]]),
+			data3: u16::from_le_bytes([bytes[6], bytes[7]]),
+			data4: [bytes[8], bytes[9], bytes[10], bytes[11], bytes[12], bytes[13], bytes[14], bytes[15]],
+		}
+	}
+	/// Returns its 16-byte little-endian wire form.
+	pub const fn to_bytes_le(&self) -> [u8; 16] {
+		let d1 = self.data1.to_le_bytes();
+		let d2 = self.data2.to_le_bytes();
+		let d3 = self.data3.to_le_bytes();
+		let d4 = self.data4;
+		[d1[0], d1[1], d1[2], d1[3], d2[0], d2[1], d3[0], d3[1], d4[0], d4[1], d4[2], d4[3], d4[4], d4[5], d4[6], d4[7]]
+	}
+}
+impl From<GUID> for Guid {
+	fn from(guid: GUID) -> Guid {
+		Guid::from_guid(guid)
+	}
+}
+impl From<Guid> for GUID {
+	fn from(guid: Guid) -> GUID {
+		*guid.as_raw()
+	}
+}
+impl AsRef<GUID> for Guid {
+	fn as_ref(&self) -> &GUID {
+		self.as_raw()
+	}
+}
+
+//----------------------------------------------------------------
+// Formatter
+
+impl fmt::Display for Guid {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let d4 = &self.data4;
+		write!(f, "{{{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}}}",
+			self.data1, self.data2, self.data3, d4[0], d4[1], d4[2], d4[3], d4[4], d4[5], d4[6], d4[7])
+	}
+}
+impl fmt::Debug for Guid {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		fmt::Display::fmt(self, f)
+	}
+}
+
+//----------------------------------------------------------------
+// Parser
+
+/// Error parsing a [`Guid`](struct.Guid.html).
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ParseGuidError {
+	/// Expected 36 characters, or 38 with braces.
+	InvalidLength(usize),
+	/// Unexpected character at the given byte offset, expected a hex digit or a hyphen.
+	InvalidCharacter(usize, char),
+	/// Opening brace without closing brace or the other way around.
+	UnmatchedBrace,
+}
+impl fmt::Display for ParseGuidError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			ParseGuidError::InvalidLength(len) => write!(f, "invalid guid length {}, expected 36 characters or 38 with braces", len),
+			ParseGuidError::InvalidCharacter(offset, chr) => write!(f, "invalid guid character {:?} at offset {}", chr, offset),
+			ParseGuidError::UnmatchedBrace => f.write_str("unmatched guid brace"),
+		}
+	}
+}
+impl error::Error for ParseGuidError {}
+
+impl str::FromStr for Guid {
+	type Err = ParseGuidError;
+	fn from_str(s: &str) -> Result<Guid, ParseGuidError> {
+		let bytes = s.as_bytes();
+		let (start, text) = match (bytes.first(), bytes.last()) {
+			(Some(&b'{'), Some(&b'}')) if bytes.len() >= 2 => (1, &bytes[1..bytes.len() - 1]),
+			(Some(&b'{'), _) | (_, Some(&b'}')) => return Err(ParseGuidError::UnmatchedBrace),
+			_ => (0, bytes),
+		};
+		if text.len() != 36 {
+			return Err(ParseGuidError::InvalidLength(s.len()));
+		}
+		// Every hex digit of the guid as a nibble, big-endian.
+		let mut nibbles = [0u8; 32];
+		let mut n = 0;
+		for (i, &byte) in text.iter().enumerate() {
+			let hyphen = i == 8 || i == 13 || i == 18 || i == 23;
+			let nibble = match byte {
+				b'-' if hyphen => continue,
+				b'0'..=b'9' if !hyphen => byte - b'0',
+				b'a'..=b'f' if !hyphen => byte - b'a' + 10,
+				b'A'..=b'F' if !hyphen => byte - b'A' + 10,
+				_ => {
+					let offset = start + i;
+					let chr = s[offset..].chars().next().unwrap_or('\0');
+					return Err(ParseGuidError::InvalidCharacter(offset, chr));
+				},
+			};
+			nibbles[n] = nibble;
+			n += 1;
+		}
+		let mut be = [0u8; 16];
+		for (byte, pair) in be.iter_mut().zip(nibbles.chunks(2)) {
+			*byte = pair[0] << 4 | pair[1];
+		}
+		Ok(Guid {
+			data1: u32::from_be_bytes([be[0], be[1], be[2], be[3]]),
+			data2: u16::from_be_bytes([be[4], be[5]]),
+			data3: u16::from_be_bytes([be[6], be[7]]),
+			data4: [be[8], be[9], be[10], be[11], be[12], be[13], be[14], be[15]],
+		})
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const IID_IUNKNOWN: Guid = Guid::new(0x00000000, 0x0000, 0x0000, [0xC0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46]);
+	const GUID: Guid = Guid::new(0x12345678, 0x9ABC, 0xDEF0, [0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF]);
+
+	#[test]
+	fn units() {
+		assert_eq!(format!("{}", GUID), "{12345678-9ABC-DEF0-0123-456789ABCDEF}");
+		assert_eq!(format!("{:?}", IID_IUNKNOWN), "{00000000-0000-0000-C000-000000000046}");
+		assert_eq!(Guid::default(), Guid::new(0, 0, 0, [0; 8]));
+		assert!(IID_IUNKNOWN < GUID);
+
+		let raw: GUID = GUID.into();
+		assert_eq!(raw.Data1, 0x12345678);
+		assert_eq!(Guid::from(raw), GUID);
+		assert_eq!(Guid::from_raw(GUID.as_raw()), &GUID);
+	}
+	#[test]
+	fn bytes() {
+		let bytes = [0x78, 0x56, 0x34, 0x12, 0xBC, 0x9A, 0xF0, 0xDE, 0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF];
+		assert_eq!(GUID.to_bytes_le(), bytes);
+		assert_eq!(Guid::from_bytes_le(bytes), GUID);
+	}
+	#[test]
+	fn parse() {
+		assert_eq!("{12345678-9ABC-DEF0-0123-456789ABCDEF}".parse(), Ok(GUID));
+		assert_eq!("12345678-9abc-def0-0123-456789abcdef".parse(), Ok(GUID));
+		assert_eq!(GUID.to_string().parse(), Ok(GUID));
+
+		assert_eq!("".parse::<Guid>(), Err(ParseGuidError::InvalidLength(0)));
+		assert_eq!("{12345678-9ABC-DEF0-0123-456789ABCDEF".parse::<Guid>(), Err(ParseGuidError::UnmatchedBrace));
+		assert_eq!("12345678-9ABC-DEF0-0123-456789ABCDEF}".parse::<Guid>(), Err(ParseGuidError::UnmatchedBrace));
+		assert_eq!("{12345678-9ABC-DEF0-0123-456789ABCDE}".parse::<Guid>(), Err(ParseGuidError::InvalidLength(37)));
+		assert_eq!("{12345678-9ABC-DEF0-0123+456789ABCDEF}".parse::<Guid>(), Err(ParseGuidError::InvalidCharacter(24, '+')));
+		assert_eq!("12345678-9ABC-DEF0-0123-456789ABCDEX".parse::<Guid>(), Err(ParseGuidError::InvalidCharacter(35, 'X')));
+		assert_eq!("123456789-ABC-DEF0-0123-456789ABCDEF".parse::<Guid>(), Err(ParseGuidError::InvalidCharacter(8, '9')));
+		assert_eq!("12345678-9ABC-DEF0-0123-456789ABCDé".parse::<Guid>(), Err(ParseGuidError::InvalidCharacter(34, 'é')));
+	}
+}
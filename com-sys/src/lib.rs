@@ -1,18 +1,35 @@
 /*!
+Macros and traits for defining the COM FFI bits.
 */
 
-#![cfg(windows)]
+#![allow(clippy::tabs_in_doc_comments)]
 
+#[cfg(windows)]
 extern crate winapi;
+#[cfg(not(windows))]
+mod winapi;
+
+extern crate com_macros;
 
 /// Re-export so that the macros can find them.
 #[doc(hidden)]
 pub use winapi::{HRESULT, GUID, LPVOID};
+/// Re-export the portable stand-ins so that `com-types` shares them.
+#[cfg(not(windows))]
+#[doc(hidden)]
+pub use winapi::{VARIANT, VARIANTARG, DISPPARAMS, EXCEPINFO};
+#[doc(hidden)]
+pub use com_macros::__guid;
 
 #[macro_use]
 pub mod macros;
 
+mod guid;
+pub use guid::{Guid, ParseGuidError};
+
 mod traits;
 pub use traits::*;
 
+pub mod dispatch;
 pub mod unknown;
+pub mod weakref;
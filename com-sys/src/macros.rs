@@ -10,13 +10,14 @@ GUID macros
 
 Define GUIDs with almost native GUID syntax.
 
-Every number group is required to be prefixed by `0x` to indicate they're hexadecimal.
+GUIDs are written as string literals, copied straight from IDL and header files, and validated at compile time.
+Alternatively every number group is prefixed by `0x` to indicate they're hexadecimal.
 
-* [`guid!`](../macro.guid!.html)`{0xAAAAAAAA-0xBBBB-0xCCCC-0xDDDD-0xEEEEEEEEEEEE}`
+* [`guid!`](../macro.guid!.html)`("AAAAAAAA-BBBB-CCCC-DDDD-EEEEEEEEEEEE")` or `guid!{0xAAAAAAAA-0xBBBB-0xCCCC-0xDDDD-0xEEEEEEEEEEEE}`
 
-  Creates a `GUID` instance.
+  Creates a `GUID` instance, convert to [`Guid`](../struct.Guid.html) to compare, hash, print or parse them.
 
-* [`static_guid!`](../macro.static_guid!.html)`(STATIC_GUID, {0xAAAAAAAA-0xBBBB-0xCCCC-0xDDDD-0xEEEEEEEEEEEE});`
+* [`static_guid!`](../macro.static_guid!.html)`(STATIC_GUID, "AAAAAAAA-BBBB-CCCC-DDDD-EEEEEEEEEEEE");`
 
   Expands to a static definition so you avoid having to name its type.
 
@@ -36,7 +37,7 @@ These help with plumbing the required FFI structs and associated traits.
   com_vtbl! {
         IInterfaceVtbl: IUnknownVtbl,
         IInterface,
-        pub Foo: unsafe extern "stdcall" fn(
+        pub Foo: unsafe extern "system" fn(
             This: *mut IInterface,
         ),
   }
@@ -54,16 +55,20 @@ These help with plumbing the required FFI structs and associated traits.
   com_interface! {
         interface IInterface(IInterfaceVtbl): IUnknown(IUnknownVtbl);
         {0xAAAAAAAA-0xBBBB-0xCCCC-0xDDDD-0xEEEEEEEEEEEE}
-        pub Foo: unsafe extern "stdcall" fn(
+        pub Foo: unsafe extern "system" fn(
             This: *mut IInterface,
         ),
   }
   # }
   ```
 
-* [`com_class!`](../macro.com_class!.html)`(_extern Class, {0xAAAAAAAA-0xBBBB-0xCCCC-0xDDDD-0xEEEEEEEEEEEE}, IInterface);`
+* [`com_class!`](../macro.com_class!.html)`(_extern Class, "AAAAAAAA-BBBB-CCCC-DDDD-EEEEEEEEEEEE", IInterface);`
 
   Defines a placeholder class (an empty enum), its class id and the interfaces it implements.
+
+* [`com_assert_slots!`](../macro.com_assert_slots!.html)`(IInterface, 4);`
+
+  Asserts at compile time the interface's vtbl has the expected number of slots, including the inherited ones.
 */
 
 /// Constructs a GUID.
@@ -71,12 +76,39 @@ These help with plumbing the required FFI structs and associated traits.
 /// See [`static_guid!`](macro.static_guid!.html) to define a named static GUID.
 /// This avoids having to specify the `GUID` type, it just works(tm).
 ///
-/// Note that you need to prefix the numbers with `0x` to indicate they're hexadecimal.
-/// Limitations in `macro_rules!` prevent this being done automatically.
+/// The GUID is given as a string literal in registry form, with or without braces.
+/// It is validated at compile time, malformed GUIDs are a compile error.
+///
+/// Alternatively the number groups are given directly, prefixed with `0x` to indicate they're hexadecimal.
+///
+/// GUIDs can appear in either form as part of other macros in this crate.
 ///
-/// GUIDs can appear as part of other macros in this crate, they are all expected to have their numbers prefixed with `0x`.
+/// # Examples
+///
+/// ```
+/// # #[macro_use] extern crate com_sys; fn main() {
+/// use ::com_sys::Guid;
+///
+/// let guid = guid!("{AAAAAAAA-BBBB-CCCC-DDDD-EEEEEEEEEEEE}");
+/// assert_eq!(Guid::from(guid), Guid::from(guid!("aaaaaaaa-bbbb-cccc-dddd-eeeeeeeeeeee")));
+/// assert_eq!(Guid::from(guid), Guid::from(guid!{0xAAAAAAAA-0xBBBB-0xCCCC-0xDDDD-0xEEEEEEEEEEEE}));
+/// # }
+/// ```
+///
+/// ```compile_fail
+/// # #[macro_use] extern crate com_sys; fn main() {
+/// // error: invalid guid character 'X' at offset 35, expected a hex digit
+/// let guid = guid!("AAAAAAAA-BBBB-CCCC-DDDD-EEEEEEEEEEEX");
+/// # }
+/// ```
 #[macro_export]
 macro_rules! guid {
+	($guid:literal) => {
+		$crate::__guid!($crate, $guid)
+	};
+	({$($guid:tt)*}) => {
+		guid!($($guid)*)
+	};
 	($a:tt-$b:tt-$c:tt-$d:tt-$e:tt) => {
 		$crate::GUID {
 			Data1: $a,
@@ -98,23 +130,24 @@ macro_rules! guid {
 
 /// Defines a named static GUID.
 ///
-/// Note that you need to prefix the numbers with `0x` to indicate they're hexadecimal.
-/// Limitations in `macro_rules!` prevent this being done automatically.
+/// Accepts the same GUID forms as [`guid!`](macro.guid!.html).
 ///
 /// # Examples
 ///
 /// ```
 /// # #[macro_use] extern crate com_sys; fn main() {
-/// static_guid!(STATIC_GUID, {0xAAAAAAAA-0xBBBB-0xCCCC-0xDDDD-0xEEEEEEEEEEEE});
+/// static_guid!(STATIC_GUID, "AAAAAAAA-BBBB-CCCC-DDDD-EEEEEEEEEEEE");
 /// 
-/// // This would be equal if you could compare `GUID`s...
-/// // assert_eq!(STATIC_GUID, guid!{0xAAAAAAAA-0xBBBB-0xCCCC-0xDDDD-0xEEEEEEEEEEEE});
+/// // Compare and print them as `Guid`s.
+/// use ::com_sys::Guid;
+/// assert_eq!(Guid::from(STATIC_GUID), Guid::from(guid!{0xAAAAAAAA-0xBBBB-0xCCCC-0xDDDD-0xEEEEEEEEEEEE}));
+/// assert_eq!(Guid::from(STATIC_GUID).to_string(), "{AAAAAAAA-BBBB-CCCC-DDDD-EEEEEEEEEEEE}");
 /// # }
 /// ```
 #[macro_export]
 macro_rules! static_guid {
 	($name:ident, $guid:tt) => {
-		static $name: $crate::GUID = guid!$guid;
+		static $name: $crate::GUID = guid!($guid);
 	};
 }
 
@@ -127,6 +160,8 @@ macro_rules! static_guid {
 ///
 /// A `Deref` impl is provided to access the base vtbl members with correct `This` parameter type.
 ///
+/// The [`ComVtbl`](trait.ComVtbl.html) impl lists the virtual function names for introspecting the slot layout.
+///
 /// This macro is expanded as part of [`com_interface!`](macro.com_interface!.html) which you should use.
 #[macro_export]
 macro_rules! com_vtbl {
@@ -138,19 +173,35 @@ macro_rules! com_vtbl {
 		#[repr(C)]
 		#[allow(non_snake_case)]
 		pub struct $iface_vtbl<$iface: $crate::ComInterface> { $(pub $ident: $vfn,)* }
-		impl<$iface: $crate::ComInterface> $crate::ComVtbl for $iface_vtbl<$iface> {}
+		impl<$iface: $crate::ComInterface> $crate::ComVtbl for $iface_vtbl<$iface> {
+			const METHODS: &'static [&'static str] = &[$(stringify!($ident)),*];
+			const SLOTS: usize = Self::METHODS.len();
+		}
 	};
 	(
 		$iface_vtbl:ident: $ibase_vtbl:ident,
 		$iface:ident,
 		$(pub $ident:ident: $vfn:ty,)*
 	) => {
-		com_vtbl! {
-			$iface_vtbl,
-			$iface,
+		#[repr(C)]
+		#[allow(non_snake_case)]
+		pub struct $iface_vtbl<$iface: $crate::ComInterface> {
 			pub base: $ibase_vtbl<$iface>,
 			$(pub $ident: $vfn,)*
 		}
+		impl<$iface: $crate::ComInterface> $crate::ComVtbl for $iface_vtbl<$iface> {
+			const METHODS: &'static [&'static str] = &[$(stringify!($ident)),*];
+			const SLOTS: usize = <$ibase_vtbl<$iface> as $crate::ComVtbl>::SLOTS + Self::METHODS.len();
+			fn slot_names() -> Vec<&'static str> {
+				let mut names = <$ibase_vtbl<$iface> as $crate::ComVtbl>::slot_names();
+				names.extend_from_slice(Self::METHODS);
+				names
+			}
+			fn slot(name: &str) -> Option<usize> {
+				let base = <$ibase_vtbl<$iface> as $crate::ComVtbl>::SLOTS;
+				<$ibase_vtbl<$iface> as $crate::ComVtbl>::slot(name).or_else(|| Self::METHODS.iter().position(|&method| method == name).map(|index| base + index))
+			}
+		}
 		impl<$iface: $crate::ComInterface> ::std::ops::Deref for $iface_vtbl<$iface> {
 			type Target = $ibase_vtbl<$iface>;
 			#[inline(always)] fn deref(&self) -> &$ibase_vtbl<$iface> { &self.base }
@@ -158,6 +209,42 @@ macro_rules! com_vtbl {
 	};
 }
 
+/// Asserts at compile time that an interface's vtbl has the expected number of slots.
+///
+/// The count includes the inherited slots, eg. 3 for `IUnknown`.
+/// Also checks the vtbl consists of exactly that many pointer sized fields.
+///
+/// ```
+/// # #[macro_use] extern crate com_sys; fn main() {
+/// use ::com_sys::unknown::{IUnknown, IUnknownVtbl};
+///
+/// com_interface! {
+/// 	interface IInterface(IInterfaceVtbl): IUnknown(IUnknownVtbl);
+/// 	"e4059080-dacb-46dd-bb59-560cb47c9578"
+/// 	pub Foo: unsafe extern "system" fn(This: *mut IInterface),
+/// }
+/// com_assert_slots!(IInterface, 4);
+/// # }
+/// ```
+///
+/// ```compile_fail
+/// # #[macro_use] extern crate com_sys; fn main() {
+/// use ::com_sys::unknown::IUnknown;
+///
+/// // error: mismatched types, expected an array with a size of 4, found one with a size of 3
+/// com_assert_slots!(IUnknown, 4);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! com_assert_slots {
+	($iface:ty, $slots:expr) => {
+		const _: () = {
+			let _: [(); $slots] = [(); <<$iface as $crate::ComInterface>::Vtbl as $crate::ComVtbl>::SLOTS];
+			let _: [(); $slots * ::std::mem::size_of::<usize>()] = [(); ::std::mem::size_of::<<$iface as $crate::ComInterface>::Vtbl>()];
+		};
+	};
+}
+
 /// Defines an interface struct and its vtbl struct.
 ///
 /// Due to Rust's macro limitations you must provide names for the vtbl types.
@@ -168,7 +255,7 @@ macro_rules! com_vtbl {
 ///
 /// Creates a new interface struct containing just a `&'static` reference to its vtbl.
 /// Implements [`ComInterface`](trait.ComInterface.html) which can access its vtbl and iid.
-/// Implements [`ComCast<$ibase>`](trait.ComCast.html) allowing an exlicit casting to its base interface.
+/// Implements [`ComInherit`](trait.ComInherit.html) naming its base interface and [`ComExtends`](trait.ComExtends.html) for itself and its ancestors.
 ///
 /// # Examples
 ///
@@ -184,8 +271,8 @@ macro_rules! com_vtbl {
 /// 
 /// com_interface! {
 /// 	interface IInterface(IInterfaceVtbl): IUnknown(IUnknownVtbl);
-/// 	{0xe4059080-0xdacb-0x46dd-0xbb59-0x560cb47c9578}
-/// 	pub Foo: unsafe extern "stdcall" fn(
+/// 	"e4059080-dacb-46dd-bb59-560cb47c9578"
+/// 	pub Foo: unsafe extern "system" fn(
 /// 		This: *mut IInterface,
 /// 	),
 /// }
@@ -199,7 +286,7 @@ macro_rules! com_vtbl {
 /// use ::com_sys::unknown::{IUnknown, IUnknownVtbl};
 /// 
 /// // Import these to reduce visual clutter, they use `$crate::*` internally.
-/// use ::com_sys::{ComInterface, ComVtbl, GUID};
+/// use ::com_sys::{ComInterface, ComVtbl, Guid, GUID};
 /// 
 /// // Represents a boring vtbl with fn pointers to its virtual functions.
 /// // Note that it is generic over its `This` type allowing easier reuse and type safety.
@@ -207,12 +294,24 @@ macro_rules! com_vtbl {
 /// #[repr(C)]
 /// pub struct IInterfaceVtbl<IInterface: ComInterface> {
 /// 	pub base: IUnknownVtbl<IInterface>,
-/// 	pub Foo: unsafe extern "stdcall" fn(
+/// 	pub Foo: unsafe extern "system" fn(
 /// 		This: *mut IInterface,
 /// 	),
 /// }
-/// // Marks the vtbl struct as `ComVtbl`.
-/// impl<IInterface: ComInterface> ComVtbl for IInterfaceVtbl<IInterface> {}
+/// // Marks the vtbl struct as `ComVtbl` listing its virtual functions, the base slots come first.
+/// impl<IInterface: ComInterface> ComVtbl for IInterfaceVtbl<IInterface> {
+/// 	const METHODS: &'static [&'static str] = &["Foo"];
+/// 	const SLOTS: usize = <IUnknownVtbl<IInterface> as ComVtbl>::SLOTS + Self::METHODS.len();
+/// 	fn slot_names() -> Vec<&'static str> {
+/// 		let mut names = <IUnknownVtbl<IInterface> as ComVtbl>::slot_names();
+/// 		names.extend_from_slice(Self::METHODS);
+/// 		names
+/// 	}
+/// 	fn slot(name: &str) -> Option<usize> {
+/// 		let base = <IUnknownVtbl<IInterface> as ComVtbl>::SLOTS;
+/// 		<IUnknownVtbl<IInterface> as ComVtbl>::slot(name).or_else(|| Self::METHODS.iter().position(|&method| method == name).map(|index| base + index))
+/// 	}
+/// }
 /// // Faux inheritance through `Deref` for convenience.
 /// impl<IInterface: ComInterface> ::std::ops::Deref for IInterfaceVtbl<IInterface> {
 /// 	type Target = IUnknownVtbl<IInterface>;
@@ -227,12 +326,20 @@ macro_rules! com_vtbl {
 /// // Implements `ComInterface` giving access to its vtbl and iid.
 /// impl ComInterface for IInterface {
 ///		type Vtbl = IInterfaceVtbl<IInterface>;
+/// 	const IID: Guid = Guid::from_guid(guid!("e4059080-dacb-46dd-bb59-560cb47c9578"));
 /// 	fn vtbl(&self) -> &Self::Vtbl { unsafe { &*self.vtbl } }
-/// 	fn iid() -> &'static GUID {
-/// 		static_guid!(IID, {0xe4059080-0xdacb-0x46dd-0xbb59-0x560cb47c9578});
-/// 		&IID
+/// 	// Querying for the base interface is answered by this interface as well.
+/// 	fn matches_iid(iid: &GUID) -> bool {
+/// 		*Guid::from_raw(iid) == Self::IID || IUnknown::matches_iid(iid)
 /// 	}
 /// }
+/// // Declares its base interface.
+/// impl ::com_sys::ComInherit for IInterface {
+/// 	type Super = IUnknown;
+/// }
+/// // Extends itself and every interface its base extends.
+/// impl ::com_sys::ComExtends<IInterface> for IInterface {}
+/// impl<B: ComInterface> ::com_sys::ComExtends<B> for IInterface where IUnknown: ::com_sys::ComExtends<B> {}
 /// // Note the lack of `Deref` abuse for inheritance, this is by design.
 /// // It would allow you to access the parent's vtbl with incorrect `This` type.
 /// // Instead you can access the parent's virtual functions directly through its vtbl.
@@ -253,12 +360,9 @@ macro_rules! com_interface {
 		impl $crate::ComInterface for $iface {
 			type Vtbl = $iface_vtbl<$iface>;
 			#[inline(always)] fn vtbl(&self) -> &Self::Vtbl { unsafe { &*self.vtbl } }
-			#[inline(always)]
-			fn iid() -> &'static $crate::GUID {
-				static_guid!(IID, $iid);
-				&IID
-			}
+			const IID: $crate::Guid = $crate::Guid::from_guid(guid!($iid));
 		}
+		impl $crate::ComExtends<$iface> for $iface {}
 	};
 	(
 		$(#[$attr:meta])*
@@ -273,28 +377,40 @@ macro_rules! com_interface {
 		impl $crate::ComInterface for $iface {
 			type Vtbl = $iface_vtbl<$iface>;
 			#[inline(always)] fn vtbl(&self) -> &Self::Vtbl { unsafe { &*self.vtbl } }
-			#[inline(always)]
-			fn iid() -> &'static $crate::GUID {
-				static_guid!(IID, $iid);
-				&IID
+			const IID: $crate::Guid = $crate::Guid::from_guid(guid!($iid));
+			#[inline]
+			fn matches_iid(iid: &$crate::GUID) -> bool {
+				*$crate::Guid::from_raw(iid) == Self::IID || <$ibase as $crate::ComInterface>::matches_iid(iid)
 			}
 		}
 		impl $crate::ComInherit for $iface {
 			type Super = $ibase;
 		}
+		impl $crate::ComExtends<$iface> for $iface {}
+		impl<B: $crate::ComInterface> $crate::ComExtends<B> for $iface where $ibase: $crate::ComExtends<B> {}
 	};
 }
 
 /// Implements `ComClass` given a class, the interface it implements and its class id.
+///
+/// See [`guid!`](macro.guid!.html) for help with the CLSID format.
+///
+/// ```
+/// # #[macro_use] extern crate com_sys; fn main() {
+/// use ::com_sys::{ComClass, Guid};
+/// use ::com_sys::unknown::IUnknown;
+///
+/// com_class!(_extern Class, "{00000323-0000-0000-C000-000000000046}", IUnknown);
+///
+/// assert_eq!(Class::CLSID.to_string(), "{00000323-0000-0000-C000-000000000046}");
+/// assert_eq!(Guid::from(*Class::clsid()), Class::CLSID);
+/// # }
+/// ```
 #[macro_export]
 macro_rules! com_class {
 	($class:ty, $clsid:tt $(,$iface:ty)*) => {
 		impl $crate::ComClass for $class {
-			#[inline(always)]
-			fn clsid() -> &'static $crate::GUID {
-				static_guid!(CLSID, $clsid);
-				&CLSID
-			}
+			const CLSID: $crate::Guid = $crate::Guid::from_guid(guid!($clsid));
 		}
 		$(impl $crate::ComClassInterface<$iface> for $class {})*
 	};
@@ -1,4 +1,7 @@
+use ::std::mem;
+
 use ::winapi::{CLSID, IID};
+use ::guid::Guid;
 
 /// COM Vtbl structs implement `ComVtbl` which is little more than a marker.
 ///
@@ -17,7 +20,7 @@ use ::winapi::{CLSID, IID};
 /// ```
 /// # use ::com_sys::ComInterface;
 /// struct IInterfaceVtbl<IInterface: ComInterface> {
-/// 	pub Foo: unsafe extern "stdcall" fn(
+/// 	pub Foo: unsafe extern "system" fn(
 /// 		This: *mut IInterface,
 /// 	),
 /// }
@@ -30,8 +33,59 @@ use ::winapi::{CLSID, IID};
 ///
 /// Implementing `Deref` to access the base vtbl (with correct `This` pointer!) is icing on the cake.
 ///
+/// The layout can be introspected: the names of the virtual functions in slot order, inherited ones first, and their slot indices.
+/// Interface definitions can be checked against the IDL ordering, see [`com_assert_slots!`](macro.com_assert_slots!.html).
+///
+/// ```
+/// use ::com_sys::ComVtbl;
+/// use ::com_sys::unknown::{IUnknown, IUnknownVtbl};
+///
+/// type Vtbl = IUnknownVtbl<IUnknown>;
+/// assert_eq!(Vtbl::slot_names(), ["QueryInterface", "AddRef", "Release"]);
+/// assert_eq!(Vtbl::SLOTS, 3);
+/// assert_eq!(Vtbl::slot("Release"), Some(2));
+/// assert_eq!(Vtbl::offset_of("AddRef"), Some(::std::mem::size_of::<usize>()));
+///
+/// // Hand written impls need not list their virtual functions.
+/// struct IUnnamedVtbl {
+/// 	pub base: Vtbl,
+/// 	pub Foo: unsafe extern "system" fn(This: *mut IUnknown),
+/// }
+/// impl ComVtbl for IUnnamedVtbl {}
+/// assert_eq!(IUnnamedVtbl::SLOTS, 4);
+/// assert_eq!(IUnnamedVtbl::slot("Foo"), None);
+/// ```
+///
 /// See [`com_vtbl!`](macros/index.html#com-ffi-macros) for how to use the provided macros to automate their implementation.
-pub trait ComVtbl: Sized {}
+pub trait ComVtbl: Sized {
+	/// Names of the virtual functions declared by this vtbl in slot order, without the inherited ones.
+	///
+	/// Defaults to none, hand written impls without it have no named slots.
+	const METHODS: &'static [&'static str] = &[];
+	/// Number of slots, including the inherited ones.
+	///
+	/// Defaults to the number of pointers fitting in the vtbl.
+	const SLOTS: usize = mem::size_of::<Self>() / mem::size_of::<usize>();
+	/// Returns the names of all virtual functions in slot order, starting with the inherited ones.
+	///
+	/// Defaults to the `METHODS`, vtbls with a base prepend its names.
+	fn slot_names() -> Vec<&'static str> {
+		Self::METHODS.to_vec()
+	}
+	/// Returns the slot index of a virtual function by name.
+	///
+	/// Defaults to looking up the `METHODS` in the last slots, vtbls with a base look up its names first.
+	#[inline]
+	fn slot(name: &str) -> Option<usize> {
+		let index = Self::METHODS.iter().position(|&method| method == name)?;
+		(Self::SLOTS + index).checked_sub(Self::METHODS.len())
+	}
+	/// Returns the byte offset of a virtual function in the vtbl by name.
+	#[inline]
+	fn offset_of(name: &str) -> Option<usize> {
+		Self::slot(name).map(|slot| slot * mem::size_of::<usize>())
+	}
+}
 
 /// COM Interface structs implement `ComInterface`.
 ///
@@ -43,13 +97,42 @@ pub trait ComVtbl: Sized {}
 ///
 /// Inheritance is declared by [`ComInherit`](trait.ComInherit.html) with an associated type indicating its base interface.
 ///
+/// The interface id is an associated constant, usable in const contexts and as a pattern:
+///
+/// ```
+/// use ::com_sys::{ComInterface, Guid};
+/// use ::com_sys::unknown::IUnknown;
+///
+/// fn name(iid: &Guid) -> &'static str {
+/// 	match *iid {
+/// 		IUnknown::IID => "IUnknown",
+/// 		_ => "unknown",
+/// 	}
+/// }
+/// assert_eq!(name(&"00000000-0000-0000-C000-000000000046".parse().unwrap()), "IUnknown");
+/// ```
+///
 /// See [`com_interface!`](macros/index.html#com-ffi-macros) for how to use the provided macros to automate their implementation.
 pub trait ComInterface: Sized {
 	type Vtbl: ComVtbl;
+	/// The interface id.
+	const IID: Guid;
 	/// Returns the vtbl.
 	fn vtbl(&self) -> &Self::Vtbl;
 	/// Returns the interface id.
-	fn iid() -> &'static IID;
+	#[inline]
+	fn iid() -> &'static IID {
+		let iid: &'static Guid = &Self::IID;
+		iid.as_raw()
+	}
+	/// Returns if `iid` identifies this interface or any interface it inherits from.
+	///
+	/// A pointer to this interface is a valid answer when querying for any of these iids.
+	/// Overridden by [`com_interface!`](macros/index.html#com-ffi-macros) to walk the inheritance chain.
+	#[inline]
+	fn matches_iid(iid: &IID) -> bool {
+		*Guid::from_raw(iid) == Self::IID
+	}
 }
 
 /// Declares a com interface inherits another interface.
@@ -59,6 +142,12 @@ pub trait ComInherit: ComInterface {
 	type Super: ComInterface;
 }
 
+/// Declares a com interface is, or transitively inherits, the interface `B`.
+///
+/// Implemented as part of [`com_interface!`](macros/index.html#com-ffi-macros) by following the [`ComInherit`](trait.ComInherit.html) chain,
+/// it tells apart the com pointers an interface's com pointer can be cast to.
+pub trait ComExtends<B: ComInterface>: ComInterface {}
+
 /// COM Class structs implement `ComClass`.
 ///
 /// Classes are identified with a CLSID (**CL**&#8203;a&#8203;**S**&#8203;s **ID**&#8203;entifier).
@@ -67,8 +156,14 @@ pub trait ComInherit: ComInterface {
 ///
 /// See [`com_class!`](macros/index.html#com-ffi-macros) for how to use the provided macros to automate their implementation.
 pub trait ComClass {
+	/// The class id.
+	const CLSID: Guid;
 	/// Returns its class id.
-	fn clsid() -> &'static CLSID;
+	#[inline]
+	fn clsid() -> &'static CLSID {
+		let clsid: &'static Guid = &Self::CLSID;
+		clsid.as_raw()
+	}
 }
 
 /// Declares the `ComInterface`s which are implemented by a `ComClass`.
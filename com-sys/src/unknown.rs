@@ -3,15 +3,15 @@ use ::winapi::{HRESULT, LPVOID, ULONG, REFIID};
 com_interface! {
 	interface IUnknown(IUnknownVtbl);
 	{0x00000000-0x0000-0x0000-0xC000-0x000000000046}
-	pub QueryInterface: unsafe extern "stdcall" fn(
+	pub QueryInterface: unsafe extern "system" fn(
 		This: *mut IUnknown,
 		riid: REFIID,
 		ppvObject: *mut LPVOID,
 	) -> HRESULT,
-	pub AddRef: unsafe extern "stdcall" fn(
+	pub AddRef: unsafe extern "system" fn(
 		This: *mut IUnknown,
 	) -> ULONG,
-	pub Release: unsafe extern "stdcall" fn(
+	pub Release: unsafe extern "system" fn(
 		This: *mut IUnknown,
 	) -> ULONG,
 }
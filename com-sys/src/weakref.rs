@@ -0,0 +1,26 @@
+/*!
+Weak references, see `weakreference.h`.
+*/
+
+use ::winapi::{HRESULT, REFIID};
+use ::unknown::{IUnknown, IUnknownVtbl};
+
+com_interface! {
+	interface IWeakReference(IWeakReferenceVtbl): IUnknown(IUnknownVtbl);
+	{0x00000037-0x0000-0x0000-0xC000-0x000000000046}
+	// Declared as `IInspectable **`, any interface pointer is passed.
+	pub Resolve: unsafe extern "system" fn(
+		This: *mut IWeakReference,
+		riid: REFIID,
+		objectReference: *mut *mut IUnknown,
+	) -> HRESULT,
+}
+
+com_interface! {
+	interface IWeakReferenceSource(IWeakReferenceSourceVtbl): IUnknown(IUnknownVtbl);
+	{0x00000038-0x0000-0x0000-0xC000-0x000000000046}
+	pub GetWeakReference: unsafe extern "system" fn(
+		This: *mut IWeakReferenceSource,
+		weakReference: *mut *mut IWeakReference,
+	) -> HRESULT,
+}
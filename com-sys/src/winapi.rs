@@ -0,0 +1,82 @@
+/*!
+Portable stand-ins for the `winapi` types used by this crate.
+
+The `winapi` crate only builds on Windows, on other targets these definitions take its place.
+They have the same layout as their Windows counterparts so interface definitions work unchanged.
+*/
+
+#![allow(non_camel_case_types, non_snake_case, clippy::upper_case_acronyms)]
+
+pub use ::std::os::raw::c_void;
+
+pub type HRESULT = i32;
+pub type ULONG = u32;
+pub type LPVOID = *mut c_void;
+pub type PVOID = *mut c_void;
+pub type BOOL = i32;
+
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct GUID {
+	pub Data1: u32,
+	pub Data2: u16,
+	pub Data3: u16,
+	pub Data4: [u8; 8],
+}
+pub type IID = GUID;
+pub type CLSID = GUID;
+pub type REFIID = *const IID;
+
+pub type WORD = u16;
+pub type DWORD = u32;
+pub type LONG = i32;
+pub type UINT = u32;
+pub type SCODE = LONG;
+pub type LCID = DWORD;
+pub type DISPID = LONG;
+
+pub type OLECHAR = u16;
+pub type LPOLESTR = *mut OLECHAR;
+pub type LPCOLESTR = *const OLECHAR;
+pub type BSTR = *mut OLECHAR;
+
+#[cfg(target_pointer_width = "64")]
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct VARIANT {
+	pub data0: u64,
+	pub data1: u64,
+	pub data2: u64,
+}
+#[cfg(target_pointer_width = "32")]
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct VARIANT {
+	pub data0: u64,
+	pub data1: u32,
+	pub data2: u32,
+}
+pub type VARIANTARG = VARIANT;
+
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct DISPPARAMS {
+	pub rgvarg: *mut VARIANTARG,
+	pub rgdispidNamedArgs: *mut DISPID,
+	pub cArgs: UINT,
+	pub cNamedArgs: UINT,
+}
+
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct EXCEPINFO {
+	pub wCode: WORD,
+	pub wReserved: WORD,
+	pub bstrSource: BSTR,
+	pub bstrDescription: BSTR,
+	pub bstrHelpFile: BSTR,
+	pub dwHelpContext: DWORD,
+	pub pvReserved: LPVOID,
+	pub pfnDeferredFillIn: Option<unsafe extern "system" fn(*mut EXCEPINFO) -> HRESULT>,
+	pub scode: SCODE,
+}
@@ -0,0 +1,316 @@
+#!/usr/bin/env python3
+"""
+Writes the MSFT type library fixtures without needing MIDL.
+
+Lays out the binary format the way midl and widl do: the header, the segment
+directory, the segments and the member records of every type info.
+shapes.tlb is meant to be the type library compiled from shapes.idl, which
+mirrors com-idl's example.idl with a dual interface and a dispinterface added.
+
+The checked in shapes.tlb is still the output of this script, not of a real
+compiler: it has not been cross-checked against widl or MIDL output yet. Replace
+it with the compiler's output once one is at hand, the tests in src/lib.rs then
+parse the real library:
+
+    widl -t -o shapes.tlb shapes.idl
+    midl /tlb shapes.tlb shapes.idl
+
+The ignored `widl` test in src/lib.rs compiles shapes.idl with widl and checks
+the result the same way as the fixture.
+
+Usage: python3 mktlb.py  (writes shapes.tlb next to this script)
+"""
+
+import os
+import struct
+import uuid
+
+VT_I2, VT_I4, VT_R8, VT_BSTR, VT_VARIANT, VT_UI1, VT_UI4 = 2, 3, 5, 8, 12, 17, 19
+VT_VOID, VT_HRESULT, VT_PTR, VT_CARRAY, VT_USERDEFINED = 24, 25, 26, 28, 29
+
+TKIND_ENUM, TKIND_RECORD, TKIND_INTERFACE, TKIND_DISPATCH, TKIND_COCLASS, TKIND_ALIAS = 0, 1, 3, 4, 5, 6
+
+PARAMFLAG_FIN, PARAMFLAG_FOUT, PARAMFLAG_FRETVAL = 1, 2, 8
+INVOKE_FUNC, INVOKE_PROPERTYGET, INVOKE_PROPERTYPUT = 1, 2, 4
+FUNC_PUREVIRTUAL, FUNC_DISPATCH = 1, 4
+VAR_PERINSTANCE, VAR_CONST = 0, 2
+
+TYPEINFO_SIZE = 0x64
+NONE = -1
+
+
+def inline(vt):
+	"""Type descriptor stored inline: the high bit set with the vt in both words."""
+	return struct.unpack("<i", struct.pack("<I", 0x80000000 | (vt << 16) | vt))[0]
+
+
+def pad4(data, fill=b"\0"):
+	return data + fill * (-len(data) % 4)
+
+
+class Lib:
+	def __init__(self):
+		self.guids = b""
+		self.names = b""
+		self.name_count = 0
+		self.strings = b""
+		self.typedescs = b""
+		self.arraydescs = b""
+		self.custdata = b""
+		self.refs = b""
+		self.impinfos = b""
+		self.impfiles = b""
+		self.infos = []
+
+	def guid(self, text, hreftype):
+		offset = len(self.guids)
+		self.guids += uuid.UUID(text).bytes_le + struct.pack("<ii", hreftype, NONE)
+		return offset
+
+	def name(self, text, hreftype=NONE):
+		offset = len(self.names)
+		self.name_count += 1
+		data = text.encode("ascii")
+		self.names += struct.pack("<iii", hreftype, NONE, len(data)) + pad4(data, b"W")
+		return offset
+
+	def string(self, text):
+		offset = len(self.strings)
+		data = text.encode("ascii")
+		self.strings += pad4(struct.pack("<h", len(data)) + data, b"W")
+		return offset
+
+	def typedesc(self, vt, target):
+		"""Pointer and safearray targets are a typedesc offset or an inline vt, user defined targets a hreftype."""
+		offset = len(self.typedescs)
+		self.typedescs += struct.pack("<Hhi", vt, 0x7FFE, target)
+		return offset
+
+	def carray(self, elem, dims):
+		offset = len(self.arraydescs)
+		self.arraydescs += struct.pack("<ii", elem, len(dims))
+		for count in dims:
+			self.arraydescs += struct.pack("<ii", count, 0)
+		return self.typedesc(VT_CARRAY, offset)
+
+	def value(self, vt, value):
+		"""Constants which do not fit the 26 inline bits go to the custom data segment."""
+		if vt == VT_I4 and 0 <= value < 0x4000000:
+			return struct.unpack("<i", struct.pack("<I", 0x80000000 | (vt << 26) | value))[0]
+		offset = len(self.custdata)
+		self.custdata += pad4(struct.pack("<Hi", vt, value))
+		return offset
+
+	def impfile(self, libid, filename):
+		offset = len(self.impfiles)
+		data = filename.encode("ascii")
+		self.impfiles += pad4(struct.pack("<iiiH", self.guid(libid, -2), 0, 2, len(data) << 2 | 1) + data)
+		return offset
+
+	def import_type(self, impfile, tkind, iid):
+		offset = len(self.impinfos)
+		self.impinfos += struct.pack("<iii", 0x00010000 | tkind << 24 | 1, impfile, self.guid(iid, NONE))
+		# Imported hreftypes have the lowest bit set.
+		return offset | 1
+
+	def reftab(self, refs):
+		"""Linked list of the interfaces implemented by a coclass."""
+		offset = len(self.refs)
+		for i, (hreftype, flags) in enumerate(refs):
+			onext = offset + (i + 1) * 16 if i + 1 < len(refs) else NONE
+			self.refs += struct.pack("<iiii", hreftype, flags, NONE, onext)
+		return offset
+
+	def typeinfo(self, **info):
+		self.infos.append(info)
+		return (len(self.infos) - 1) * TYPEINFO_SIZE
+
+
+def funcs_block(lib, funcs, vars):
+	"""Member records followed by the member ids, names and record offsets."""
+	records = b""
+	offsets = []
+	for func in funcs:
+		params = b""
+		for (datatype, name, flags) in func["params"]:
+			params += struct.pack("<iii", datatype, lib.name(name), flags)
+		optional = struct.pack("<ii", 0, lib.string(func["doc"])) if "doc" in func else b""
+		fkccic = func["funckind"] | func["invkind"] << 3 | 4 << 8
+		if any(flags & PARAMFLAG_FRETVAL for (_, _, flags) in func["params"]):
+			fkccic |= 0x4000
+		body = struct.pack("<iihhihh", func["ret"], 0, func["vft"] * 4, 0, fkccic, len(func["params"]), 0) + optional + params
+		offsets.append(len(records))
+		records += struct.pack("<i", 4 + len(body)) + body
+	for var in vars:
+		body = struct.pack("<iihhi", var["datatype"], 0, var["varkind"], 0, var["value"])
+		offsets.append(len(records))
+		records += struct.pack("<i", 4 + len(body)) + body
+	members = funcs + vars
+	memids = [member.get("memid", 0x60000000 + i) for (i, member) in enumerate(members)]
+	names = [lib.name(member["name"]) for member in members]
+	trailer = b"".join(struct.pack("<i", value) for value in memids + names + offsets)
+	return struct.pack("<i", len(records)) + records + trailer
+
+
+def write(lib, path, header, typeinfo_data):
+	"""The header holds the library guid, name and doc string offsets."""
+	libid, libname, libdoc = header
+	header_size = 0x54
+	count = len(lib.infos)
+	segdir_offset = header_size + count * 4
+	segments_offset = segdir_offset + 15 * 16
+
+	guidhash = struct.pack("<i", NONE) * 32
+	namehash = struct.pack("<i", NONE) * 128
+	typeinfo_tab_len = count * TYPEINFO_SIZE
+	segments = [
+		("typeinfo", typeinfo_tab_len),
+		("impinfo", lib.impinfos),
+		("impfiles", lib.impfiles),
+		("reftab", lib.refs),
+		("guidhash", guidhash),
+		("guids", lib.guids),
+		("namehash", namehash),
+		("names", lib.names),
+		("strings", lib.strings),
+		("typedescs", lib.typedescs),
+		("arraydescs", lib.arraydescs),
+		("custdata", lib.custdata),
+		("cdguids", b""),
+	]
+	offset = segments_offset
+	layout = []
+	for (_, data) in segments:
+		size = data if isinstance(data, int) else len(data)
+		layout.append((offset if size else NONE, size))
+		offset += size
+	data_offset = offset
+
+	# Member blocks follow the segments.
+	memoffsets = []
+	blob = b""
+	for data in typeinfo_data:
+		memoffsets.append(data_offset + len(blob))
+		blob += data or b""
+
+	typeinfos = b""
+	for (info, memoffset) in zip(lib.infos, memoffsets):
+		cfuncs, cvars = info.get("cfuncs", 0), info.get("cvars", 0)
+		typeinfos += struct.pack("<iiiiiiiiiiiiiiiiiiihhiiiii",
+			info["kind"] | 4 << 11, memoffset, cfuncs * 0x40, (cfuncs - 1) * 0x38 if cfuncs else NONE, 3, 0,
+			cvars << 16 | cfuncs, 0, 0, 0, 0,
+			info["guid"], info.get("flags", 0), info["name"], info.get("version", 0), info.get("doc", NONE),
+			0, 0, NONE,
+			info.get("impltypes", 0), info.get("vft", 0) * 4, info.get("size", 0),
+			info.get("datatype1", NONE), info.get("datatype2", 0), 0, NONE)
+	assert len(typeinfos) == typeinfo_tab_len
+
+	header = struct.pack("<iiiiiiiiiiiiiiiiiiiii",
+		0x5446534D, 0x00010002, libid, 0x409, 0, 0x41, 1, 8, count, libdoc, 0, 0,
+		lib.name_count, len(lib.names), libname, NONE, NONE, 0x20, 0x80, NONE, len(lib.impinfos) // 12)
+	assert len(header) == header_size
+	header += b"".join(struct.pack("<i", memoffset) for memoffset in memoffsets)
+
+	segdir = b"".join(struct.pack("<iiii", offset, size, NONE, 0x0F) for (offset, size) in layout)
+	segdir += struct.pack("<iiii", NONE, 0, NONE, 0x0F) * 2
+
+	body = typeinfos
+	for (_, data) in segments[1:]:
+		body += data
+	with open(path, "wb") as f:
+		f.write(header + segdir + body + blob)
+
+
+def shapes():
+	lib = Lib()
+	header = (
+		lib.guid("6d5140c3-7436-11ce-8034-00aa006009fa", -2),
+		lib.name("ShapesLib"),
+		lib.string("Shapes 1.0 Type Library"),
+	)
+
+	stdole = lib.impfile("00020430-0000-0000-c000-000000000046", "stdole2.tlb")
+	iunknown = lib.import_type(stdole, TKIND_INTERFACE, "00000000-0000-0000-c000-000000000046")
+	idispatch = lib.import_type(stdole, TKIND_INTERFACE, "00020400-0000-0000-c000-000000000046")
+
+	# Type info hreftypes are their offset in the type info table.
+	SHAPE_KIND, EXTENT, SHAPE_ID, ISHAPE, ISHAPES, ISHAPE_INFO, DSHAPE_EVENTS, SHAPES = [i * TYPEINFO_SIZE for i in range(8)]
+	data = []
+
+	lib.typeinfo(kind=TKIND_ENUM, name=lib.name("ShapeKind", SHAPE_KIND), guid=lib.guid("6d5140c5-7436-11ce-8034-00aa006009fa", SHAPE_KIND),
+		cvars=3, size=4)
+	data.append(funcs_block(lib, [], [
+		dict(name="SK_CIRCLE", datatype=inline(VT_I4), varkind=VAR_CONST, value=lib.value(VT_I4, 1)),
+		dict(name="SK_RECT", datatype=inline(VT_I4), varkind=VAR_CONST, value=lib.value(VT_I4, 2)),
+		dict(name="SK_NONE", datatype=inline(VT_I4), varkind=VAR_CONST, value=lib.value(VT_I4, -1)),
+	]))
+
+	lib.typeinfo(kind=TKIND_RECORD, name=lib.name("Extent", EXTENT), guid=lib.guid("6d5140c6-7436-11ce-8034-00aa006009fa", EXTENT),
+		cvars=3, size=12, doc=lib.string("Size of a shape."))
+	data.append(funcs_block(lib, [], [
+		dict(name="cx", datatype=inline(VT_I4), varkind=VAR_PERINSTANCE, value=0),
+		dict(name="cy", datatype=inline(VT_I4), varkind=VAR_PERINSTANCE, value=4),
+		dict(name="tag", datatype=lib.carray(inline(VT_UI1), [4]), varkind=VAR_PERINSTANCE, value=8),
+	]))
+
+	lib.typeinfo(kind=TKIND_ALIAS, name=lib.name("SHAPEID", SHAPE_ID), guid=NONE, datatype1=inline(VT_UI4), size=4)
+	data.append(None)
+
+	p_shape_kind = lib.typedesc(VT_PTR, lib.typedesc(VT_USERDEFINED, SHAPE_KIND))
+	p_bstr = lib.typedesc(VT_PTR, inline(VT_BSTR))
+	p_extent = lib.typedesc(VT_PTR, lib.typedesc(VT_USERDEFINED, EXTENT))
+	p_variant = lib.typedesc(VT_PTR, inline(VT_VARIANT))
+	lib.typeinfo(kind=TKIND_INTERFACE, name=lib.name("IShape", ISHAPE), guid=lib.guid("6d5140c1-7436-11ce-8034-00aa006009fa", ISHAPE),
+		cfuncs=5, impltypes=1, vft=8, datatype1=iunknown, datatype2=3 << 16 | 1, doc=lib.string("A shape."), flags=0x100)
+	data.append(funcs_block(lib, [
+		dict(name="Kind", ret=inline(VT_HRESULT), funckind=FUNC_PUREVIRTUAL, invkind=INVOKE_PROPERTYGET, vft=3,
+			params=[(p_shape_kind, "pKind", PARAMFLAG_FOUT | PARAMFLAG_FRETVAL)]),
+		dict(name="Name", ret=inline(VT_HRESULT), funckind=FUNC_PUREVIRTUAL, invkind=INVOKE_PROPERTYGET, vft=4,
+			params=[(p_bstr, "pName", PARAMFLAG_FOUT | PARAMFLAG_FRETVAL)]),
+		dict(name="Name", ret=inline(VT_HRESULT), funckind=FUNC_PUREVIRTUAL, invkind=INVOKE_PROPERTYPUT, vft=5,
+			params=[(inline(VT_BSTR), "name", PARAMFLAG_FIN)]),
+		dict(name="GetExtent", ret=inline(VT_HRESULT), funckind=FUNC_PUREVIRTUAL, invkind=INVOKE_FUNC, vft=6,
+			params=[(p_extent, "pExtent", PARAMFLAG_FOUT)], doc="Returns the extent."),
+		dict(name="Describe", ret=inline(VT_HRESULT), funckind=FUNC_PUREVIRTUAL, invkind=INVOKE_FUNC, vft=7,
+			params=[(p_variant, "options", PARAMFLAG_FIN), (p_variant, "pResult", PARAMFLAG_FOUT)]),
+	], []))
+
+	p_ui4 = lib.typedesc(VT_PTR, inline(VT_UI4))
+	p_shape = lib.typedesc(VT_PTR, lib.typedesc(VT_USERDEFINED, ISHAPE))
+	pp_shape = lib.typedesc(VT_PTR, p_shape)
+	lib.typeinfo(kind=TKIND_INTERFACE, name=lib.name("IShapes", ISHAPES), guid=lib.guid("6d5140c2-7436-11ce-8034-00aa006009fa", ISHAPES),
+		cfuncs=3, impltypes=1, vft=11, datatype1=ISHAPE, datatype2=8 << 16 | 2)
+	data.append(funcs_block(lib, [
+		dict(name="Count", ret=inline(VT_HRESULT), funckind=FUNC_PUREVIRTUAL, invkind=INVOKE_FUNC, vft=8,
+			params=[(p_ui4, "pCount", PARAMFLAG_FOUT | PARAMFLAG_FRETVAL)]),
+		dict(name="Item", ret=inline(VT_HRESULT), funckind=FUNC_PUREVIRTUAL, invkind=INVOKE_FUNC, vft=9,
+			params=[(inline(VT_UI4), "index", PARAMFLAG_FIN), (pp_shape, "ppShape", PARAMFLAG_FOUT | PARAMFLAG_FRETVAL)]),
+		dict(name="Add", ret=inline(VT_HRESULT), funckind=FUNC_PUREVIRTUAL, invkind=INVOKE_FUNC, vft=10,
+			params=[(p_shape, "shape", PARAMFLAG_FIN)]),
+	], []))
+
+	# Dual interfaces are stored as dispatch type infos, their functions keep their vtbl offsets.
+	p_r8 = lib.typedesc(VT_PTR, inline(VT_R8))
+	lib.typeinfo(kind=TKIND_DISPATCH, name=lib.name("IShapeInfo", ISHAPE_INFO), guid=lib.guid("6d5140c7-7436-11ce-8034-00aa006009fa", ISHAPE_INFO),
+		cfuncs=1, impltypes=1, vft=8, flags=0x1040 | 0x100)
+	data.append(funcs_block(lib, [
+		dict(name="Area", ret=inline(VT_HRESULT), funckind=FUNC_DISPATCH, invkind=INVOKE_PROPERTYGET, vft=7, memid=1,
+			params=[(p_r8, "pArea", PARAMFLAG_FOUT | PARAMFLAG_FRETVAL)]),
+	], []))
+
+	lib.typeinfo(kind=TKIND_DISPATCH, name=lib.name("DShapeEvents", DSHAPE_EVENTS), guid=lib.guid("6d5140c8-7436-11ce-8034-00aa006009fa", DSHAPE_EVENTS),
+		cfuncs=1, impltypes=1, vft=7, flags=0x1000)
+	data.append(funcs_block(lib, [
+		dict(name="OnChanged", ret=inline(VT_VOID), funckind=FUNC_DISPATCH, invkind=INVOKE_FUNC, vft=0, memid=1,
+			params=[(inline(VT_I2), "what", PARAMFLAG_FIN)]),
+	], []))
+
+	lib.typeinfo(kind=TKIND_COCLASS, name=lib.name("Shapes", SHAPES), guid=lib.guid("6d5140c4-7436-11ce-8034-00aa006009fa", SHAPES),
+		impltypes=3, flags=0x2, datatype1=lib.reftab([(ISHAPES, 1), (ISHAPE_INFO, 0), (DSHAPE_EVENTS, 3)]))
+	data.append(None)
+
+	write(lib, os.path.join(os.path.dirname(os.path.abspath(__file__)), "shapes.tlb"), header, data)
+
+
+if __name__ == "__main__":
+	shapes()
@@ -0,0 +1,90 @@
+// Generated from IDL, do not edit.
+
+pub type ShapeKind = i32;
+pub const SK_CIRCLE: ShapeKind = 1;
+pub const SK_RECT: ShapeKind = 2;
+pub const SK_NONE: ShapeKind = -1;
+
+/// Size of a shape.
+#[repr(C)]
+pub struct Extent {
+	pub cx: i32,
+	pub cy: i32,
+	pub tag: [u8; 4],
+}
+
+pub type SHAPEID = u32;
+
+com_interface! {
+	/// A shape.
+	interface IShape(IShapeVtbl): IUnknown(IUnknownVtbl);
+	"6d5140c1-7436-11ce-8034-00aa006009fa"
+	pub get_Kind: unsafe extern "system" fn(
+		This: *mut IShape,
+		pKind: *mut ShapeKind,
+	) -> HRESULT,
+	pub get_Name: unsafe extern "system" fn(
+		This: *mut IShape,
+		pName: *mut BSTR,
+	) -> HRESULT,
+	pub put_Name: unsafe extern "system" fn(
+		This: *mut IShape,
+		name: BSTR,
+	) -> HRESULT,
+	pub GetExtent: unsafe extern "system" fn(
+		This: *mut IShape,
+		pExtent: *mut Extent,
+	) -> HRESULT,
+	pub Describe: unsafe extern "system" fn(
+		This: *mut IShape,
+		options: *mut VARIANT,
+		pResult: *mut VARIANT,
+	) -> HRESULT,
+}
+com_ptr! {
+	/// A shape.
+	pub struct IShapePtr(IShape): IUnknownPtr;
+}
+
+com_interface! {
+	interface IShapes(IShapesVtbl): IShape(IShapeVtbl);
+	"6d5140c2-7436-11ce-8034-00aa006009fa"
+	pub Count: unsafe extern "system" fn(
+		This: *mut IShapes,
+		pCount: *mut u32,
+	) -> HRESULT,
+	pub Item: unsafe extern "system" fn(
+		This: *mut IShapes,
+		index: u32,
+		ppShape: *mut *mut IShape,
+	) -> HRESULT,
+	pub Add: unsafe extern "system" fn(
+		This: *mut IShapes,
+		shape: *mut IShape,
+	) -> HRESULT,
+}
+com_ptr! {
+	pub struct IShapesPtr(IShapes): IShapePtr;
+}
+
+com_interface! {
+	interface IShapeInfo(IShapeInfoVtbl): IDispatch(IDispatchVtbl);
+	"6d5140c7-7436-11ce-8034-00aa006009fa"
+	pub get_Area: unsafe extern "system" fn(
+		This: *mut IShapeInfo,
+		pArea: *mut f64,
+	) -> HRESULT,
+}
+com_ptr! {
+	pub struct IShapeInfoPtr(IShapeInfo): IDispatchPtr;
+}
+
+com_interface! {
+	interface DShapeEvents(DShapeEventsVtbl): IDispatch(IDispatchVtbl);
+	"6d5140c8-7436-11ce-8034-00aa006009fa"
+}
+com_ptr! {
+	pub struct DShapeEventsPtr(DShapeEvents): IDispatchPtr;
+}
+
+com_class!(_extern Shapes, "6d5140c4-7436-11ce-8034-00aa006009fa", IShapes, IShapeInfo);
@@ -0,0 +1,165 @@
+/*!
+Converts the type library to the IDL syntax tree.
+*/
+
+use ::com_idl::ast::*;
+
+use super::*;
+
+impl TypeLib {
+	/// Converts the type infos to IDL items for the [`Generator`](../com_idl/struct.Generator.html).
+	///
+	/// Modules and unions have no equivalent and are skipped.
+	pub fn to_items(&self) -> Result<Vec<Item>, Error> {
+		let mut items = Vec::new();
+		for info in &self.types {
+			let mut attrs = Vec::new();
+			if let Some(ref guid) = info.guid {
+				let uuid = guid.to_string();
+				attrs.push(attr("uuid", Some(uuid.trim_matches(|c| c == '{' || c == '}').to_lowercase())));
+			}
+			if let Some(ref doc) = info.doc {
+				attrs.push(attr("helpstring", Some(format!("\"{}\"", doc))));
+			}
+			let name = info.name.clone();
+			items.push(match info.kind {
+				TypeKind::Interface | TypeKind::Dispatch => Item::Interface(self.interface(info, attrs)?),
+				TypeKind::Coclass => {
+					let mut interfaces = Vec::new();
+					for imp in info.impls.iter().filter(|imp| !imp.is_source()) {
+						interfaces.push(self.name(&imp.ty)?);
+					}
+					Item::Coclass(Coclass { attrs, name, interfaces })
+				},
+				TypeKind::Enum => {
+					let variants = info.vars.iter().map(|var| Enumerator {
+						name: var.name.clone(),
+						value: match var.value {
+							VarValue::Const(value) => value,
+							VarValue::Offset(offset) => offset as i64,
+						},
+					}).collect();
+					Item::Enum(Enum { attrs, tag: Some(name), variants })
+				},
+				TypeKind::Record => {
+					let mut fields = Vec::new();
+					for var in &info.vars {
+						let (ty, dims) = match var.ty {
+							TypeDesc::CArray(ref elem, ref dims) => (self.ty(elem)?, dims.iter().map(|dim| dim.to_string()).collect()),
+							ref ty => (self.ty(ty)?, Vec::new()),
+						};
+						fields.push(Field { ty, name: var.name.clone(), dims });
+					}
+					Item::Struct(Struct { attrs, tag: Some(name), fields })
+				},
+				TypeKind::Alias => {
+					let target = match info.alias {
+						Some(ref ty) => self.ty(ty)?,
+						None => continue,
+					};
+					Item::Typedef(Typedef { attrs, target: Target::Type(target), names: vec![Declarator { name, pointers: 0 }] })
+				},
+				TypeKind::Module | TypeKind::Union => continue,
+			});
+		}
+		Ok(items)
+	}
+
+	fn interface(&self, info: &TypeInfo, attrs: Vec<Attribute>) -> Result<Interface, Error> {
+		let base = match info.impls.first() {
+			Some(imp) => Some(self.name(&imp.ty)?),
+			None if info.kind == TypeKind::Dispatch => Some(String::from("IDispatch")),
+			None => None,
+		};
+		// The members of dispinterfaces are not in the vtbl.
+		let mut funcs: Vec<&FuncDesc> = Vec::new();
+		if info.kind == TypeKind::Interface || info.is_dual() {
+			funcs.extend(&info.funcs);
+			funcs.sort_by_key(|func| func.vtbl_index);
+		}
+		let mut methods = Vec::new();
+		for func in funcs {
+			let mut attrs = Vec::new();
+			match func.invoke {
+				InvokeKind::Func => (),
+				InvokeKind::PropertyGet => attrs.push(attr("propget", None)),
+				InvokeKind::PropertyPut => attrs.push(attr("propput", None)),
+				InvokeKind::PropertyPutRef => attrs.push(attr("propputref", None)),
+			}
+			if let Some(ref doc) = func.doc {
+				attrs.push(attr("helpstring", Some(format!("\"{}\"", doc))));
+			}
+			let mut params = Vec::new();
+			for param in &func.params {
+				let mut attrs = Vec::new();
+				for &(flag, name) in &[(flags::PARAMFLAG_FIN, "in"), (flags::PARAMFLAG_FOUT, "out"), (flags::PARAMFLAG_FRETVAL, "retval")] {
+					if param.flags & flag != 0 {
+						attrs.push(attr(name, None));
+					}
+				}
+				params.push(Param { attrs, ty: self.ty(&param.ty)?, name: param.name.clone() });
+			}
+			methods.push(Method { attrs, ret: self.ty(&func.ret)?, name: func.name.clone(), params });
+		}
+		Ok(Interface { attrs, name: info.name.clone(), base, methods })
+	}
+
+	fn name(&self, ty: &TypeRef) -> Result<String, Error> {
+		match (self.type_name(ty), *ty) {
+			(Some(name), _) => Ok(name.to_string()),
+			(None, TypeRef::Import(guid)) => Err(Error::UnknownImport(guid)),
+			(None, TypeRef::Local(_)) => Err(Error::Malformed("hreftype out of range")),
+		}
+	}
+
+	/// Translates the type descriptor to its IDL spelling.
+	fn ty(&self, desc: &TypeDesc) -> Result<Type, Error> {
+		Ok(match *desc {
+			TypeDesc::Base(vt) => {
+				let (name, pointers) = match vt {
+					2 => ("short", 0),
+					3 => ("long", 0),
+					4 => ("float", 0),
+					5 => ("double", 0),
+					6 => ("CY", 0),
+					7 => ("DATE", 0),
+					8 => ("BSTR", 0),
+					9 => ("IDispatch", 1),
+					10 => ("SCODE", 0),
+					11 => ("VARIANT_BOOL", 0),
+					12 => ("VARIANT", 0),
+					13 => ("IUnknown", 1),
+					14 => ("DECIMAL", 0),
+					16 => ("char", 0),
+					17 => ("unsigned char", 0),
+					18 => ("unsigned short", 0),
+					19 => ("unsigned long", 0),
+					20 => ("hyper", 0),
+					21 => ("unsigned hyper", 0),
+					22 => ("int", 0),
+					23 => ("unsigned int", 0),
+					24 => ("void", 0),
+					25 => ("HRESULT", 0),
+					30 => ("LPSTR", 0),
+					31 => ("LPWSTR", 0),
+					37 => ("INT_PTR", 0),
+					38 => ("UINT_PTR", 0),
+					_ => return Err(Error::Malformed("unsupported vartype")),
+				};
+				Type { pointers, ..Type::new(name) }
+			},
+			TypeDesc::Ptr(ref pointee) => {
+				let ty = self.ty(pointee)?;
+				Type { pointers: ty.pointers + 1, ..ty }
+			},
+			// Safe arrays are passed as `SAFEARRAY *` regardless of their element type.
+			TypeDesc::SafeArray(_) => Type { pointers: 1, ..Type::new("SAFEARRAY") },
+			TypeDesc::CArray(..) => return Err(Error::Malformed("array outside of a record")),
+			TypeDesc::UserDefined(ref ty) => Type::new(&self.name(ty)?),
+		})
+	}
+}
+
+fn attr(name: &str, args: Option<String>) -> Attribute {
+	Attribute { name: name.to_string(), args }
+}
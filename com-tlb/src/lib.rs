@@ -0,0 +1,422 @@
+/*!
+Reads type libraries and generates COM interface definitions from them.
+
+Parses the MSFT binary format written by MIDL, `.tlb` files and the `TYPELIB` resources embedded in DLLs, without relying on `oleaut32`.
+It runs on any platform.
+
+The [`TypeLib`](struct.TypeLib.html) enumerates the interfaces, dispinterfaces, coclasses, enums, records and aliases with their GUIDs and members.
+These convert to the [`com_idl`](../com_idl/index.html) syntax tree to emit the equivalent `com_interface!`, `com_ptr!` and `com_class!` definitions.
+
+Interfaces keep their methods in vtbl order. Dual interfaces derive from their base interface or `IDispatch`,
+dispinterfaces derive from `IDispatch` without methods as their members are only reachable through `IDispatch::Invoke`.
+Types imported from other type libraries are referred to by name, which is known for the `stdole2.tlb` interfaces.
+
+Use it from a build script or run the `com-tlb` binary on a type library and check in its output.
+
+# Examples
+
+```
+extern crate com_tlb;
+
+let data = include_bytes!("../fixtures/shapes.tlb");
+let lib = com_tlb::TypeLib::parse(data).unwrap();
+assert_eq!(lib.name, "ShapesLib");
+
+let rust = com_tlb::translate(data).unwrap();
+assert!(rust.contains("interface IShapes(IShapesVtbl): IShape(IShapeVtbl);"));
+assert!(rust.contains("pub struct IShapesPtr(IShapes): IShapePtr;"));
+assert!(rust.contains("com_class!(_extern Shapes, \"6d5140c4-7436-11ce-8034-00aa006009fa\", IShapes, IShapeInfo);"));
+```
+*/
+
+#![allow(clippy::tabs_in_doc_comments)]
+
+extern crate com_idl;
+#[cfg_attr(test, macro_use)]
+extern crate com_sys;
+
+#[cfg(test)]
+#[macro_use]
+extern crate com_types;
+
+use ::std::{error, fmt};
+
+use ::com_sys::Guid;
+
+mod convert;
+mod reader;
+
+/// Reads the type library and generates the Rust bindings with the default [`Generator`](../com_idl/struct.Generator.html).
+pub fn translate(data: &[u8]) -> Result<String, Error> {
+	let lib = TypeLib::parse(data)?;
+	Ok(::com_idl::Generator::new().generate(&lib.to_items()?))
+}
+
+/// Type library.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypeLib {
+	pub name: String,
+	pub guid: Guid,
+	/// Major and minor version.
+	pub version: (u16, u16),
+	pub doc: Option<String>,
+	/// Type infos in their order in the library, local [`TypeRef`](enum.TypeRef.html)s index into it.
+	pub types: Vec<TypeInfo>,
+}
+impl TypeLib {
+	/// Parses a type library in the MSFT format.
+	pub fn parse(data: &[u8]) -> Result<TypeLib, Error> {
+		reader::parse(data)
+	}
+	/// Finds a type info by name.
+	pub fn find(&self, name: &str) -> Option<&TypeInfo> {
+		self.types.iter().find(|info| info.name == name)
+	}
+	/// Returns the name of a referenced type, `None` for types imported from other libraries which are not known.
+	pub fn type_name(&self, ty: &TypeRef) -> Option<&str> {
+		match *ty {
+			TypeRef::Local(index) => self.types.get(index).map(|info| info.name.as_str()),
+			TypeRef::Import(ref guid) => KNOWN_IMPORTS.iter().find(|known| known.0 == *guid).map(|known| known.1),
+		}
+	}
+}
+
+/// Interfaces defined by `stdole2.tlb` commonly imported by other type libraries.
+const KNOWN_IMPORTS: &[(Guid, &str)] = &[
+	(Guid::new(0x00000000, 0x0000, 0x0000, [0xC0, 0, 0, 0, 0, 0, 0, 0x46]), "IUnknown"),
+	(Guid::new(0x00020400, 0x0000, 0x0000, [0xC0, 0, 0, 0, 0, 0, 0, 0x46]), "IDispatch"),
+	(Guid::new(0x00020404, 0x0000, 0x0000, [0xC0, 0, 0, 0, 0, 0, 0, 0x46]), "IEnumVARIANT"),
+	(Guid::new(0xBEF6E002, 0xA874, 0x101A, [0x8B, 0xBA, 0x00, 0xAA, 0x00, 0x30, 0x0C, 0xAB]), "IFontDisp"),
+	(Guid::new(0x7BF80981, 0xBF32, 0x101A, [0x8B, 0xBB, 0x00, 0xAA, 0x00, 0x30, 0x0C, 0xAB]), "IPictureDisp"),
+];
+
+/// Kind of a type info, `TYPEKIND`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum TypeKind {
+	Enum,
+	Record,
+	Module,
+	Interface,
+	Dispatch,
+	Coclass,
+	Alias,
+	Union,
+}
+
+/// Type flags, `TYPEFLAGS`.
+pub mod flags {
+	pub const TYPEFLAG_FAPPOBJECT: u16 = 0x1;
+	pub const TYPEFLAG_FCANCREATE: u16 = 0x2;
+	pub const TYPEFLAG_FHIDDEN: u16 = 0x10;
+	pub const TYPEFLAG_FDUAL: u16 = 0x40;
+	pub const TYPEFLAG_FOLEAUTOMATION: u16 = 0x100;
+	pub const TYPEFLAG_FDISPATCHABLE: u16 = 0x1000;
+
+	pub const IMPLTYPEFLAG_FDEFAULT: i32 = 0x1;
+	pub const IMPLTYPEFLAG_FSOURCE: i32 = 0x2;
+
+	pub const PARAMFLAG_FIN: u16 = 0x1;
+	pub const PARAMFLAG_FOUT: u16 = 0x2;
+	pub const PARAMFLAG_FLCID: u16 = 0x4;
+	pub const PARAMFLAG_FRETVAL: u16 = 0x8;
+	pub const PARAMFLAG_FOPT: u16 = 0x10;
+}
+
+/// Type description, eg. an interface, enum or record.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypeInfo {
+	pub kind: TypeKind,
+	pub name: String,
+	/// Aliases and records may be without GUID.
+	pub guid: Option<Guid>,
+	pub doc: Option<String>,
+	/// The [`flags`](flags/index.html) `TYPEFLAG_*` bits.
+	pub flags: u16,
+	/// Major and minor version.
+	pub version: (u16, u16),
+	/// Size of an instance in bytes.
+	pub size: u32,
+	/// Base interface of an interface or the interfaces of a coclass.
+	pub impls: Vec<ImplType>,
+	pub funcs: Vec<FuncDesc>,
+	pub vars: Vec<VarDesc>,
+	/// Aliased type of an alias.
+	pub alias: Option<TypeDesc>,
+}
+impl TypeInfo {
+	/// Returns if this is a dual interface, a dispinterface whose methods are also in its vtbl.
+	pub fn is_dual(&self) -> bool {
+		self.flags & flags::TYPEFLAG_FDUAL != 0
+	}
+}
+
+/// Reference to a type info.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum TypeRef {
+	/// Index in the [`types`](struct.TypeLib.html#structfield.types) of this library.
+	Local(usize),
+	/// Type imported from another library by its GUID.
+	Import(Guid),
+}
+
+/// Interface implemented by a coclass or inherited by an interface.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ImplType {
+	pub ty: TypeRef,
+	/// The [`flags`](flags/index.html) `IMPLTYPEFLAG_*` bits.
+	pub flags: i32,
+}
+impl ImplType {
+	/// Returns if the interface is called by the coclass rather than implemented.
+	pub fn is_source(&self) -> bool {
+		self.flags & flags::IMPLTYPEFLAG_FSOURCE != 0
+	}
+}
+
+/// Type of a member, parameter or alias, `TYPEDESC`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TypeDesc {
+	/// Builtin `VARTYPE`, eg. `VT_I4` or `VT_BSTR`.
+	Base(u16),
+	Ptr(Box<TypeDesc>),
+	SafeArray(Box<TypeDesc>),
+	/// Fixed size array with the number of elements of each dimension.
+	CArray(Box<TypeDesc>, Vec<u32>),
+	UserDefined(TypeRef),
+}
+
+/// Kind of a function, `FUNCKIND`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum FuncKind {
+	Virtual,
+	PureVirtual,
+	NonVirtual,
+	Static,
+	Dispatch,
+}
+
+/// How a function is invoked, `INVOKEKIND`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum InvokeKind {
+	Func,
+	PropertyGet,
+	PropertyPut,
+	PropertyPutRef,
+}
+
+/// Function of an interface, `FUNCDESC`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FuncDesc {
+	pub name: String,
+	pub memid: i32,
+	pub kind: FuncKind,
+	pub invoke: InvokeKind,
+	/// Index of its vtbl slot, meaningless for the members of dispinterfaces which are not dual.
+	pub vtbl_index: usize,
+	pub ret: TypeDesc,
+	pub params: Vec<ParamDesc>,
+	pub doc: Option<String>,
+}
+
+/// Function parameter.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParamDesc {
+	pub name: Option<String>,
+	pub ty: TypeDesc,
+	/// The [`flags`](flags/index.html) `PARAMFLAG_*` bits.
+	pub flags: u16,
+}
+
+/// Variable of a record or constant of an enum, `VARDESC`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VarDesc {
+	pub name: String,
+	pub memid: i32,
+	pub ty: TypeDesc,
+	pub value: VarValue,
+	pub doc: Option<String>,
+}
+
+/// Offset of a record field or value of a constant.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum VarValue {
+	Offset(u32),
+	Const(i64),
+}
+
+/// Error reading a type library.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Error {
+	/// Not an MSFT type library, with the magic found instead.
+	BadMagic(u32),
+	/// Reading past the end of the data at the given offset.
+	Truncated(usize),
+	/// Inconsistent structure.
+	Malformed(&'static str),
+	/// Type imported from another library whose name is not known.
+	UnknownImport(Guid),
+}
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Error::BadMagic(magic) => write!(f, "not an MSFT type library, magic {:#010x}", magic),
+			Error::Truncated(offset) => write!(f, "unexpected end of data at offset {:#x}", offset),
+			Error::Malformed(msg) => write!(f, "malformed type library: {}", msg),
+			Error::UnknownImport(ref guid) => write!(f, "unknown imported type {}", guid),
+		}
+	}
+}
+impl error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const SHAPES_TLB: &[u8] = include_bytes!("../fixtures/shapes.tlb");
+
+	#[test]
+	fn shapes() {
+		check_shapes(&TypeLib::parse(SHAPES_TLB).unwrap());
+	}
+	/// Checks a type library compiled from `fixtures/shapes.idl`.
+	fn check_shapes(lib: &TypeLib) {
+		assert_eq!(lib.name, "ShapesLib");
+		assert_eq!(lib.guid, "6d5140c3-7436-11ce-8034-00aa006009fa".parse().unwrap());
+		assert_eq!(lib.version, (1, 0));
+		assert_eq!(lib.doc.as_deref(), Some("Shapes 1.0 Type Library"));
+
+		let types: Vec<_> = lib.types.iter().map(|info| (info.name.as_str(), info.kind, info.guid.map(|guid| guid.to_string()))).collect();
+		assert_eq!(types, [
+			("ShapeKind", TypeKind::Enum, Some(String::from("{6D5140C5-7436-11CE-8034-00AA006009FA}"))),
+			("Extent", TypeKind::Record, Some(String::from("{6D5140C6-7436-11CE-8034-00AA006009FA}"))),
+			("SHAPEID", TypeKind::Alias, None),
+			("IShape", TypeKind::Interface, Some(String::from("{6D5140C1-7436-11CE-8034-00AA006009FA}"))),
+			("IShapes", TypeKind::Interface, Some(String::from("{6D5140C2-7436-11CE-8034-00AA006009FA}"))),
+			("IShapeInfo", TypeKind::Dispatch, Some(String::from("{6D5140C7-7436-11CE-8034-00AA006009FA}"))),
+			("DShapeEvents", TypeKind::Dispatch, Some(String::from("{6D5140C8-7436-11CE-8034-00AA006009FA}"))),
+			("Shapes", TypeKind::Coclass, Some(String::from("{6D5140C4-7436-11CE-8034-00AA006009FA}"))),
+		]);
+
+		let kind = lib.find("ShapeKind").unwrap();
+		let values: Vec<_> = kind.vars.iter().map(|var| (var.name.as_str(), var.value)).collect();
+		assert_eq!(values, [("SK_CIRCLE", VarValue::Const(1)), ("SK_RECT", VarValue::Const(2)), ("SK_NONE", VarValue::Const(-1))]);
+
+		let extent = lib.find("Extent").unwrap();
+		assert_eq!(extent.size, 12);
+		assert_eq!(extent.vars[2].ty, TypeDesc::CArray(Box::new(TypeDesc::Base(17)), vec![4]));
+		assert_eq!(extent.vars[2].value, VarValue::Offset(8));
+		assert_eq!(lib.find("SHAPEID").unwrap().alias, Some(TypeDesc::Base(19)));
+
+		let shape = lib.find("IShape").unwrap();
+		assert_eq!(lib.type_name(&shape.impls[0].ty), Some("IUnknown"));
+		let funcs: Vec<_> = shape.funcs.iter().map(|func| (func.name.as_str(), func.invoke, func.vtbl_index)).collect();
+		assert_eq!(funcs, [
+			("Kind", InvokeKind::PropertyGet, 3),
+			("Name", InvokeKind::PropertyGet, 4),
+			("Name", InvokeKind::PropertyPut, 5),
+			("GetExtent", InvokeKind::Func, 6),
+			("Describe", InvokeKind::Func, 7),
+		]);
+		assert_eq!(shape.funcs[3].doc.as_deref(), Some("Returns the extent."));
+		let kind_param = &shape.funcs[0].params[0];
+		assert_eq!(kind_param.name.as_deref(), Some("pKind"));
+		assert_eq!(kind_param.flags, flags::PARAMFLAG_FOUT | flags::PARAMFLAG_FRETVAL);
+		assert_eq!(kind_param.ty, TypeDesc::Ptr(Box::new(TypeDesc::UserDefined(TypeRef::Local(0)))));
+
+		let shapes = lib.find("IShapes").unwrap();
+		assert_eq!(shapes.impls[0].ty, TypeRef::Local(3));
+		assert_eq!(shapes.funcs[1].params[1].ty, TypeDesc::Ptr(Box::new(TypeDesc::Ptr(Box::new(TypeDesc::UserDefined(TypeRef::Local(3)))))));
+
+		let info = lib.find("IShapeInfo").unwrap();
+		assert!(info.is_dual());
+		assert_eq!(info.funcs[0].kind, FuncKind::Dispatch);
+		assert!(!lib.find("DShapeEvents").unwrap().is_dual());
+
+		let class = lib.find("Shapes").unwrap();
+		let impls: Vec<_> = class.impls.iter().map(|imp| (lib.type_name(&imp.ty).unwrap(), imp.is_source())).collect();
+		assert_eq!(impls, [("IShapes", false), ("IShapeInfo", false), ("DShapeEvents", true)]);
+	}
+	#[test]
+	fn fixtures() {
+		assert_eq!(translate(SHAPES_TLB).unwrap(), include_str!("../fixtures/shapes.rs"));
+	}
+	// The fixture is still written by `mktlb.py` rather than a compiler, this checks it against the same library compiled by widl.
+	// Run with `cargo test -p com-tlb -- --ignored`, the `WIDL` environment variable overrides the compiler.
+	#[test]
+	#[ignore]
+	fn widl() {
+		use ::std::{env, fs};
+		use ::std::process::Command;
+		let widl = env::var("WIDL").unwrap_or_else(|_| String::from("widl"));
+		let path = env::temp_dir().join("com-tlb-shapes.tlb");
+		let status = Command::new(widl)
+			.arg("-t")
+			.arg("-o").arg(&path)
+			.arg(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/shapes.idl"))
+			.status().unwrap();
+		assert!(status.success());
+		let data = fs::read(&path).unwrap();
+		let _ = fs::remove_file(&path);
+		check_shapes(&TypeLib::parse(&data).unwrap());
+		assert_eq!(translate(&data).unwrap(), include_str!("../fixtures/shapes.rs"));
+	}
+	#[test]
+	fn errors() {
+		assert_eq!(TypeLib::parse(b"SLTG\0\0\0\0"), Err(Error::BadMagic(0x47544C53)));
+		assert_eq!(TypeLib::parse(&SHAPES_TLB[..0x40]), Err(Error::Truncated(0x40)));
+		// The segments are checked up front, the type info table starts after the directory.
+		assert_eq!(TypeLib::parse(&SHAPES_TLB[..0x200]), Err(Error::Truncated(0x164)));
+	}
+
+	// The generated code compiles given the names it expects in scope.
+	#[allow(dead_code, non_camel_case_types, non_snake_case, clippy::upper_case_acronyms)]
+	mod shapes {
+		use ::com_sys::HRESULT;
+		use ::com_sys::unknown::{IUnknown, IUnknownVtbl};
+		use ::com_types::unknown::IUnknownPtr;
+		type BSTR = *mut u16;
+		#[repr(C)]
+		pub struct VARIANT([u64; 3]);
+
+		com_interface! {
+			interface IDispatch(IDispatchVtbl): IUnknown(IUnknownVtbl);
+			"00020400-0000-0000-c000-000000000046"
+			pub GetTypeInfoCount: usize,
+			pub GetTypeInfo: usize,
+			pub GetIDsOfNames: usize,
+			pub Invoke: usize,
+		}
+		com_ptr! {
+			pub struct IDispatchPtr(IDispatch): IUnknownPtr;
+		}
+
+		include!("../fixtures/shapes.rs");
+
+		#[test]
+		fn layout() {
+			use ::std::mem;
+			use ::com_sys::{ComClass, ComInterface};
+			assert_eq!(<IShapes as ComInterface>::IID, "6d5140c2-7436-11ce-8034-00aa006009fa".parse().unwrap());
+			assert_eq!(<Shapes as ComClass>::CLSID, "6d5140c4-7436-11ce-8034-00aa006009fa".parse().unwrap());
+			assert_eq!(mem::size_of::<IShapesVtbl<IShapes>>(), 11 * mem::size_of::<usize>());
+			assert_eq!(mem::size_of::<IShapeInfoVtbl<IShapeInfo>>(), 8 * mem::size_of::<usize>());
+			assert_eq!(mem::size_of::<DShapeEventsVtbl<DShapeEvents>>(), 7 * mem::size_of::<usize>());
+			assert_eq!(mem::size_of::<Extent>(), 12);
+		}
+		#[test]
+		fn slots() {
+			use ::com_sys::ComVtbl;
+			use super::super::TypeLib;
+			use super::SHAPES_TLB;
+			// The vtbl slots agree with the type library.
+			let lib = TypeLib::parse(SHAPES_TLB).unwrap();
+			for func in &lib.find("IShapes").unwrap().funcs {
+				assert_eq!(IShapesVtbl::<IShapes>::slot(&func.name), Some(func.vtbl_index));
+			}
+			assert_eq!(IShapeInfoVtbl::<IShapeInfo>::slot("get_Area"), Some(lib.find("IShapeInfo").unwrap().funcs[0].vtbl_index));
+		}
+		com_assert_slots!(IShapes, 11);
+		com_assert_slots!(IShapeInfo, 8);
+		com_assert_slots!(DShapeEvents, 7);
+	}
+}
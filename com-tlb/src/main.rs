@@ -0,0 +1,39 @@
+/*!
+Translates a type library to Rust.
+
+Usage: `com-tlb [--attribute] <input.tlb>`, writes the Rust source to stdout.
+*/
+
+extern crate com_idl;
+extern crate com_tlb;
+
+use ::std::{env, fs, process};
+
+fn main() {
+	let mut attribute = false;
+	let mut path = None;
+	for arg in env::args().skip(1) {
+		match arg.as_str() {
+			"--attribute" => attribute = true,
+			_ if path.is_none() => path = Some(arg),
+			_ => usage(),
+		}
+	}
+	let path = path.unwrap_or_else(|| usage());
+	let data = fs::read(&path).unwrap_or_else(|err| {
+		eprintln!("{}: {}", path, err);
+		process::exit(1);
+	});
+	match com_tlb::TypeLib::parse(&data).and_then(|lib| lib.to_items()) {
+		Ok(items) => print!("{}", com_idl::Generator::new().attribute(attribute).generate(&items)),
+		Err(err) => {
+			eprintln!("{}: {}", path, err);
+			process::exit(1);
+		},
+	}
+}
+
+fn usage() -> ! {
+	eprintln!("usage: com-tlb [--attribute] <input.tlb>");
+	process::exit(2);
+}
@@ -0,0 +1,439 @@
+/*!
+Parses the MSFT binary format.
+
+The file starts with a header followed by the offsets of the type infos' member data and a directory of segments.
+The segments hold the fixed size type info entries, GUIDs, names, strings, type descriptors and the implemented interface lists.
+All offsets stored in the entries are relative to their segment.
+*/
+
+use ::com_sys::Guid;
+
+use super::*;
+
+const MSFT_MAGIC: u32 = 0x5446_534D;
+const HELPDLL_FLAG: i32 = 0x100;
+const SYS_WIN64: i32 = 3;
+
+const HEADER_SIZE: usize = 0x54;
+const TYPEINFO_SIZE: usize = 0x64;
+const SEGMENT_SIZE: usize = 16;
+
+const VT_PTR: u16 = 26;
+const VT_SAFEARRAY: u16 = 27;
+const VT_CARRAY: u16 = 28;
+const VT_USERDEFINED: u16 = 29;
+const VT_TYPEMASK: i32 = 0xFFF;
+
+const VAR_CONST: i16 = 2;
+const IMPINFO_OFFSET_IS_GUID: i32 = 0x0001_0000;
+
+/// Limits the nesting of type descriptors guarding against cycles.
+const MAX_DEPTH: u32 = 32;
+
+/// Segments of the directory in file order, the hash tables and import files are not needed.
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+enum Seg {
+	TypeInfo,
+	ImpInfo,
+	ImpFiles,
+	RefTab,
+	GuidHash,
+	Guid,
+	NameHash,
+	Name,
+	String,
+	TypeDesc,
+	ArrayDesc,
+	CustData,
+}
+
+#[derive(Copy, Clone, Default)]
+struct Segment {
+	offset: usize,
+	length: usize,
+}
+
+pub fn parse(data: &[u8]) -> Result<TypeLib, Error> {
+	let mut reader = Reader { data, segments: [Segment::default(); 15], ptr_size: 4, count: 0 };
+	reader.typelib()
+}
+
+struct Reader<'a> {
+	data: &'a [u8],
+	segments: [Segment; 15],
+	ptr_size: usize,
+	count: usize,
+}
+impl<'a> Reader<'a> {
+	//----------------------------------------------------------------
+	// Primitives
+
+	fn bytes(&self, offset: usize, len: usize) -> Result<&'a [u8], Error> {
+		match offset.checked_add(len) {
+			Some(end) if end <= self.data.len() => Ok(&self.data[offset..end]),
+			_ => Err(Error::Truncated(offset)),
+		}
+	}
+	fn u16(&self, offset: usize) -> Result<u16, Error> {
+		let bytes = self.bytes(offset, 2)?;
+		Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+	}
+	fn i16(&self, offset: usize) -> Result<i16, Error> {
+		self.u16(offset).map(|value| value as i16)
+	}
+	fn i32(&self, offset: usize) -> Result<i32, Error> {
+		let bytes = self.bytes(offset, 4)?;
+		Ok(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+	}
+
+	/// Returns the file offset of an offset into a segment.
+	fn seg(&self, seg: Seg, offset: i32) -> Result<usize, Error> {
+		let segment = self.segments[seg as usize];
+		if offset < 0 || offset as usize >= segment.length {
+			return Err(Error::Malformed("offset outside of its segment"));
+		}
+		Ok(segment.offset + offset as usize)
+	}
+
+	fn guid(&self, offset: i32) -> Result<Option<Guid>, Error> {
+		if offset < 0 {
+			return Ok(None);
+		}
+		let bytes = self.bytes(self.seg(Seg::Guid, offset)?, 16)?;
+		let mut guid = [0u8; 16];
+		guid.copy_from_slice(bytes);
+		Ok(Some(Guid::from_bytes_le(guid)))
+	}
+	/// Names are preceded by their hreftype, the next name in their hash bucket and their length in the low byte.
+	fn name(&self, offset: i32) -> Result<String, Error> {
+		let at = self.seg(Seg::Name, offset)?;
+		let len = self.i32(at + 8)? & 0xFF;
+		Ok(String::from_utf8_lossy(self.bytes(at + 12, len as usize)?).into_owned())
+	}
+	/// Strings are preceded by their 16-bit length.
+	fn string(&self, offset: i32) -> Result<Option<String>, Error> {
+		if offset < 0 {
+			return Ok(None);
+		}
+		let at = self.seg(Seg::String, offset)?;
+		let len = self.i16(at)?.max(0);
+		Ok(Some(String::from_utf8_lossy(self.bytes(at + 2, len as usize)?).into_owned()))
+	}
+
+	//----------------------------------------------------------------
+	// Library
+
+	fn typelib(&mut self) -> Result<TypeLib, Error> {
+		let magic = self.i32(0)? as u32;
+		if magic != MSFT_MAGIC {
+			return Err(Error::BadMagic(magic));
+		}
+		let mut header = [0i32; HEADER_SIZE / 4];
+		for (i, field) in header.iter_mut().enumerate() {
+			*field = self.i32(i * 4)?;
+		}
+		let (posguid, varflags, version, count, helpstring, name_offset) = (header[2], header[5], header[6], header[8], header[9], header[14]);
+		if count < 0 {
+			return Err(Error::Malformed("negative type info count"));
+		}
+		self.count = count as usize;
+		if varflags & 0xF == SYS_WIN64 {
+			self.ptr_size = 8;
+		}
+
+		// The segment directory follows the offsets of the type infos' member data.
+		let mut offset = HEADER_SIZE + self.count * 4;
+		if varflags & HELPDLL_FLAG != 0 {
+			offset += 4;
+		}
+		for i in 0..self.segments.len() {
+			let at = offset + i * SEGMENT_SIZE;
+			let (seg_offset, seg_length) = (self.i32(at)?, self.i32(at + 4)?);
+			if seg_offset >= 0 && seg_length > 0 {
+				self.bytes(seg_offset as usize, seg_length as usize)?;
+				self.segments[i] = Segment { offset: seg_offset as usize, length: seg_length as usize };
+			}
+		}
+		if self.segments[Seg::TypeInfo as usize].length < self.count * TYPEINFO_SIZE {
+			return Err(Error::Malformed("type info segment too small"));
+		}
+
+		let mut types = Vec::with_capacity(self.count);
+		for index in 0..self.count {
+			types.push(self.typeinfo(index)?);
+		}
+		Ok(TypeLib {
+			name: self.name(name_offset)?,
+			guid: self.guid(posguid)?.ok_or(Error::Malformed("library without guid"))?,
+			version: (version as u16, (version >> 16) as u16),
+			doc: self.string(helpstring)?,
+			types,
+		})
+	}
+
+	//----------------------------------------------------------------
+	// Type infos
+
+	fn typeinfo(&self, index: usize) -> Result<TypeInfo, Error> {
+		let at = self.segments[Seg::TypeInfo as usize].offset + index * TYPEINFO_SIZE;
+		let typekind = self.i32(at)?;
+		let memoffset = self.i32(at + 0x04)?;
+		let elements = self.i32(at + 0x18)? as u32;
+		let posguid = self.i32(at + 0x2C)?;
+		let flags = self.i32(at + 0x30)? as u16;
+		let name = self.name(self.i32(at + 0x34)?)?;
+		let version = self.i32(at + 0x38)?;
+		let doc = self.string(self.i32(at + 0x3C)?)?;
+		let impl_count = self.i16(at + 0x4C)?.max(0) as usize;
+		let size = self.i32(at + 0x50)? as u32;
+		let datatype1 = self.i32(at + 0x54)?;
+
+		let kind = match typekind & 0xF {
+			0 => TypeKind::Enum,
+			1 => TypeKind::Record,
+			2 => TypeKind::Module,
+			3 => TypeKind::Interface,
+			4 => TypeKind::Dispatch,
+			5 => TypeKind::Coclass,
+			6 => TypeKind::Alias,
+			7 => TypeKind::Union,
+			_ => return Err(Error::Malformed("unknown type kind")),
+		};
+
+		let mut impls = Vec::new();
+		match kind {
+			// Coclasses list their interfaces in the ref table.
+			TypeKind::Coclass => {
+				let mut next = datatype1;
+				for _ in 0..impl_count {
+					if next < 0 {
+						break;
+					}
+					let at = self.seg(Seg::RefTab, next)?;
+					let ty = self.typeref(self.i32(at)?)?;
+					impls.push(ImplType { ty, flags: self.i32(at + 4)? });
+					next = self.i32(at + 12)?;
+				}
+			},
+			// Interfaces store the hreftype of their base, dispinterfaces only when dual with a base other than IDispatch.
+			TypeKind::Interface | TypeKind::Dispatch if impl_count > 0 && datatype1 != -1 => {
+				impls.push(ImplType { ty: self.typeref(datatype1)?, flags: 0 });
+			},
+			_ => (),
+		}
+		let alias = match kind {
+			TypeKind::Alias => Some(self.typedesc(datatype1, 0)?),
+			_ => None,
+		};
+
+		let (func_count, var_count) = ((elements & 0xFFFF) as usize, (elements >> 16) as usize);
+		let mut funcs = Vec::with_capacity(func_count);
+		let mut vars = Vec::with_capacity(var_count);
+		if func_count + var_count > 0 {
+			let members = Members { offset: memoffset.max(0) as usize, func_count, var_count, info_len: self.i32(memoffset.max(0) as usize)?.max(0) as usize };
+			let mut record = members.offset + 4;
+			for i in 0..func_count {
+				let func = self.func(&members, i, record, funcs.last())?;
+				record += func.1;
+				funcs.push(func.0);
+			}
+			for i in 0..var_count {
+				vars.push(self.var(&members, i)?);
+			}
+		}
+
+		Ok(TypeInfo {
+			kind,
+			name,
+			guid: self.guid(posguid)?,
+			doc,
+			flags,
+			version: (version as u16, (version >> 16) as u16),
+			size,
+			impls,
+			funcs,
+			vars,
+			alias,
+		})
+	}
+
+	/// Hreftypes of local types are the offset of their entry, imported ones point into the import info segment.
+	fn typeref(&self, hreftype: i32) -> Result<TypeRef, Error> {
+		if hreftype < 0 {
+			return Err(Error::Malformed("invalid hreftype"));
+		}
+		if hreftype & 1 == 0 {
+			let index = hreftype as usize / TYPEINFO_SIZE;
+			if index >= self.count {
+				return Err(Error::Malformed("hreftype out of range"));
+			}
+			return Ok(TypeRef::Local(index));
+		}
+		let at = self.seg(Seg::ImpInfo, hreftype & !3)?;
+		let flags = self.i32(at)?;
+		if flags & IMPINFO_OFFSET_IS_GUID == 0 {
+			return Err(Error::Malformed("types imported by index are not supported"));
+		}
+		let guid = self.guid(self.i32(at + 8)?)?.ok_or(Error::Malformed("imported type without guid"))?;
+		Ok(TypeRef::Import(guid))
+	}
+
+	/// Type descriptors are either a builtin vt inline, with the high bit set, or an offset into the type descriptor segment.
+	fn typedesc(&self, datatype: i32, depth: u32) -> Result<TypeDesc, Error> {
+		if datatype < 0 {
+			return Ok(TypeDesc::Base((datatype & VT_TYPEMASK) as u16));
+		}
+		if depth >= MAX_DEPTH {
+			return Err(Error::Malformed("type descriptors nested too deep"));
+		}
+		let at = self.seg(Seg::TypeDesc, datatype)?;
+		let vt = self.u16(at)?;
+		let target = self.i32(at + 4)?;
+		Ok(match vt & VT_TYPEMASK as u16 {
+			VT_PTR => TypeDesc::Ptr(Box::new(self.typedesc(target, depth + 1)?)),
+			VT_SAFEARRAY => TypeDesc::SafeArray(Box::new(self.typedesc(target, depth + 1)?)),
+			VT_CARRAY => {
+				let at = self.seg(Seg::ArrayDesc, target & 0xFFFF)?;
+				let elem = self.typedesc(self.i32(at)?, depth + 1)?;
+				let dim_count = self.i32(at + 4)? & 0xFFFF;
+				let mut dims = Vec::with_capacity(dim_count as usize);
+				for i in 0..dim_count as usize {
+					dims.push(self.i32(at + 8 + i * 8)? as u32);
+				}
+				TypeDesc::CArray(Box::new(elem), dims)
+			},
+			VT_USERDEFINED => TypeDesc::UserDefined(self.typeref(target)?),
+			vt => TypeDesc::Base(vt),
+		})
+	}
+
+	/// Constants fitting 26 bits are stored inline with their vt, others in the custom data segment.
+	fn value(&self, value: i32) -> Result<i64, Error> {
+		if value < 0 {
+			return Ok((value & 0x3FF_FFFF) as i64);
+		}
+		let at = self.seg(Seg::CustData, value)?;
+		let bytes = |len| self.bytes(at + 2, len);
+		Ok(match self.u16(at)? {
+			// VT_I1 and VT_UI1
+			16 => bytes(1)?[0] as i8 as i64,
+			17 => bytes(1)?[0] as i64,
+			// VT_I2, VT_BOOL and VT_UI2
+			2 | 11 => self.i16(at + 2)? as i64,
+			18 => self.u16(at + 2)? as i64,
+			// VT_I4, VT_ERROR, VT_INT and VT_UI4, VT_UINT
+			3 | 10 | 22 => self.i32(at + 2)? as i64,
+			19 | 23 => self.i32(at + 2)? as u32 as i64,
+			// VT_I8 and VT_UI8
+			20 | 21 => {
+				let mut le = [0u8; 8];
+				le.copy_from_slice(bytes(8)?);
+				i64::from_le_bytes(le)
+			},
+			_ => return Err(Error::Malformed("unsupported constant type")),
+		})
+	}
+
+	//----------------------------------------------------------------
+	// Members
+
+	/// Reads a function record, returns it with the length of its record.
+	fn func(&self, members: &Members, index: usize, at: usize, prev: Option<&FuncDesc>) -> Result<(FuncDesc, usize), Error> {
+		let len = (self.i32(at)? & 0xFFFF) as usize;
+		let datatype = self.i32(at + 4)?;
+		let vtbl_offset = (self.i16(at + 12)? & !1).max(0) as usize;
+		let fkccic = self.i32(at + 16)?;
+		let param_count = self.i16(at + 20)?.max(0) as usize;
+
+		// The optional fields precede the parameters, the per argument custom data is counted separately.
+		let mut optional = len.saturating_sub(param_count * 12);
+		if fkccic & 0x1000 != 0 {
+			optional = optional.saturating_sub(param_count * 4);
+		}
+		let doc = if optional >= 32 { self.string(self.i32(at + 28)?)? } else { None };
+
+		let kind = match fkccic & 0x7 {
+			0 => FuncKind::Virtual,
+			1 => FuncKind::PureVirtual,
+			2 => FuncKind::NonVirtual,
+			3 => FuncKind::Static,
+			4 => FuncKind::Dispatch,
+			_ => return Err(Error::Malformed("unknown function kind")),
+		};
+		let invoke = match (fkccic >> 3) & 0xF {
+			1 => InvokeKind::Func,
+			2 => InvokeKind::PropertyGet,
+			4 => InvokeKind::PropertyPut,
+			8 => InvokeKind::PropertyPutRef,
+			_ => return Err(Error::Malformed("unknown invoke kind")),
+		};
+
+		// The second function of a property may leave out its name.
+		let name_offset = self.i32(members.table(members.func_count + members.var_count + index))?;
+		let name = match prev {
+			Some(prev) if name_offset == -1 => prev.name.clone(),
+			_ => self.name(name_offset)?,
+		};
+
+		let mut params = Vec::with_capacity(param_count);
+		let params_at = (at + len).checked_sub(param_count * 12).ok_or(Error::Malformed("function record too small"))?;
+		for i in 0..param_count {
+			let at = params_at + i * 12;
+			let name_offset = self.i32(at + 4)?;
+			params.push(ParamDesc {
+				name: if name_offset == -1 { None } else { Some(self.name(name_offset)?) },
+				ty: self.typedesc(self.i32(at)?, 0)?,
+				flags: self.i32(at + 8)? as u16,
+			});
+		}
+
+		let func = FuncDesc {
+			name,
+			memid: self.i32(members.table(index))?,
+			kind,
+			invoke,
+			vtbl_index: vtbl_offset / self.ptr_size,
+			ret: self.typedesc(datatype, 0)?,
+			params,
+			doc,
+		};
+		Ok((func, len))
+	}
+
+	fn var(&self, members: &Members, index: usize) -> Result<VarDesc, Error> {
+		let (func_count, var_count) = (members.func_count, members.var_count);
+		let record = self.i32(members.table(2 * (func_count + var_count) + func_count + index))?;
+		let at = members.offset + 4 + record.max(0) as usize;
+		let len = (self.i32(at)? & 0xFFFF) as usize;
+		let ty = self.typedesc(self.i32(at + 4)?, 0)?;
+		let value = self.i32(at + 16)?;
+		let value = match self.i16(at + 12)? {
+			VAR_CONST => VarValue::Const(self.value(value)?),
+			_ => VarValue::Offset(value as u32),
+		};
+		Ok(VarDesc {
+			name: self.name(self.i32(members.table(func_count + var_count + func_count + index))?)?,
+			memid: self.i32(members.table(func_count + index))?,
+			ty,
+			value,
+			doc: if len >= 28 { self.string(self.i32(at + 24)?)? } else { None },
+		})
+	}
+}
+
+/// Member data of a type info.
+///
+/// The records of the functions and variables are followed by tables of their member ids, names and record offsets.
+struct Members {
+	offset: usize,
+	func_count: usize,
+	var_count: usize,
+	info_len: usize,
+}
+impl Members {
+	/// Returns the file offset of an entry in the tables following the records.
+	fn table(&self, index: usize) -> usize {
+		self.offset + 4 + self.info_len + index * 4
+	}
+}
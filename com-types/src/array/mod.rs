@@ -2,32 +2,41 @@
 Safe array.
 */
 
-use ::std::{fmt, mem};
-
+#[cfg(windows)]
 mod safe_arr;
+#[cfg(windows)]
 pub use self::safe_arr::*;
 
+#[cfg(windows)]
 mod safe_array;
+#[cfg(windows)]
 pub use self::safe_array::*;
 
+#[cfg(windows)]
 mod typed_arr;
+#[cfg(windows)]
 pub use self::typed_arr::*;
 
+#[cfg(windows)]
 mod typed_array;
+#[cfg(windows)]
 pub use self::typed_array::*;
 
 mod slice;
 pub use self::slice::*;
 
+#[cfg(windows)]
 use ::winapi::{SAFEARRAY};
+#[cfg(windows)]
 fn vartype(raw: *const SAFEARRAY) -> ElemTag {
 	unsafe {
-		let mut vt = mem::uninitialized();
+		let mut vt = 0;
 		let hr = sys::SafeArrayGetVartype(raw, &mut vt);
 		debug_assert_eq!(hr, 0);
 		ElemTag(vt)
 	}
 }
+#[cfg(windows)]
 fn assert_vartype(raw: *const SAFEARRAY, tag: ElemTag) {
 	assert_eq!(vartype(raw), tag);
 }
@@ -40,14 +49,15 @@ pub trait ElemType: Sized {
 	fn elem_tag() -> ElemTag;
 
 	type Raw: Sized;
-	type Ref: fmt::Debug + ?Sized;
+	type Ref: ?Sized;
 
 	fn elem_ref(raw: &Self::Raw) -> &Self::Ref;
 }
 
 impl ElemType for ::bstr::BString {
 	fn elem_tag() -> ElemTag {
-		ElemTag(::winapi::VT_BSTR.0 as u16)
+		use ::IntoInner;
+		ElemTag(::variant::vt::VT_BSTR.into_inner())
 	}
 	type Raw = ::winapi::BSTR;
 	type Ref = ::bstr::BStr;
@@ -57,4 +67,34 @@ impl ElemType for ::bstr::BString {
 	}
 }
 
+/// Variants are stored in place, the array owns and clears them.
+impl<'v> ElemType for ::variant::Variant<'v> {
+	fn elem_tag() -> ElemTag {
+		use ::IntoInner;
+		ElemTag(::variant::vt::VT_VARIANT.into_inner())
+	}
+	type Raw = ::winapi::VARIANT;
+	type Ref = ::variant::Variant<'v>;
+
+	fn elem_ref(raw: &Self::Raw) -> &Self::Ref {
+		// `Variant` is a `#[repr(transparent)]` `VARIANT`.
+		unsafe { &*(raw as *const Self::Raw as *const Self::Ref) }
+	}
+}
+
+/// Records are stored in place, the array initializes, copies and clears them through its record info.
+impl<T: ::record::RecordType> ElemType for T {
+	fn elem_tag() -> ElemTag {
+		use ::IntoInner;
+		ElemTag(::variant::vt::VT_RECORD.into_inner())
+	}
+	type Raw = T;
+	type Ref = T;
+
+	fn elem_ref(raw: &T) -> &T {
+		raw
+	}
+}
+
+#[cfg(windows)]
 pub mod sys;
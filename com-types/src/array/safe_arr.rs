@@ -1,9 +1,13 @@
 
-use ::std::{fmt, slice, mem};
+use ::std::{fmt, slice, mem, ptr};
 use ::std::os::raw::c_void;
 
 use ::winapi::{USHORT, ULONG, PVOID, SAFEARRAY, SAFEARRAYBOUND};
 
+use ::com::{ComPtr, ComResult};
+use ::hr::HResult;
+use ::record::RecordInfoPtr;
+
 use super::{ElemType, ElemTag};
 use super::typed_arr::TypedArr;
 
@@ -62,6 +66,14 @@ impl SafeArr {
 	pub fn bounds(&self) -> &[SAFEARRAYBOUND] {
 		&self.bounds
 	}
+	/// Returns the record info describing the elements of an array of records.
+	pub fn record_info(&self) -> ComResult<RecordInfoPtr> {
+		let mut info = ptr::null_mut();
+		unsafe {
+			let hr = super::sys::SafeArrayGetRecordInfo(self.as_raw() as *mut SAFEARRAY, &mut info);
+			HResult::result_of(hr, || RecordInfoPtr::from_ptr(info))
+		}
+	}
 }
 
 //----------------------------------------------------------------
@@ -1,5 +1,5 @@
 
-use ::std::{ops, fmt};
+use ::std::{ops, fmt, mem};
 
 use ::winapi::{SAFEARRAY};
 
@@ -8,6 +8,16 @@ use super::sys::{SafeArrayDestroy};
 use super::safe_arr::SafeArr;
 
 pub struct SafeArray(*mut SAFEARRAY);
+impl SafeArray {
+	pub unsafe fn from_raw(raw: *mut SAFEARRAY) -> SafeArray {
+		SafeArray(raw)
+	}
+	pub fn into_raw(self) -> *mut SAFEARRAY {
+		let raw = self.0;
+		mem::forget(self);
+		raw
+	}
+}
 impl Drop for SafeArray {
 	fn drop(&mut self) {
 		unsafe {
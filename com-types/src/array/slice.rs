@@ -66,7 +66,7 @@ impl<T: ElemType> ops::Index<ops::RangeFull> for ArrSlice<T> {
 	}
 }
 
-impl<T: ElemType> fmt::Debug for ArrSlice<T> {
+impl<T: ElemType> fmt::Debug for ArrSlice<T> where T::Ref: fmt::Debug {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		f.debug_list()
 			.entries(self.iter())
@@ -99,7 +99,7 @@ impl<'a, T: 'a + ElemType> ExactSizeIterator for ArrIter<'a, T> {
 		self.0.len()
 	}
 }
-impl<'a, T: 'a + ElemType> fmt::Debug for ArrIter<'a, T> {
+impl<'a, T: 'a + ElemType> fmt::Debug for ArrIter<'a, T> where T::Ref: fmt::Debug {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		f.debug_tuple("ArrIter")
 			.field(&self.as_slice())
@@ -3,6 +3,7 @@ Contribute me back plz.
 */
 
 use ::winapi::{SAFEARRAY, SAFEARRAYBOUND, LONG, ULONG, VARTYPE, UINT, HRESULT, PVOID, c_void, GUID, REFGUID};
+use ::com_sys::dispatch::IRecordInfo;
 
 extern "system" {
 	pub fn SafeArrayAccessData(psa: *mut SAFEARRAY, ppvData: *mut *mut c_void) -> HRESULT;
@@ -23,7 +24,7 @@ extern "system" {
 	pub fn SafeArrayGetElemsize(psa: *const SAFEARRAY) -> UINT;
 	pub fn SafeArrayGetIID(psa: *const SAFEARRAY, pguid: *mut GUID) -> HRESULT;
 	pub fn SafeArrayGetLBound(psa: *const SAFEARRAY, nDim: UINT, plLbound: *mut LONG) -> HRESULT;
-	// pub fn SafeArrayGetRecordInfo(psa: *mut SAFEARRAY, prinfo: *mut *mut IRecordInfo) -> HRESULT;
+	pub fn SafeArrayGetRecordInfo(psa: *mut SAFEARRAY, prinfo: *mut *mut IRecordInfo) -> HRESULT;
 	pub fn SafeArrayGetUBound(psa: *const SAFEARRAY, nDim: UINT, plUbound: *mut LONG) -> HRESULT;
 	pub fn SafeArrayGetVartype(psa: *const SAFEARRAY, pvt: *mut VARTYPE) -> HRESULT;
 	pub fn SafeArrayLock(psa: *mut SAFEARRAY) -> HRESULT;
@@ -31,7 +32,7 @@ extern "system" {
 	pub fn SafeArrayPutElement(psa: *mut SAFEARRAY, rgIndices: *mut LONG, pv: *mut c_void) -> HRESULT;
 	pub fn SafeArrayRedim(psa: *mut SAFEARRAY, psaboundNew: *mut SAFEARRAYBOUND) -> HRESULT;
 	pub fn SafeArraySetIID(psa: *mut SAFEARRAY, guid: REFGUID) -> HRESULT;
-	// pub fn SafeArraySetRecordInfo(psa: *mut SAFEARRAY, prinfo: *mut IRecordInfo) -> HRESULT;
+	pub fn SafeArraySetRecordInfo(psa: *mut SAFEARRAY, prinfo: *mut IRecordInfo) -> HRESULT;
 	pub fn SafeArrayUnaccessData(psa: *mut SAFEARRAY) -> HRESULT;
 	pub fn SafeArrayUnlock(psa: *mut SAFEARRAY) -> HRESULT;
 }
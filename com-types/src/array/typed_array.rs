@@ -1,10 +1,15 @@
 
-use ::std::{ops, borrow, mem};
+use ::std::{ops, borrow, mem, ptr};
 use ::std::marker::PhantomData;
 
-use ::winapi::{SAFEARRAY};
+use ::winapi::{SAFEARRAY, ULONG, PVOID};
 
-use super::sys::{SafeArrayDestroy};
+use ::com::{AsComPtr, ComResult};
+use ::hr::code;
+use ::record::RecordType;
+use ::variant::Variant;
+
+use super::sys::{SafeArrayDestroy, SafeArrayCreateVector, SafeArrayCreateVectorEx};
 
 use super::{ElemType, TypedArr};
 
@@ -20,6 +25,45 @@ impl<T: ElemType> TypedArray<T> {
 		raw
 	}
 }
+impl<T: RecordType> TypedArray<T> {
+	/// Creates a vector of copies of the records, described by their derived record info.
+	pub fn from_records(records: &[T]) -> ComResult<TypedArray<T>> {
+		use ::IntoInner;
+		let info = T::record_info();
+		unsafe {
+			let raw = SafeArrayCreateVectorEx(T::elem_tag().into_inner(), 0, records.len() as ULONG, info.as_ptr() as PVOID);
+			if raw.is_null() {
+				return Err(code::E_OUTOFMEMORY);
+			}
+			let array = TypedArray::from_raw(raw);
+			// The elements are zero initialized, overwrite them without dropping.
+			let data = (*raw).pvData as *mut T;
+			for (index, record) in records.iter().enumerate() {
+				ptr::write(data.add(index), record.clone());
+			}
+			Ok(array)
+		}
+	}
+}
+impl TypedArray<Variant<'static>> {
+	/// Creates a vector of copies of the variants, dereferencing variants passed by reference.
+	pub fn from_variants(variants: &[Variant]) -> ComResult<TypedArray<Variant<'static>>> {
+		use ::IntoInner;
+		unsafe {
+			let raw = SafeArrayCreateVector(Variant::elem_tag().into_inner(), 0, variants.len() as ULONG);
+			if raw.is_null() {
+				return Err(code::E_OUTOFMEMORY);
+			}
+			// The elements are empty variants, the array clears the copies made so far if one fails.
+			let array = TypedArray::from_raw(raw);
+			let data = (*raw).pvData as *mut ::winapi::VARIANT;
+			for (index, variant) in variants.iter().enumerate() {
+				ptr::write(data.add(index), variant.try_clone_ind()?.into_raw());
+			}
+			Ok(array)
+		}
+	}
+}
 impl<T: ElemType> Drop for TypedArray<T> {
 	fn drop(&mut self) {
 		unsafe {
@@ -15,6 +15,9 @@ impl<A: FixedSizeArray> Array<A::Item> for A {
 	fn capacity() -> usize { A::capacity() }
 }
 
+/// # Safety
+///
+/// `as_ptr` and `as_mut_ptr` must point to `capacity()` contiguous items.
 pub unsafe trait FixedSizeArray {
 	type Item;
 	fn as_ptr(&self) -> *const Self::Item;
@@ -55,7 +58,7 @@ mod tests {
 	fn takes_mut_array_i32<A: Array<i32>>(arr: &mut A) { arr.as_mut_slice(); }
 	#[test]
 	fn units() {
-		let mut a: [i32; 768] = unsafe { ::std::mem::uninitialized() };
+		let mut a: [i32; 768] = [0; 768];
 		assert_eq!(a.as_slice().len(), 768);
 		takes_array_i32(&a);
 		takes_mut_array_i32(&mut a);
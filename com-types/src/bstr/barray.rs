@@ -1,13 +1,13 @@
 
-use ::std::{fmt, ops, slice, mem};
+use ::std::{fmt, ops, slice, mem, ptr};
 use ::std::iter::{FromIterator};
 use ::std::ffi::{OsStr};
-use ::std::os::windows::ffi::{OsStrExt};
 
 use ::winapi::{BSTR, OLECHAR};
 
 use super::array_traits::{Array};
 use super::bstr::{BStr, AsRawBStr};
+use super::wide::{OsStrExt};
 
 /// Array backed `BSTR`.
 ///
@@ -40,7 +40,7 @@ impl<A: Array<OLECHAR>> BArray<A> {
 	/// Creates an empty string.
 	pub fn new() -> BArray<A> {
 		unsafe {
-			let mut arr: A = mem::uninitialized();
+			let mut arr: A = mem::zeroed();
 			{ let sl = arr.as_mut_slice(); sl[0] = 0; sl[1] = 0; sl[2] = 0; }
 			BArray(arr)
 		}
@@ -55,19 +55,19 @@ impl<A: Array<OLECHAR>> BArray<A> {
 	///
 	/// Panics if string doesn't fit in the backing array.
 	pub fn set_from<T: IntoIterator<Item = OLECHAR>>(&mut self, ty: T) {
-		let mut sl = self.0.as_mut_slice();
+		let sl = self.0.as_mut_slice();
 		let mut n = 0;
 		for ch in ty.into_iter() {
 			sl[n + 2] = ch;
 			n += 1;
 		}
 		sl[n + 2] = 0;
-		unsafe { *(sl.as_mut_ptr() as *mut u32) = (n << 1) as u32; }
+		unsafe { ptr::write_unaligned(sl.as_mut_ptr() as *mut u32, (n << 1) as u32); }
 	}
 	fn shrink(&self) -> &[OLECHAR] {
 		unsafe {
 			let sp = self.0.as_slice().as_ptr();
-			let len = (*(sp as *const u32) >> 1) as usize;
+			let len = (ptr::read_unaligned(sp as *const u32) >> 1) as usize;
 			slice::from_raw_parts(sp, len.wrapping_add(3))
 		}
 	}
@@ -121,7 +121,7 @@ impl<A: Array<OLECHAR>> AsRawBStr for BArray<A> {
 
 impl<A: Array<OLECHAR>> fmt::Debug for BArray<A> {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		(&**self).fmt(f)
+		(**self).fmt(f)
 	}
 }
 
@@ -145,7 +145,7 @@ mod tests {
 		equal::<[_; 21], _>("filled to capacity");
 	}
 	#[test]
-	#[should_panic(expected = "assertion failed: index <")]
+	#[should_panic(expected = "index out of bounds")]
 	fn overflow() {
 		equal::<[_; 10], _>("overflow");
 	}
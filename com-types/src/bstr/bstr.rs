@@ -1,11 +1,12 @@
 
 use ::std::{fmt, slice, borrow, ops, mem, ptr};
 use ::std::ffi::{OsStr, OsString};
-use ::std::os::windows::ffi::{OsStrExt, OsStringExt};
 
-use ::oleaut32::{SysAllocStringLen};
 use ::winapi::{OLECHAR, BSTR};
 
+use super::sys::{SysAllocStringLen};
+use super::wide::{OsStrExt, OsStringExt};
+
 use super::bstring::{BString};
 
 /// Borrow as raw `BSTR`.
@@ -48,7 +49,7 @@ impl BStr {
 		}
 		else {
 			// Extract length prefix: number of bytes as `u32`
-			let len = (*(raw.offset(-2) as *const u32) >> 1) as usize;
+			let len = (ptr::read_unaligned(raw.offset(-2) as *const u32) >> 1) as usize;
 			// Wrangle it into a fat pointer Rust expects
 			let s = slice::from_raw_parts(raw.offset(-2), len.wrapping_add(3));
 			BStr::new(s)
@@ -64,7 +65,7 @@ impl BStr {
 		OsString::from_wide(self.as_ref())
 	}
 	unsafe fn assert_invariant(s: &[OLECHAR]) {
-		debug_assert_eq!(s.len() * 2 - 6, *(s.as_ptr() as *const u32) as usize);
+		debug_assert_eq!(s.len() * 2 - 6, ptr::read_unaligned(s.as_ptr() as *const u32) as usize);
 		debug_assert_eq!(s.last(), Some(&0));
 	}
 }
@@ -174,7 +175,7 @@ impl<T: ?Sized + AsRef<OsStr>> PartialEq<T> for BStr {
 			}
 			return false;
 		}
-		it.next() == None
+		it.next().is_none()
 	}
 }
 
@@ -211,7 +212,7 @@ mod tests {
 		let hello = unsafe { BStr::new(&hello_words) };
 
 		assert_eq!(hello.len(), 5);
-		assert_eq!(BStr::empty().is_empty(), true);
+		assert!(BStr::empty().is_empty());
 
 		unsafe {
 			let raw = hello.as_raw();
@@ -1,12 +1,12 @@
 
 use ::std::{fmt, ops, mem, ptr};
 use ::std::ffi::{OsStr};
-use ::std::os::windows::ffi::{OsStrExt};
 
-use ::oleaut32::{SysAllocStringLen, SysFreeString};
 use ::winapi::{BSTR, OLECHAR};
 
 use super::bstr::{BStr, AsRawBStr};
+use super::sys::{SysAllocStringLen, SysFreeString};
+use super::wide::{OsStrExt};
 
 /// System allocated `BSTR`.
 ///
@@ -58,11 +58,9 @@ use super::bstr::{BStr, AsRawBStr};
 ///
 /// ```
 /// # use com_types::bstr::{BString};
-/// use ::std::ffi::OsStr;
-/// use ::std::os::windows::ffi::OsStrExt;
 /// use com_types::bstr::IntoBString;
 ///
-/// let bstring = OsStr::new("Encoded BString").encode_wide().into_bstring();
+/// let bstring = "Encoded BString".encode_utf16().into_bstring();
 /// assert_eq!(bstring, "Encoded BString");
 /// ```
 ///
@@ -71,10 +69,7 @@ use super::bstr::{BStr, AsRawBStr};
 ///
 /// ```
 /// # use com_types::bstr::BString;
-/// use ::std::ffi::OsStr;
-/// use ::std::os::windows::ffi::OsStrExt;
-///
-/// let bstring: BString = OsStr::new("Collected BString").encode_wide().collect();
+/// let bstring: BString = "Collected BString".encode_utf16().collect();
 /// assert_eq!(bstring, "Collected BString");
 /// ```
 pub struct BString(BSTR);
@@ -88,6 +83,10 @@ impl BString {
 	/// Takes ownership of a system allocated `BSTR`.
 	///
 	/// Null is allowed, as it is semantically equivalent to the empty string.
+	///
+	/// # Safety
+	///
+	/// The `BSTR` must be null or allocated by the [`sys`](sys/index.html) backend and not owned by anything else.
 	pub unsafe fn from_raw(raw: BSTR) -> BString {
 		BString(raw)
 	}
@@ -117,7 +116,7 @@ impl Default for BString {
 }
 impl Drop for BString {
 	fn drop(&mut self) {
-		unsafe { let _ = SysFreeString(self.0); }
+		unsafe { SysFreeString(self.0); }
 	}
 }
 
@@ -134,7 +133,7 @@ impl<T: AsRef<OsStr>> From<T> for BString {
 impl FromIterator<OLECHAR> for BString {
 	fn from_iter<T: IntoIterator<Item = OLECHAR>>(ty: T) -> BString {
 		let vec: Vec<_> = ty.into_iter().collect();
-		if vec.len() == 0 {
+		if vec.is_empty() {
 			BString(ptr::null_mut())
 		}
 		else {
@@ -171,7 +170,7 @@ impl<I: IntoIterator<Item = OLECHAR>> IntoBString for I where I::IntoIter: Clone
 				}
 				// Encode the string
 				for (i, ch) in iter.enumerate() {
-					*raw.offset(i as isize) = ch;
+					*raw.add(i) = ch;
 				}
 				BString::from_raw(raw)
 			}
@@ -244,7 +243,7 @@ impl<T: ?Sized + AsRef<OsStr>> PartialEq<T> for BString {
 
 impl fmt::Debug for BString {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		(&**self).fmt(f)
+		(**self).fmt(f)
 	}
 }
 
@@ -258,7 +257,7 @@ mod tests {
 
 	#[test]
 	fn units() {
-		BString::new().clone();
+		let _ = BString::new().clone();
 		let hello = BString::from("Hello, World!");
 		assert_eq!(hello, "Hello, World!");
 		let clone = hello.clone();
@@ -7,8 +7,12 @@ and [Eric’s Complete Guide To BSTR Semantics](https://blogs.msdn.microsoft.com
 */
 
 mod array_traits;
+mod wide;
+
+pub mod sys;
 
 mod barray;
+#[allow(clippy::module_inception)]
 mod bstr;
 mod bstring;
 
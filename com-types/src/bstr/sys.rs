@@ -0,0 +1,116 @@
+/*!
+`BSTR` allocation backend.
+
+On Windows strings are allocated by oleaut32 so ownership can be transferred across COM boundaries.
+
+On other targets a pure Rust backend lays out the same block in Rust managed memory:
+a `u32` byte length prefix followed by the UTF-16 characters and a terminating nul.
+The `BSTR` points right after the length prefix, exactly like its system allocated counterpart.
+*/
+
+#[cfg(windows)]
+pub use ::oleaut32::{SysAllocStringLen, SysFreeString, SysStringLen};
+
+#[cfg(not(windows))]
+pub use self::rust::{SysAllocStringLen, SysFreeString, SysStringLen};
+
+#[cfg(not(windows))]
+#[allow(non_snake_case)]
+mod rust {
+	use ::std::{alloc, mem, ptr};
+
+	use ::winapi::{BSTR, OLECHAR, UINT};
+
+	const PREFIX: usize = mem::size_of::<u32>();
+
+	fn layout(cch: usize) -> Option<alloc::Layout> {
+		let size = cch.checked_mul(2)?.checked_add(PREFIX + 2)?;
+		alloc::Layout::from_size_align(size, mem::align_of::<u32>()).ok()
+	}
+
+	/// Allocates a new string and copies `ui` characters from `strIn` into it.
+	///
+	/// If `strIn` is null the characters are zero initialized. Returns null if out of memory.
+	///
+	/// # Safety
+	///
+	/// If not null `strIn` must point to at least `ui` characters.
+	pub unsafe fn SysAllocStringLen(strIn: *const OLECHAR, ui: UINT) -> BSTR {
+		let cch = ui as usize;
+		let cb = match cch.checked_mul(2) {
+			Some(cb) if cb <= u32::MAX as usize => cb,
+			_ => return ptr::null_mut(),
+		};
+		let layout = match layout(cch) {
+			Some(layout) => layout,
+			None => return ptr::null_mut(),
+		};
+		let block = alloc::alloc_zeroed(layout);
+		if block.is_null() {
+			return ptr::null_mut();
+		}
+		*(block as *mut u32) = cb as u32;
+		let raw = block.add(PREFIX) as BSTR;
+		if !strIn.is_null() {
+			ptr::copy_nonoverlapping(strIn, raw, cch);
+		}
+		raw
+	}
+
+	/// Frees a string previously allocated with `SysAllocStringLen`.
+	///
+	/// Null is allowed and does nothing.
+	///
+	/// # Safety
+	///
+	/// The string must have been allocated by this backend and not already freed.
+	pub unsafe fn SysFreeString(bstrString: BSTR) {
+		if !bstrString.is_null() {
+			let block = (bstrString as *mut u8).sub(PREFIX);
+			let cch = (*(block as *const u32) >> 1) as usize;
+			alloc::dealloc(block, layout(cch).unwrap());
+		}
+	}
+
+	/// Returns the number of characters in the string, null has zero length.
+	///
+	/// # Safety
+	///
+	/// The string must be null or a valid `BSTR`.
+	pub unsafe fn SysStringLen(pbstr: BSTR) -> UINT {
+		if pbstr.is_null() {
+			0
+		}
+		else {
+			*((pbstr as *const u8).sub(PREFIX) as *const u32) >> 1
+		}
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use ::std::ptr;
+	use super::*;
+
+	#[test]
+	fn layout() {
+		let hello = [72u16, 101, 108, 108, 111];
+		unsafe {
+			let raw = SysAllocStringLen(hello.as_ptr(), 5);
+			assert!(!raw.is_null());
+			assert_eq!(SysStringLen(raw), 5);
+			assert_eq!(*(raw.offset(-2) as *const u32), 10);
+			assert_eq!(::std::slice::from_raw_parts(raw, 6), &[72, 101, 108, 108, 111, 0]);
+			SysFreeString(raw);
+
+			let zeroed = SysAllocStringLen(ptr::null(), 3);
+			assert_eq!(::std::slice::from_raw_parts(zeroed, 4), &[0, 0, 0, 0]);
+			SysFreeString(zeroed);
+
+			assert_eq!(SysStringLen(ptr::null_mut()), 0);
+			SysFreeString(ptr::null_mut());
+		}
+	}
+}
@@ -0,0 +1,66 @@
+/*!
+Wide string conversions for `OsStr`.
+
+On Windows these are the `OsStrExt` and `OsStringExt` extensions from the standard library.
+
+Other targets get a stand-in with the same interface which goes through UTF-8,
+replacing invalid sequences with the replacement character.
+*/
+
+#[cfg(windows)]
+pub use ::std::os::windows::ffi::{OsStrExt, OsStringExt};
+
+#[cfg(not(windows))]
+pub use self::portable::{OsStrExt, OsStringExt};
+
+#[cfg(not(windows))]
+mod portable {
+	use ::std::borrow::Cow;
+	use ::std::ffi::{OsStr, OsString};
+
+	/// Encodes an `OsStr` as UTF-16.
+	pub trait OsStrExt {
+		fn encode_wide(&self) -> EncodeWide<'_>;
+	}
+	impl OsStrExt for OsStr {
+		fn encode_wide(&self) -> EncodeWide<'_> {
+			EncodeWide { s: self.to_string_lossy(), pos: 0, extra: 0 }
+		}
+	}
+
+	/// Decodes an `OsString` from UTF-16.
+	pub trait OsStringExt {
+		fn from_wide(wide: &[u16]) -> Self;
+	}
+	impl OsStringExt for OsString {
+		fn from_wide(wide: &[u16]) -> OsString {
+			String::from_utf16_lossy(wide).into()
+		}
+	}
+
+	/// Iterator over the UTF-16 code units of an `OsStr`.
+	#[derive(Clone, Debug)]
+	pub struct EncodeWide<'a> {
+		s: Cow<'a, str>,
+		pos: usize,
+		extra: u16,
+	}
+	impl<'a> Iterator for EncodeWide<'a> {
+		type Item = u16;
+		fn next(&mut self) -> Option<u16> {
+			if self.extra != 0 {
+				let low = self.extra;
+				self.extra = 0;
+				return Some(low);
+			}
+			let ch = self.s[self.pos..].chars().next()?;
+			self.pos += ch.len_utf8();
+			let mut buf = [0u16; 2];
+			let units = ch.encode_utf16(&mut buf);
+			if units.len() == 2 {
+				self.extra = units[1];
+			}
+			Some(units[0])
+		}
+	}
+}
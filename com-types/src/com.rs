@@ -1,4 +1,94 @@
+use ::std::{error, fmt, ptr};
+
 use ::com_sys::{ComInterface};
+use ::com_sys::unknown::IUnknown;
+
+use ::hr::{code, HResult};
+use ::unknown::IUnknownPtr;
+
+/// Defines a COM interface from a trait.
+///
+/// The attribute takes the interface id, the trait's methods are its virtual functions and its supertrait is the base interface.
+/// Generates, named after the trait:
+///
+/// * The `#[repr(C)]` interface struct implementing `ComInterface` and `ComInherit`.
+/// * Its vtbl struct `IInterfaceVtbl` generic over its `This` pointer, see [`ComVtbl`](../trait.ComVtbl.html).
+///   The base vtbl is expected next to the base interface, named after it with `Vtbl` added at the end.
+/// * Its `#[repr(transparent)]` com pointer `IInterfacePtr` wrapping the com pointer of the base interface, see [`ComInterfacePtr`](trait.ComInterfacePtr.html).
+///   Derives on the trait are applied to the com pointer.
+/// * Shims on the com pointer calling the virtual functions.
+///   They are `unsafe` unless every argument is plain data, eg. integers, floats or `GUID` passed by value, as pointers hidden behind aliases like `BSTR` cannot be checked.
+/// * Safe wrappers on the com pointer for the virtual functions returning `HRESULT`, named after them in snake case.
+///   No wrapper is generated if the virtual function is already named in snake case.
+/// * The same shims and wrappers on the interface struct.
+///   They are reached through [`ComRc<IInterface>`](../rc/struct.ComRc.html) and [`ComRef<IInterface>`](../rc/struct.ComRef.html),
+///   those of the base interface after upcasting with `as_super` or `upcast`.
+///
+/// Exported from this module rather than the crate root to avoid clashing with `com_interface!` under `#[macro_use]`.
+///
+/// Virtual functions take `&self` which becomes the `This` pointer, the remaining arguments are passed as is.
+///
+/// The wrappers return `ComResult`, replacing the boilerplate around `com_call!` and `HResult::result`:
+///
+/// * Trailing arguments marked `#[out]` or `#[retval]` must be `*mut` pointers, their values are returned.
+///   More than one are returned as a tuple.
+/// * `BSTR` is taken as `&BStr` and returned as `BString`.
+/// * `VARIANT` is taken as `&Variant`, or `&mut Variant` if passed as `*mut`, and returned as `Variant`.
+/// * `SAFEARRAY` pointers are taken as `&SafeArr` and returned as `SafeArray`.
+///   Mark the argument `#[array(T)]` to take `&TypedArr<T>` and return `TypedArray<T>` instead.
+/// * Interface pointers marked `#[interface]`, passed as `*mut I` and returned as `*mut *mut I`, are taken and returned as their com pointer.
+///   Returning a null interface pointer or `SAFEARRAY` fails with `E_POINTER`.
+/// * Other out arguments are left uninitialized and returned as is once the virtual function succeeded.
+///   The wrapper is `unsafe` unless every other argument is plain data, like the shims.
+///
+/// # Examples
+///
+/// ```
+/// extern crate com_sys;
+/// extern crate com_types;
+/// # fn main() {
+/// use ::com_sys::HRESULT;
+/// use ::com_types::com::com_interface;
+///
+/// #[com_interface("582cdb5a-e8b9-47bb-bff8-5a18da55bc99")]
+/// /// Doc comment.
+/// #[derive(Clone, Debug)]
+/// pub trait IInterface: ::com_sys::unknown::IUnknown {
+/// 	fn Foo(&self, value: i32) -> HRESULT;
+/// 	fn Bar(&self, #[out] pValue: *mut i32) -> HRESULT;
+/// }
+///
+/// // Is the same as:
+/// //
+/// // com_interface! {
+/// // 	/// Doc comment.
+/// // 	interface IInterface(IInterfaceVtbl): IUnknown(IUnknownVtbl);
+/// // 	"582cdb5a-e8b9-47bb-bff8-5a18da55bc99"
+/// // 	pub Foo: unsafe extern "system" fn(This: *mut IInterface, value: i32) -> HRESULT,
+/// // 	pub Bar: unsafe extern "system" fn(This: *mut IInterface, pValue: *mut i32) -> HRESULT,
+/// // }
+/// // com_ptr! {
+/// // 	/// Doc comment.
+/// // 	#[derive(Clone, Debug)]
+/// // 	pub struct IInterfacePtr(IInterface): IUnknownPtr;
+/// // }
+/// //
+/// // With the shims:
+/// //
+/// // impl IInterfacePtr {
+/// // 	pub fn Foo(&self, value: i32) -> HRESULT;
+/// // 	pub unsafe fn Bar(&self, pValue: *mut i32) -> HRESULT;
+/// // }
+/// //
+/// // And the wrappers:
+/// //
+/// // impl IInterfacePtr {
+/// // 	pub fn foo(&self, value: i32) -> ComResult<()>;
+/// // 	pub fn bar(&self) -> ComResult<i32>;
+/// // }
+/// # }
+/// ```
+pub use com_macros::com_interface;
 
 pub type ComResult<T> = Result<T, super::hr::HResult>;
 
@@ -18,11 +108,109 @@ pub trait ComPtr: AsComPtr {
 	///
 	/// Must not be null, should be a valid raw interface pointer.
 	unsafe fn from_ptr(iface: *mut Self::Interface) -> Self;
-	/// Cast the com pointer to a super class.
+	/// Cast the com pointer to the com pointer of an ancestor interface.
 	#[inline(always)]
 	fn cast<P: ComPtr>(&self) -> &P where Self: ComCast<P> {
 		<Self as ComCast<P>>::cast(self)
 	}
+	/// Queries the object for the interface of the com pointer `T`, the fallible counterpart of [`cast`](#method.cast) for any interface.
+	///
+	/// Fails with [`CastError::NoInterface`](enum.CastError.html#variant.NoInterface) if the object does not implement it.
+	#[inline]
+	#[cfg_attr(any(test, feature = "leak"), track_caller)]
+	fn try_cast<T: ComPtr>(&self) -> Result<T, CastError> {
+		call_site!();
+		unsafe { query_interface(self.as_ptr() as *mut IUnknown, <T::Interface as ComInterface>::iid()).map(|ppv| T::new(ppv)) }
+	}
+	/// Returns if the object implements the interface `I`.
+	///
+	/// Failures other than `E_NOINTERFACE` are reported as not supported.
+	#[inline]
+	#[cfg_attr(any(test, feature = "leak"), track_caller)]
+	fn supports<I: ComInterface>(&self) -> bool {
+		call_site!();
+		unsafe {
+			query_interface(self.as_ptr() as *mut IUnknown, I::iid())
+				.map(|ppv| drop(IUnknownPtr::new(ppv)))
+				.is_ok()
+		}
+	}
+	/// Queries the object for its canonical `IUnknown`, which is the same for all of its com pointers.
+	///
+	/// Com pointers of the same object may differ, their identity tells whether they point to the same object.
+	#[inline]
+	#[cfg_attr(any(test, feature = "leak"), track_caller)]
+	fn identity(&self) -> ComResult<IUnknownPtr> {
+		call_site!();
+		self.try_cast().map_err(HResult::from)
+	}
+	/// Returns if both com pointers point to the same object, comparing their [`identity`](#method.identity).
+	#[inline]
+	#[cfg_attr(any(test, feature = "leak"), track_caller)]
+	fn is_same_object<P: ComPtr>(&self, other: &P) -> bool {
+		call_site!();
+		match (self.identity(), other.identity()) {
+			(Ok(lhs), Ok(rhs)) => lhs.as_ptr() == rhs.as_ptr(),
+			_ => false,
+		}
+	}
+}
+
+/// Error of [`ComPtr::try_cast`](trait.ComPtr.html#method.try_cast).
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CastError {
+	/// The object does not implement the interface, `E_NOINTERFACE`.
+	NoInterface,
+	/// The query failed otherwise, eg. a transport failure of a proxy.
+	Failed(HResult),
+}
+impl CastError {
+	/// Returns the error as `HResult`.
+	pub fn hresult(self) -> HResult {
+		match self {
+			CastError::NoInterface => code::E_NOINTERFACE,
+			CastError::Failed(hr) => hr,
+		}
+	}
+}
+impl From<HResult> for CastError {
+	fn from(hr: HResult) -> CastError {
+		if hr == code::E_NOINTERFACE { CastError::NoInterface } else { CastError::Failed(hr) }
+	}
+}
+impl From<CastError> for HResult {
+	fn from(err: CastError) -> HResult {
+		err.hresult()
+	}
+}
+impl fmt::Display for CastError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			CastError::NoInterface => f.write_str("interface not supported"),
+			CastError::Failed(hr) => write!(f, "query interface failed with {}", hr),
+		}
+	}
+}
+impl error::Error for CastError {}
+
+/// Calls `QueryInterface`, the returned pointer holds a reference.
+///
+/// # Safety
+///
+/// The pointer must be a valid interface pointer.
+pub unsafe fn query_interface(unknown: *mut IUnknown, iid: &::GUID) -> Result<::LPVOID, CastError> {
+	let mut ppv = ptr::null_mut();
+	let hr = ((*unknown).vtbl().QueryInterface)(unknown, iid, &mut ppv);
+	HResult::result(hr, ppv).map_err(CastError::from)
+}
+
+/// Associates a com interface with its com pointer.
+///
+/// Implemented by [`com_ptr!`](../macro.com_ptr!.html) and [`#[com_interface]`](attr.com_interface.html),
+/// the latter uses it to find the com pointer of the base interface.
+pub trait ComInterfacePtr: ComInterface {
+	/// The com pointer wrapping this interface.
+	type Ptr: ComPtr<Interface = Self>;
 }
 
 /// Access the com interface pointer.
@@ -50,6 +238,33 @@ impl<'a, P: 'a + AsComPtr> AsComPtr for &'a mut P {
 }
 
 /// Casts an instance to a related type.
+///
+/// Com pointers cast to themselves and to the com pointer of any ancestor interface,
+/// [`com_ptr!`](../macro.com_ptr!.html) and [`#[com_interface]`](attr.com_interface.html) implement it through the com pointer of the base interface.
+/// Every com pointer wraps the com pointer of its base interface, the cast borrows it and costs nothing.
+///
+/// ```
+/// # extern crate com_sys; extern crate com_types; fn main() {
+/// use ::com_sys::HRESULT;
+/// use ::com_sys::unknown::{IUnknown, IUnknownVtbl};
+/// use ::com_types::com::{com_interface, AsComPtr, ComCast};
+/// use ::com_types::unknown::IUnknownPtr;
+///
+/// #[com_interface("5b0b0c4e-2f1d-4c8a-9e3b-7a6d5c4b3a21")]
+/// pub trait IBase: IUnknown {}
+/// #[com_interface("5b0b0c4e-2f1d-4c8a-9e3b-7a6d5c4b3a22")]
+/// pub trait IDerived: IBase {
+/// 	fn Foo(&self) -> HRESULT;
+/// }
+///
+/// fn identity<P: ComCast<IUnknownPtr>>(ptr: P) -> *mut IUnknown {
+/// 	ptr.cast().as_ptr()
+/// }
+/// fn derived(ptr: &IDerivedPtr) -> (&IBasePtr, *mut IUnknown) {
+/// 	(ptr.cast(), identity(ptr))
+/// }
+/// # }
+/// ```
 pub trait ComCast<T: ComPtr>: AsComPtr {
 	/// Perform the cast.
 	fn cast(&self) -> &T;
@@ -66,3 +281,154 @@ impl<'a, S: 'a + ComCast<T>, T: ComPtr> ComCast<T> for &'a mut S {
 		(**self).cast()
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use ::std::mem;
+	use ::std::num::NonZeroU32;
+	use ::com_sys::HRESULT;
+	use ::com_sys::unknown::{IUnknown, IUnknownVtbl};
+	use ::object::ComBox;
+	use ::GUID;
+	use super::*;
+
+	#[::com::com_interface("6c1d2e3f-4a5b-4c6d-8e7f-9a0b1c2d3e40")]
+	#[derive(Clone)]
+	pub trait IFirst: IUnknown {}
+	#[::com::com_interface("6c1d2e3f-4a5b-4c6d-8e7f-9a0b1c2d3e41")]
+	pub trait ISecond: IUnknown {}
+	#[::com::com_interface("6c1d2e3f-4a5b-4c6d-8e7f-9a0b1c2d3e42")]
+	pub trait IRemote: IUnknown {}
+
+	const RPC_E_DISCONNECTED: HResult = HResult(0x80010108);
+
+	struct Object;
+	com_object! {
+		impl Object {
+			IFirst(IFirstVtbl {
+				base: IUnknownVtbl,
+			}),
+		}
+		unsafe {
+			// Pretends to be a proxy whose server went away.
+			fn query_fallback(&self, _outer: &IUnknownPtr, iid: &GUID) -> ComResult<IUnknownPtr> {
+				Err(if IRemote::matches_iid(iid) { RPC_E_DISCONNECTED } else { code::E_NOINTERFACE })
+			}
+		}
+	}
+
+	#[test]
+	fn casts() {
+		let first: IFirstPtr = ComBox::new(Object);
+		let unknown = first.try_cast::<IUnknownPtr>().unwrap();
+		assert_eq!(unknown.as_ptr() as usize, first.as_ptr() as usize);
+		assert!(first.try_cast::<IFirstPtr>().is_ok());
+
+		assert_eq!(first.try_cast::<ISecondPtr>().err(), Some(CastError::NoInterface));
+		assert_eq!(unknown.try_cast::<ISecondPtr>().err(), Some(CastError::NoInterface));
+		assert_eq!(first.try_cast::<IRemotePtr>().err(), Some(CastError::Failed(RPC_E_DISCONNECTED)));
+		assert_eq!(HResult::from(CastError::NoInterface), code::E_NOINTERFACE);
+
+		assert!(first.supports::<IUnknown>());
+		assert!(unknown.supports::<IFirst>());
+		assert!(!first.supports::<ISecond>());
+		assert!(!first.supports::<IRemote>());
+		// The probes do not leak references.
+		drop(unknown);
+		unsafe { assert_eq!(ComBox::<Object>::ref_count(first.as_ptr()), 1); }
+	}
+
+	// Named like an interface but passed by value.
+	#[allow(clippy::upper_case_acronyms)]
+	type IID = GUID;
+
+	#[::com::com_interface("6c1d2e3f-4a5b-4c6d-8e7f-9a0b1c2d3e43")]
+	pub trait IText: IUnknown {
+		fn Describe(&self, value: i32, #[out] pName: *mut ::winapi::BSTR) -> HRESULT;
+		fn Echo(&self, value: *const ::winapi::VARIANT, #[retval] pResult: *mut ::winapi::VARIANT) -> HRESULT;
+		fn Measure(&self, text: ::winapi::BSTR, #[out] pLen: *mut u32, #[out] #[interface] ppFirst: *mut *mut IFirst) -> HRESULT;
+		fn Identify(&self, #[interface] first: *mut IFirst, #[out] pIid: *mut IID, #[out] pRefs: *mut NonZeroU32) -> HRESULT;
+		fn Fill(&self, value: u8, pBuffer: *mut u8) -> HRESULT;
+	}
+	unsafe extern "system" fn text_describe(_this: *mut IText, value: i32, name: *mut ::winapi::BSTR) -> HRESULT {
+		if value < 0 {
+			return code::E_INVALIDARG.into();
+		}
+		*name = ::bstr::BString::from(format!("#{}", value)).into_raw();
+		code::S_OK.into()
+	}
+	unsafe extern "system" fn text_echo(_this: *mut IText, value: *const ::winapi::VARIANT, result: *mut ::winapi::VARIANT) -> HRESULT {
+		::variant::sys::VariantCopy(result, value)
+	}
+	unsafe extern "system" fn text_measure(this: *mut IText, text: ::winapi::BSTR, len: *mut u32, first: *mut *mut IFirst) -> HRESULT {
+		*len = ::bstr::BStr::from_raw(text).len() as u32;
+		let ptr = ComBox::<Text>::from_interface(this).0.clone();
+		*first = ptr.as_ptr();
+		mem::forget(ptr);
+		code::S_OK.into()
+	}
+	unsafe extern "system" fn text_identify(_this: *mut IText, first: *mut IFirst, iid: *mut IID, refs: *mut NonZeroU32) -> HRESULT {
+		if first.is_null() {
+			return code::E_POINTER.into();
+		}
+		*iid = IFirst::IID.into();
+		*refs = NonZeroU32::new(ComBox::<Object>::ref_count(first)).unwrap();
+		code::S_OK.into()
+	}
+	unsafe extern "system" fn text_fill(_this: *mut IText, value: u8, buffer: *mut u8) -> HRESULT {
+		*buffer = value;
+		code::S_OK.into()
+	}
+	struct Text(IFirstPtr);
+	com_object! {
+		impl Text {
+			IText(ITextVtbl {
+				base: IUnknownVtbl,
+				Describe: text_describe,
+				Echo: text_echo,
+				Measure: text_measure,
+				Identify: text_identify,
+				Fill: text_fill,
+			}),
+		}
+	}
+
+	#[test]
+	fn wrappers() {
+		use ::bstr::{AsRawBStr, BString};
+		use ::variant::{Variant, types};
+
+		let first: IFirstPtr = ComBox::new(Object);
+		let text: ITextPtr = ComBox::new(Text(first.clone()));
+
+		assert_eq!(text.describe(7).unwrap(), "#7");
+		assert_eq!(text.describe(-1), Err(code::E_INVALIDARG));
+
+		let echo = text.echo(&Variant::from(types::BString::from("echo"))).unwrap();
+		assert_eq!(echo.borrow::<types::BString>(), "echo");
+
+		let (len, returned) = text.measure(&BString::from("four")).unwrap();
+		assert_eq!(len, 4);
+		assert_eq!(returned.as_ptr(), first.as_ptr());
+
+		// Only arguments marked `#[interface]` are com pointers, the out arguments need no valid zero value.
+		let (iid, refs) = text.identify(&first).unwrap();
+		assert!(IFirst::matches_iid(&iid));
+		assert_eq!(refs.get(), 3);
+
+		// Raw pointers make the wrapper unsafe.
+		let mut value = 0;
+		assert_eq!(unsafe { text.fill(9, &mut value) }, Ok(()));
+		assert_eq!(value, 9);
+
+		// Wrappers are generated next to the shims.
+		let mut len = 0;
+		let mut ptr = ptr::null_mut();
+		assert_eq!(HResult::from(unsafe { text.Measure(BString::from("two").as_raw(), &mut len, &mut ptr) }), code::S_OK);
+		assert_eq!(len, 3);
+		drop(unsafe { IFirstPtr::from_ptr(ptr) });
+
+		drop((text, returned));
+		unsafe { assert_eq!(ComBox::<Object>::ref_count(first.as_ptr()), 1); }
+	}
+}
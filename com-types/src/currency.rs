@@ -2,7 +2,7 @@
 Currency data type. Incomplete.
 */
 
-use ::std::{fmt, mem};
+use ::std::{fmt};
 
 use ::winapi::CY;
 
@@ -16,22 +16,22 @@ use ::winapi::CY;
 pub struct Currency(CY);
 impl From<i64> for Currency {
 	fn from(cy: i64) -> Currency {
-		Currency(unsafe { mem::transmute(cy) })
+		Currency(CY { int64: cy })
 	}
 }
 impl From<Currency> for i64 {
 	fn from(cy: Currency) -> i64 {
-		unsafe { mem::transmute(cy.0) }
+		cy.0.int64
 	}
 }
 impl AsRef<i64> for Currency {
 	fn as_ref(&self) -> &i64 {
-		unsafe { mem::transmute(&self.0) }
+		&self.0.int64
 	}
 }
 impl AsMut<i64> for Currency {
 	fn as_mut(&mut self) -> &mut i64 {
-		unsafe { mem::transmute(&mut self.0) }
+		&mut self.0.int64
 	}
 }
 
@@ -1,4 +1,5 @@
 /*!
+Decimal data type. Incomplete.
 */
 
 use ::winapi::DECIMAL;
@@ -0,0 +1,859 @@
+/*!
+Late binding through `IDispatch`.
+
+Automation servers, eg. Office applications and scripting hosts, expose their members by name.
+[`DispatchPtr`](struct.DispatchPtr.html) looks up the names and invokes the members with [`Variant`](../variant/struct.Variant.html) arguments.
+
+Rust objects expose their members by implementing [`Dispatch`](trait.Dispatch.html) with [`com_dispatch!`](../macro.com_dispatch!.html),
+then listing `IDispatch` in [`com_object!`](../macro.com_object!.html) with [`get_ids_of_names`](fn.get_ids_of_names.html) and [`invoke`](fn.invoke.html).
+
+# Examples
+
+```ignore
+let excel: DispatchPtr = create_instance(&CLSID_EXCEL_APPLICATION)?;
+excel.put_property("Visible", &Variant::from(Bool::TRUE))?;
+let workbooks = excel.get_property("Workbooks")?;
+```
+
+Exposing a Rust object to scripts.
+
+```
+# #[macro_use] extern crate com_sys; #[macro_use] extern crate com_types; fn main() {
+use ::std::cell::Cell;
+use ::com_sys::dispatch::{IDispatch, IDispatchVtbl};
+use ::com_types::dispatch::{self, DispatchPtr, DispatchError};
+use ::com_types::object::ComBox;
+use ::com_types::variant::Variant;
+use ::com_types::variant::types::I4;
+
+struct Counter(Cell<i32>);
+impl Counter {
+	fn add(&self, amount: i32) -> Result<i32, DispatchError> {
+		self.0.set(self.0.get() + amount);
+		Ok(self.0.get())
+	}
+	fn count(&self) -> Result<i32, DispatchError> {
+		Ok(self.0.get())
+	}
+}
+com_dispatch! {
+	impl Counter {
+		fn Add(amount: I4) -> I4 = Counter::add;
+		get Count -> I4 = Counter::count;
+	}
+}
+com_object! {
+	impl Counter {
+		IDispatch(IDispatchVtbl {
+			base: IUnknownVtbl,
+			GetTypeInfoCount: dispatch::get_type_info_count,
+			GetTypeInfo: dispatch::get_type_info,
+			GetIDsOfNames: dispatch::get_ids_of_names::<Counter>,
+			Invoke: dispatch::invoke::<Counter>,
+		}),
+	}
+}
+
+let counter: DispatchPtr = ComBox::new(Counter(Cell::new(0)));
+counter.invoke_method("add", &[Variant::from(2)]).unwrap();
+assert_eq!(counter.get_property("Count").unwrap().into::<I4>(), 2);
+# }
+```
+*/
+
+use ::std::{any, error, fmt, mem, ptr, slice};
+use ::std::ffi::OsStr;
+use ::std::panic::{self, AssertUnwindSafe};
+
+use ::com_sys::Guid;
+use ::com_sys::dispatch::{IDispatch, IID_NULL, LOCALE_USER_DEFAULT};
+use ::com_sys::unknown::IUnknown;
+use ::winapi::{GUID, HRESULT, WORD, LCID, UINT, DISPID, LPOLESTR, VARIANT, DISPPARAMS, EXCEPINFO};
+
+use ::bstr::BString;
+use ::hr::{code, HResult};
+use ::com::ComResult;
+use ::object::{ComBox, ComObjectInterface};
+use ::unknown::IUnknownPtr;
+use ::variant::{Type, Variant};
+
+pub use ::com_sys::dispatch::{DISPID_UNKNOWN, DISPID_VALUE, DISPID_PROPERTYPUT};
+pub use ::com_sys::dispatch::{DISPATCH_METHOD, DISPATCH_PROPERTYGET, DISPATCH_PROPERTYPUT, DISPATCH_PROPERTYPUTREF};
+
+com_ptr! {
+	#[derive(Clone, Debug)]
+	pub struct DispatchPtr(IDispatch): IUnknownPtr;
+}
+impl DispatchPtr {
+	/// Maps a member name followed by the names of its parameters to their dispatch identifiers.
+	///
+	/// Fails with `DISP_E_UNKNOWNNAME` if any of the names is unknown.
+	pub fn get_ids_of_names(&self, names: &[&str]) -> ComResult<Vec<DISPID>> {
+		let wide: Vec<Vec<u16>> = names.iter().map(|name| name.encode_utf16().chain(Some(0)).collect()).collect();
+		let mut names: Vec<LPOLESTR> = wide.iter().map(|name| name.as_ptr() as LPOLESTR).collect();
+		let mut ids = vec![0; names.len()];
+		unsafe {
+			let hr = com_call!(GetIDsOfNames(self, &IID_NULL, names.as_mut_ptr(), names.len() as UINT, LOCALE_USER_DEFAULT, ids.as_mut_ptr()));
+			HResult::result(hr, ids)
+		}
+	}
+	/// Maps a member name to its dispatch identifier.
+	pub fn get_id_of_name(&self, name: &str) -> ComResult<DISPID> {
+		self.get_ids_of_names(&[name]).map(|ids| ids[0])
+	}
+	/// Invokes the member with the `DISPATCH_*` flags.
+	///
+	/// The arguments are passed in order, the named arguments are passed with their dispatch identifiers.
+	pub fn invoke(&self, member: DISPID, flags: WORD, args: &[Variant], named_args: &[(DISPID, &Variant)]) -> Result<Variant<'static>, DispatchError> {
+		unsafe {
+			// The callee borrows the arguments, named arguments come first and positional arguments follow in reverse.
+			let mut rgvarg: Vec<VARIANT> = named_args.iter().map(|&(_, arg)| *arg.as_ptr())
+				.chain(args.iter().rev().map(|arg| *arg.as_ptr()))
+				.collect();
+			let mut named: Vec<DISPID> = named_args.iter().map(|&(id, _)| id).collect();
+			let mut params = DISPPARAMS {
+				rgvarg: rgvarg.as_mut_ptr(),
+				rgdispidNamedArgs: named.as_mut_ptr(),
+				cArgs: rgvarg.len() as UINT,
+				cNamedArgs: named.len() as UINT,
+			};
+			let mut result = Variant::init();
+			let mut excep_info: EXCEPINFO = mem::zeroed();
+			let mut arg_err: UINT = 0;
+			let hr = HResult::from(com_call!(Invoke(self, member, &IID_NULL, LOCALE_USER_DEFAULT, flags,
+				&mut params, result.as_mut_ptr(), &mut excep_info, &mut arg_err)));
+			if hr.succeeded() {
+				return Ok(result);
+			}
+			let arg_err = arg_err as usize;
+			Err(match hr {
+				code::DISP_E_EXCEPTION => DispatchError::Exception(Exception::from_raw(&mut excep_info)),
+				code::DISP_E_TYPEMISMATCH | code::DISP_E_PARAMNOTFOUND if arg_err < rgvarg.len() => {
+					let index = if arg_err < named.len() { args.len() + arg_err } else { rgvarg.len() - 1 - arg_err };
+					DispatchError::Argument(hr, index)
+				},
+				_ => DispatchError::Failed(hr),
+			})
+		}
+	}
+	/// Invokes the method by name.
+	pub fn invoke_method(&self, name: &str, args: &[Variant]) -> Result<Variant<'static>, DispatchError> {
+		let member = self.get_id_of_name(name)?;
+		self.invoke(member, DISPATCH_METHOD, args, &[])
+	}
+	/// Invokes the method by name, passing arguments by the name of their parameter after the positional arguments.
+	pub fn invoke_method_named(&self, name: &str, args: &[Variant], named_args: &[(&str, &Variant)]) -> Result<Variant<'static>, DispatchError> {
+		let names: Vec<&str> = Some(name).into_iter().chain(named_args.iter().map(|&(name, _)| name)).collect();
+		let ids = self.get_ids_of_names(&names)?;
+		let named_args: Vec<(DISPID, &Variant)> = ids[1..].iter().zip(named_args).map(|(&id, &(_, arg))| (id, arg)).collect();
+		self.invoke(ids[0], DISPATCH_METHOD, args, &named_args)
+	}
+	/// Gets the value of the property by name.
+	pub fn get_property(&self, name: &str) -> Result<Variant<'static>, DispatchError> {
+		let member = self.get_id_of_name(name)?;
+		self.invoke(member, DISPATCH_PROPERTYGET, &[], &[])
+	}
+	/// Puts the value of the property by name.
+	pub fn put_property(&self, name: &str, value: &Variant) -> Result<(), DispatchError> {
+		let member = self.get_id_of_name(name)?;
+		self.invoke(member, DISPATCH_PROPERTYPUT, &[], &[(DISPID_PROPERTYPUT, value)]).map(drop)
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Exception raised by a member, decoded from `EXCEPINFO`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Exception {
+	/// Error code of the server, zero if `scode` is used instead.
+	pub code: u16,
+	/// Error code as `HResult`, zero if `code` is used instead.
+	pub scode: HResult,
+	/// Name of the source of the exception, typically the application name.
+	pub source: BString,
+	/// Description of the error intended for the user.
+	pub description: BString,
+	/// Path of the help file with more information about the error.
+	pub help_file: BString,
+	/// Help context identifier within the help file.
+	pub help_context: u32,
+}
+impl Exception {
+	/// Creates an exception with the error code and description.
+	pub fn new<S: AsRef<OsStr>>(scode: HResult, description: S) -> Exception {
+		Exception {
+			code: 0,
+			scode,
+			source: BString::new(),
+			description: BString::from(description),
+			help_file: BString::new(),
+			help_context: 0,
+		}
+	}
+	/// Takes ownership of the strings in the raw `EXCEPINFO`, filling it in first if it was deferred.
+	///
+	/// # Safety
+	///
+	/// The `EXCEPINFO` must have been filled in by a failed `Invoke` with `DISP_E_EXCEPTION`.
+	pub unsafe fn from_raw(raw: &mut EXCEPINFO) -> Exception {
+		if let Some(fill_in) = raw.pfnDeferredFillIn.take() {
+			fill_in(raw);
+		}
+		let exception = Exception {
+			code: raw.wCode,
+			scode: HResult::from(raw.scode),
+			source: BString::from_raw(raw.bstrSource),
+			description: BString::from_raw(raw.bstrDescription),
+			help_file: BString::from_raw(raw.bstrHelpFile),
+			help_context: raw.dwHelpContext,
+		};
+		raw.bstrSource = ptr::null_mut();
+		raw.bstrDescription = ptr::null_mut();
+		raw.bstrHelpFile = ptr::null_mut();
+		exception
+	}
+	/// Surrenders ownership of the strings to a raw `EXCEPINFO` for FFI.
+	pub fn into_raw(self) -> EXCEPINFO {
+		unsafe {
+			let mut raw: EXCEPINFO = mem::zeroed();
+			raw.wCode = self.code;
+			raw.scode = self.scode.into();
+			raw.bstrSource = self.source.into_raw();
+			raw.bstrDescription = self.description.into_raw();
+			raw.bstrHelpFile = self.help_file.into_raw();
+			raw.dwHelpContext = self.help_context;
+			raw
+		}
+	}
+	/// Returns the error as `HResult`, `DISP_E_EXCEPTION` unless the exception carries a failed `scode`.
+	pub fn hresult(&self) -> HResult {
+		if self.scode.failed() { self.scode } else { code::DISP_E_EXCEPTION }
+	}
+}
+impl fmt::Display for Exception {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		if !self.source.is_empty() {
+			write!(f, "{}: ", self.source.to_os_string().to_string_lossy())?;
+		}
+		if !self.description.is_empty() {
+			write!(f, "{} ", self.description.to_os_string().to_string_lossy())?;
+		}
+		if self.scode.failed() {
+			write!(f, "({})", self.scode)
+		}
+		else {
+			write!(f, "(code {})", self.code)
+		}
+	}
+}
+
+/// Error of invoking a member through [`DispatchPtr`](struct.DispatchPtr.html).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DispatchError {
+	/// The call failed, eg. `DISP_E_UNKNOWNNAME` or `DISP_E_MEMBERNOTFOUND`.
+	Failed(HResult),
+	/// The argument was rejected with `DISP_E_TYPEMISMATCH` or `DISP_E_PARAMNOTFOUND`.
+	///
+	/// The index counts the positional arguments followed by the named arguments, in the order they were passed.
+	Argument(HResult, usize),
+	/// The member raised an exception, `DISP_E_EXCEPTION`.
+	Exception(Exception),
+}
+impl DispatchError {
+	/// Returns the error as `HResult`.
+	pub fn hresult(&self) -> HResult {
+		match *self {
+			DispatchError::Failed(hr) => hr,
+			DispatchError::Argument(hr, _) => hr,
+			DispatchError::Exception(ref exception) => exception.hresult(),
+		}
+	}
+}
+impl From<HResult> for DispatchError {
+	fn from(hr: HResult) -> DispatchError {
+		DispatchError::Failed(hr)
+	}
+}
+impl From<Exception> for DispatchError {
+	fn from(exception: Exception) -> DispatchError {
+		DispatchError::Exception(exception)
+	}
+}
+impl From<DispatchError> for HResult {
+	fn from(err: DispatchError) -> HResult {
+		err.hresult()
+	}
+}
+impl fmt::Display for DispatchError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			DispatchError::Failed(hr) => write!(f, "invoke failed with {}", hr),
+			DispatchError::Argument(hr, index) => write!(f, "argument {} rejected with {}", index, hr),
+			DispatchError::Exception(ref exception) => write!(f, "exception {}", exception),
+		}
+	}
+}
+impl error::Error for DispatchError {}
+
+//----------------------------------------------------------------
+
+/// Exposes the members of a Rust object through `IDispatch`.
+///
+/// Implement with [`com_dispatch!`](../macro.com_dispatch!.html).
+pub trait Dispatch: Sized + 'static {
+	/// The member table, a property is listed once for getting and once for putting.
+	const MEMBERS: &'static [Member<Self>];
+}
+
+/// Member of a [`Dispatch`](trait.Dispatch.html) object.
+pub struct Member<T> {
+	/// Name of the member, looked up ignoring case.
+	pub name: &'static str,
+	/// Names of the parameters, their dispatch identifier is their index.
+	pub params: &'static [&'static str],
+	/// How the member is invoked, one of `DISPATCH_METHOD`, `DISPATCH_PROPERTYGET` or `DISPATCH_PROPERTYPUT`.
+	pub flags: WORD,
+	/// Unpacks the arguments and invokes the member.
+	pub invoke: fn(&T, &Args) -> Result<Variant<'static>, DispatchError>,
+}
+
+/// Arguments passed to a [`Member`](struct.Member.html).
+///
+/// The index of a `DispatchError::Argument` is the position of the argument in `DISPPARAMS`, it is reported back to the caller.
+pub struct Args<'a> {
+	params: &'a DISPPARAMS,
+	names: &'static [&'static str],
+}
+impl<'a> Args<'a> {
+	/// Validates the argument count and the dispatch identifiers of the named arguments.
+	///
+	/// Only property puts are passed their value as the `DISPID_PROPERTYPUT` named argument.
+	unsafe fn new(params: &'a DISPPARAMS, names: &'static [&'static str], put: bool) -> Result<Args<'a>, DispatchError> {
+		let (count, named) = (params.cArgs as usize, params.cNamedArgs as usize);
+		if named > count || count - named > names.len() {
+			return Err(DispatchError::Failed(code::DISP_E_BADPARAMCOUNT));
+		}
+		for index in 0..named {
+			let id = *params.rgdispidNamedArgs.add(index);
+			let known = if id == DISPID_PROPERTYPUT { put } else { id >= 0 && (id as usize) < names.len() };
+			if !known {
+				return Err(DispatchError::Argument(code::DISP_E_PARAMNOTFOUND, index));
+			}
+		}
+		Ok(Args { params, names })
+	}
+	/// Returns the position of the argument passed for the dispatch identifier.
+	fn position(&self, id: DISPID) -> Option<usize> {
+		let (count, named) = (self.params.cArgs as usize, self.params.cNamedArgs as usize);
+		// Named arguments come first, positional arguments follow in reverse.
+		let ids = unsafe { slice::from_raw_parts(self.params.rgdispidNamedArgs as *const DISPID, named) };
+		ids.iter().position(|&named_id| named_id == id)
+			.or_else(|| if id >= 0 && (id as usize) < count - named { Some(count - 1 - id as usize) } else { None })
+	}
+	fn unpack<T: Type<'static>>(&self, id: DISPID) -> Result<T::Owned, DispatchError> {
+		let index = self.position(id).ok_or(DispatchError::Failed(code::DISP_E_PARAMNOTOPTIONAL))?;
+		let arg = unsafe { &*(self.params.rgvarg.add(index) as *const Variant<'static>) };
+		// Coerces like `DispInvoke`, dereferencing arguments passed by reference.
+		arg.to::<T>().map_err(|hr| {
+			let hr = if hr == code::DISP_E_OVERFLOW { hr } else { code::DISP_E_TYPEMISMATCH };
+			DispatchError::Argument(hr, index)
+		})
+	}
+	/// Returns the argument for the parameter, passed by position or by name.
+	pub fn get(&self, name: &str) -> Option<&Variant<'static>> {
+		let id = self.names.iter().position(|&param| param == name)?;
+		let index = self.position(id as DISPID)?;
+		Some(unsafe { &*(self.params.rgvarg.add(index) as *const Variant<'static>) })
+	}
+	/// Unpacks the argument for the parameter as `T`.
+	///
+	/// The argument is coerced to `T` with [`Variant::change_type`](../variant/struct.Variant.html#method.change_type).
+	/// Fails with `DISP_E_PARAMNOTOPTIONAL` if it was not passed, `DISP_E_TYPEMISMATCH` if it cannot be coerced and `DISP_E_OVERFLOW` if it does not fit.
+	///
+	/// # Panics
+	///
+	/// The parameter must be declared by the member.
+	pub fn arg<T: Type<'static>>(&self, name: &str) -> Result<T::Owned, DispatchError> {
+		let id = self.names.iter().position(|&param| param == name).expect("undeclared parameter");
+		self.unpack::<T>(id as DISPID)
+	}
+	/// Unpacks the value of a property put as `T`.
+	pub fn value<T: Type<'static>>(&self) -> Result<T::Owned, DispatchError> {
+		self.unpack::<T>(DISPID_PROPERTYPUT)
+	}
+}
+
+/// Implements `IDispatch::GetTypeInfoCount`, `Dispatch` objects provide no type information.
+///
+/// # Safety
+///
+/// Only to be listed in the `IDispatch` vtbl in `com_object!`.
+pub unsafe extern "system" fn get_type_info_count(_this: *mut IDispatch, pctinfo: *mut UINT) -> HRESULT {
+	if pctinfo.is_null() {
+		return code::E_POINTER.into();
+	}
+	*pctinfo = 0;
+	code::S_OK.into()
+}
+
+/// Implements `IDispatch::GetTypeInfo`, `Dispatch` objects provide no type information.
+///
+/// # Safety
+///
+/// Only to be listed in the `IDispatch` vtbl in `com_object!`.
+pub unsafe extern "system" fn get_type_info(_this: *mut IDispatch, _itinfo: UINT, _lcid: LCID, pptinfo: *mut *mut IUnknown) -> HRESULT {
+	if !pptinfo.is_null() {
+		*pptinfo = ptr::null_mut();
+	}
+	code::DISP_E_BADINDEX.into()
+}
+
+/// Implements `IDispatch::GetIDsOfNames` for the `ComBox<T>` object from its member table.
+///
+/// The dispatch identifier of a member is its index in the table plus one, the parameter names are looked up in the first name's member.
+///
+/// # Safety
+///
+/// Only to be listed in the `IDispatch` vtbl of `T` in `com_object!`.
+pub unsafe extern "system" fn get_ids_of_names<T>(_this: *mut IDispatch, _riid: *const GUID, names: *mut LPOLESTR, count: UINT, _lcid: LCID, ids: *mut DISPID) -> HRESULT
+	where T: Dispatch + ComObjectInterface<IDispatch>
+{
+	if names.is_null() || ids.is_null() {
+		return code::E_POINTER.into();
+	}
+	let names = slice::from_raw_parts(names as *const LPOLESTR, count as usize);
+	let ids = slice::from_raw_parts_mut(ids, count as usize);
+	let mut hr = code::S_OK;
+	let mut member = None;
+	for (index, (&name, id)) in names.iter().zip(ids.iter_mut()).enumerate() {
+		let len = (0..).take_while(|&i| *name.add(i) != 0).count();
+		let name = String::from_utf16_lossy(slice::from_raw_parts(name, len));
+		let found = if index == 0 {
+			member = T::MEMBERS.iter().position(|member| member.name.eq_ignore_ascii_case(&name));
+			member.map(|member| member as DISPID + 1)
+		}
+		else {
+			member.and_then(|member| T::MEMBERS[member].params.iter().position(|param| param.eq_ignore_ascii_case(&name))).map(|param| param as DISPID)
+		};
+		*id = found.unwrap_or_else(|| {
+			hr = code::DISP_E_UNKNOWNNAME;
+			DISPID_UNKNOWN
+		});
+	}
+	hr.into()
+}
+
+/// Implements `IDispatch::Invoke` for the `ComBox<T>` object from its member table.
+///
+/// Methods are preferred over property gets when both are requested, as scripting hosts do.
+/// A panicking member raises an `E_UNEXPECTED` exception with the panic message.
+///
+/// # Safety
+///
+/// Only to be listed in the `IDispatch` vtbl of `T` in `com_object!`.
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "system" fn invoke<T>(this: *mut IDispatch, member: DISPID, riid: *const GUID, _lcid: LCID, flags: WORD,
+	params: *mut DISPPARAMS, result: *mut VARIANT, excep_info: *mut EXCEPINFO, arg_err: *mut UINT) -> HRESULT
+	where T: Dispatch + ComObjectInterface<IDispatch>
+{
+	if params.is_null() {
+		return code::E_POINTER.into();
+	}
+	if !riid.is_null() && Guid::from_raw(&*riid) != Guid::from_raw(&IID_NULL) {
+		return code::DISP_E_UNKNOWNINTERFACE.into();
+	}
+	let name = match T::MEMBERS.get((member as usize).wrapping_sub(1)) {
+		Some(member) => member.name,
+		None => return code::DISP_E_MEMBERNOTFOUND.into(),
+	};
+	let kinds = [DISPATCH_METHOD, DISPATCH_PROPERTYGET, DISPATCH_PROPERTYPUT | DISPATCH_PROPERTYPUTREF];
+	let member = kinds.iter()
+		.filter(|&&kind| flags & kind != 0)
+		.filter_map(|&kind| T::MEMBERS.iter().find(|member| member.name == name && member.flags & kind != 0))
+		.next();
+	let member = match member {
+		Some(member) => member,
+		None => return code::DISP_E_MEMBERNOTFOUND.into(),
+	};
+	let put = member.flags & (DISPATCH_PROPERTYPUT | DISPATCH_PROPERTYPUTREF) != 0;
+	let value = Args::new(&*params, member.params, put).and_then(|args| {
+		// Unwinding out of `Invoke` aborts, a panic is raised as exception instead.
+		panic::catch_unwind(AssertUnwindSafe(|| (member.invoke)(ComBox::<T>::from_interface(this), &args)))
+			.unwrap_or_else(|payload| Err(DispatchError::Exception(panic_exception(payload))))
+	});
+	match value {
+		Ok(value) => {
+			if !result.is_null() {
+				*result = value.into_raw();
+			}
+			code::S_OK.into()
+		},
+		Err(DispatchError::Failed(hr)) => hr.into(),
+		Err(DispatchError::Argument(hr, index)) => {
+			if !arg_err.is_null() {
+				*arg_err = index as UINT;
+			}
+			hr.into()
+		},
+		Err(DispatchError::Exception(exception)) => {
+			if excep_info.is_null() {
+				return exception.hresult().into();
+			}
+			*excep_info = exception.into_raw();
+			code::DISP_E_EXCEPTION.into()
+		},
+	}
+}
+
+/// Describes the panic of a member as `E_UNEXPECTED` exception.
+fn panic_exception(payload: Box<dyn any::Any + Send>) -> Exception {
+	let message = match payload.downcast_ref::<&'static str>() {
+		Some(message) => message.to_string(),
+		None => payload.downcast_ref::<String>().cloned().unwrap_or_else(|| String::from("panicked")),
+	};
+	Exception::new(code::E_UNEXPECTED, message)
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use ::std::cell::RefCell;
+	use ::std::mem::ManuallyDrop;
+	use ::com_sys::dispatch::IDispatchVtbl;
+	use ::bstr::AsRawBStr;
+	use ::variant::types::{BString as BStringType, I4};
+	use super::*;
+
+	const SUB: DISPID = 1;
+	const NAME: DISPID = 2;
+	const FAIL: DISPID = 3;
+
+	/// Dispatches `Sub(a, b)`, the `Name` property and `Fail()` by hand.
+	struct Calc {
+		name: RefCell<BString>,
+	}
+
+	unsafe extern "system" fn calc_get_ids_of_names(_this: *mut IDispatch, _riid: *const GUID, names: *mut LPOLESTR, count: UINT, _lcid: LCID, ids: *mut DISPID) -> HRESULT {
+		let names = slice::from_raw_parts(names, count as usize);
+		let ids = slice::from_raw_parts_mut(ids, count as usize);
+		let mut hr = code::S_OK;
+		for (i, &name) in names.iter().enumerate() {
+			let len = (0..).take_while(|&j| *name.offset(j) != 0).count();
+			let name = String::from_utf16_lossy(slice::from_raw_parts(name, len));
+			ids[i] = match (i, &*name) {
+				(0, "Sub") => SUB,
+				(0, "Name") => NAME,
+				(0, "Fail") => FAIL,
+				(_, "a") if ids[0] == SUB => 0,
+				(_, "b") if ids[0] == SUB => 1,
+				_ => {
+					hr = code::DISP_E_UNKNOWNNAME;
+					DISPID_UNKNOWN
+				},
+			};
+		}
+		hr.into()
+	}
+	unsafe fn arg(params: &DISPPARAMS, index: usize) -> ManuallyDrop<Variant<'static>> {
+		ManuallyDrop::new(Variant::from_raw(*params.rgvarg.add(index)))
+	}
+	unsafe fn named(params: &DISPPARAMS, index: usize) -> DISPID {
+		// Positional arguments are passed in reverse, their dispatch identifier is their parameter index.
+		if index < params.cNamedArgs as usize { *params.rgdispidNamedArgs.add(index) } else { (params.cArgs as usize - 1 - index) as DISPID }
+	}
+	unsafe extern "system" fn calc_invoke(this: *mut IDispatch, member: DISPID, _riid: *const GUID, _lcid: LCID, flags: WORD,
+		params: *mut DISPPARAMS, result: *mut VARIANT, excep_info: *mut EXCEPINFO, arg_err: *mut UINT) -> HRESULT
+	{
+		let calc = ComBox::<Calc>::from_interface(this);
+		let params = &*params;
+		match (member, flags) {
+			(SUB, DISPATCH_METHOD) => {
+				let mut values = [None, None];
+				for index in 0..params.cArgs as usize {
+					let value = match arg(params, index).try_borrow::<I4>() {
+						Some(&value) => value,
+						None => {
+							*arg_err = index as UINT;
+							return code::DISP_E_TYPEMISMATCH.into();
+						},
+					};
+					values[named(params, index) as usize] = Some(value);
+				}
+				match values {
+					[Some(a), Some(b)] => {
+						*result = Variant::from(a - b).into_raw();
+						code::S_OK.into()
+					},
+					_ => code::DISP_E_BADPARAMCOUNT.into(),
+				}
+			},
+			(NAME, DISPATCH_PROPERTYGET) => {
+				*result = Variant::from(calc.name.borrow().clone()).into_raw();
+				code::S_OK.into()
+			},
+			(NAME, DISPATCH_PROPERTYPUT) => {
+				if params.cNamedArgs != 1 || named(params, 0) != DISPID_PROPERTYPUT {
+					return code::DISP_E_PARAMNOTFOUND.into();
+				}
+				match arg(params, 0).try_borrow::<BStringType>() {
+					Some(name) => {
+						*calc.name.borrow_mut() = name.to_owned();
+						code::S_OK.into()
+					},
+					None => {
+						*arg_err = 0;
+						code::DISP_E_TYPEMISMATCH.into()
+					},
+				}
+			},
+			(FAIL, DISPATCH_METHOD) => {
+				(*excep_info).bstrSource = BString::from("Calc").into_raw();
+				(*excep_info).bstrDescription = BString::from("Division by zero.").into_raw();
+				(*excep_info).scode = code::DISP_E_DIVBYZERO.into();
+				code::DISP_E_EXCEPTION.into()
+			},
+			_ => code::DISP_E_MEMBERNOTFOUND.into(),
+		}
+	}
+
+	com_object! {
+		impl Calc {
+			IDispatch(IDispatchVtbl {
+				base: IUnknownVtbl,
+				GetTypeInfoCount: get_type_info_count,
+				GetTypeInfo: get_type_info,
+				GetIDsOfNames: calc_get_ids_of_names,
+				Invoke: calc_invoke,
+			}),
+		}
+	}
+
+	fn calc() -> DispatchPtr {
+		ComBox::new(Calc { name: RefCell::new(BString::from("calc")) })
+	}
+
+	#[test]
+	fn names() {
+		let calc = calc();
+		assert_eq!(calc.get_ids_of_names(&["Sub", "b", "a"]), Ok(vec![SUB, 1, 0]));
+		assert_eq!(calc.get_id_of_name("Name"), Ok(NAME));
+		assert_eq!(calc.get_id_of_name("Mul"), Err(code::DISP_E_UNKNOWNNAME));
+	}
+
+	#[test]
+	fn methods() {
+		let calc = calc();
+		let diff = calc.invoke_method("Sub", &[Variant::from(7), Variant::from(2)]).unwrap();
+		assert_eq!(diff.into::<I4>(), 5);
+
+		let diff = calc.invoke_method_named("Sub", &[], &[("b", &Variant::from(2)), ("a", &Variant::from(7))]).unwrap();
+		assert_eq!(diff.into::<I4>(), 5);
+		let diff = calc.invoke_method_named("Sub", &[Variant::from(7)], &[("b", &Variant::from(2))]).unwrap();
+		assert_eq!(diff.into::<I4>(), 5);
+
+		let mismatch = calc.invoke_method("Sub", &[Variant::from(7), Variant::from(BString::from("2"))]);
+		assert_eq!(mismatch.err(), Some(DispatchError::Argument(code::DISP_E_TYPEMISMATCH, 1)));
+		let mismatch = calc.invoke_method_named("Sub", &[Variant::from(7)], &[("b", &Variant::from(BString::from("2")))]);
+		assert_eq!(mismatch.err(), Some(DispatchError::Argument(code::DISP_E_TYPEMISMATCH, 1)));
+
+		let unknown = calc.invoke_method("Mul", &[]);
+		assert_eq!(unknown.err(), Some(DispatchError::Failed(code::DISP_E_UNKNOWNNAME)));
+	}
+
+	#[test]
+	fn properties() {
+		let calc = calc();
+		assert_eq!(calc.get_property("Name").unwrap().into::<BStringType>(), "calc");
+		calc.put_property("Name", &Variant::from(BString::from("abacus"))).unwrap();
+		assert_eq!(calc.get_property("Name").unwrap().into::<BStringType>(), "abacus");
+		let mismatch = calc.put_property("Name", &Variant::from(1));
+		assert_eq!(mismatch.err().map(|err| err.hresult()), Some(code::DISP_E_TYPEMISMATCH));
+		assert_eq!(calc.get_property("Sub").err(), Some(DispatchError::Failed(code::DISP_E_MEMBERNOTFOUND)));
+	}
+
+	#[test]
+	fn exceptions() {
+		let err = calc().invoke_method("Fail", &[]).unwrap_err();
+		assert_eq!(err.hresult(), code::DISP_E_DIVBYZERO);
+		match err {
+			DispatchError::Exception(ref exception) => {
+				assert_eq!(exception.source, "Calc");
+				assert_eq!(exception.description, "Division by zero.");
+				assert!(exception.help_file.as_raw().is_null());
+			},
+			_ => panic!("{:?}", err),
+		}
+		assert_eq!(err.to_string(), "exception Calc: Division by zero. (0x80020012)");
+	}
+
+	//----------------------------------------------------------------
+
+	/// Dispatches through its member table.
+	struct Greeter {
+		greeting: RefCell<BString>,
+	}
+	impl Greeter {
+		fn greet(&self, name: BString, times: i32) -> Result<BString, DispatchError> {
+			let greeting = format!("{}, {}!", self.greeting.borrow().to_os_string().to_string_lossy(), name.to_os_string().to_string_lossy());
+			Ok(BString::from(greeting.repeat(times as usize)))
+		}
+		fn reset(&self) -> Result<(), DispatchError> {
+			*self.greeting.borrow_mut() = BString::from("Hello");
+			Ok(())
+		}
+		fn fail(&self) -> Result<(), Exception> {
+			Err(Exception::new(code::E_ACCESSDENIED, "Not allowed."))
+		}
+		fn greeting(&self) -> Result<BString, DispatchError> {
+			Ok(self.greeting.borrow().clone())
+		}
+		fn set_greeting(&self, greeting: BString) -> Result<(), DispatchError> {
+			*self.greeting.borrow_mut() = greeting;
+			Ok(())
+		}
+		fn crash(&self) -> Result<(), DispatchError> {
+			panic!("crashed")
+		}
+	}
+	com_dispatch! {
+		impl Greeter {
+			fn Greet(name: BStringType, times: I4) -> BStringType = Greeter::greet;
+			fn Reset() = Greeter::reset;
+			fn Fail() = Greeter::fail;
+			get Greeting -> BStringType = Greeter::greeting;
+			put Greeting(value: BStringType) = Greeter::set_greeting;
+			fn Crash() = Greeter::crash;
+		}
+	}
+	com_object! {
+		impl Greeter {
+			IDispatch(IDispatchVtbl {
+				base: IUnknownVtbl,
+				GetTypeInfoCount: get_type_info_count,
+				GetTypeInfo: get_type_info,
+				GetIDsOfNames: get_ids_of_names::<Greeter>,
+				Invoke: invoke::<Greeter>,
+			}),
+		}
+	}
+
+	fn greeter() -> DispatchPtr {
+		ComBox::new(Greeter { greeting: RefCell::new(BString::from("Hi")) })
+	}
+
+	#[test]
+	fn member_names() {
+		let greeter = greeter();
+		assert_eq!(greeter.get_ids_of_names(&["greet", "TIMES", "name"]), Ok(vec![1, 1, 0]));
+		assert_eq!(greeter.get_id_of_name("Greeting"), Ok(4));
+		assert_eq!(greeter.get_ids_of_names(&["Reset", "name"]), Err(code::DISP_E_UNKNOWNNAME));
+		assert_eq!(greeter.get_id_of_name("Wave"), Err(code::DISP_E_UNKNOWNNAME));
+	}
+
+	#[test]
+	fn member_methods() {
+		let greeter = greeter();
+		let greeting = greeter.invoke_method("Greet", &[Variant::from(BString::from("Rust")), Variant::from(2)]).unwrap();
+		assert_eq!(greeting.into::<BStringType>(), "Hi, Rust!Hi, Rust!");
+		let greeting = greeter.invoke_method_named("Greet", &[], &[("times", &Variant::from(1)), ("name", &Variant::from(BString::from("COM")))]).unwrap();
+		assert_eq!(greeting.into::<BStringType>(), "Hi, COM!");
+
+		// Arguments passed by reference are dereferenced.
+		let times = 1;
+		let greeting = greeter.invoke_method("Greet", &[Variant::from(BString::from("VB")), Variant::from(&times)]).unwrap();
+		assert_eq!(greeting.into::<BStringType>(), "Hi, VB!");
+		let name = Variant::from(BString::from("JScript"));
+		let greeting = greeter.invoke_method("Greet", &[Variant::from(&name), Variant::from(1)]).unwrap();
+		assert_eq!(greeting.into::<BStringType>(), "Hi, JScript!");
+
+		assert!(greeter.invoke_method("Reset", &[]).unwrap().is::<::variant::types::Empty>());
+		assert_eq!(greeter.get_property("Greeting").unwrap().into::<BStringType>(), "Hello");
+	}
+
+	#[test]
+	fn member_properties() {
+		let greeter = greeter();
+		greeter.put_property("greeting", &Variant::from(BString::from("Howdy"))).unwrap();
+		assert_eq!(greeter.get_property("GREETING").unwrap().into::<BStringType>(), "Howdy");
+		assert_eq!(greeter.get_property("Reset").err(), Some(DispatchError::Failed(code::DISP_E_MEMBERNOTFOUND)));
+
+		// Scripting hosts invoke members as either method or property get.
+		let id = greeter.get_id_of_name("Greeting").unwrap();
+		let greeting = greeter.invoke(id, DISPATCH_METHOD | DISPATCH_PROPERTYGET, &[], &[]).unwrap();
+		assert_eq!(greeting.into::<BStringType>(), "Howdy");
+	}
+
+	#[test]
+	fn member_errors() {
+		let greeter = greeter();
+		let mismatch = greeter.invoke_method("Greet", &[Variant::from(::variant::types::Null), Variant::from(2)]);
+		assert_eq!(mismatch.err(), Some(DispatchError::Argument(code::DISP_E_TYPEMISMATCH, 0)));
+		let mismatch = greeter.invoke_method("Greet", &[Variant::from(BString::from("Rust")), Variant::from(BString::from("twice"))]);
+		assert_eq!(mismatch.err(), Some(DispatchError::Argument(code::DISP_E_TYPEMISMATCH, 1)));
+		let overflow = greeter.invoke_method("Greet", &[Variant::from(BString::from("Rust")), Variant::from(1e10)]);
+		assert_eq!(overflow.err().map(|err| err.hresult()), Some(code::DISP_E_OVERFLOW));
+		let mismatch = greeter.put_property("Greeting", &Variant::from(::variant::types::Null));
+		assert_eq!(mismatch.err(), Some(DispatchError::Argument(code::DISP_E_TYPEMISMATCH, 0)));
+		let missing = greeter.invoke_method("Greet", &[Variant::from(BString::from("Rust"))]);
+		assert_eq!(missing.err(), Some(DispatchError::Failed(code::DISP_E_PARAMNOTOPTIONAL)));
+		let too_many = greeter.invoke_method("Reset", &[Variant::from(1)]);
+		assert_eq!(too_many.err(), Some(DispatchError::Failed(code::DISP_E_BADPARAMCOUNT)));
+
+		let err = greeter.invoke_method("Fail", &[]).unwrap_err();
+		assert_eq!(err.hresult(), code::E_ACCESSDENIED);
+		assert_eq!(err.to_string(), "exception Not allowed. (0x80070005)");
+
+		// Panics do not unwind out of `Invoke`.
+		let err = greeter.invoke_method("Crash", &[]).unwrap_err();
+		assert_eq!(err.hresult(), code::E_UNEXPECTED);
+		assert_eq!(err.to_string(), "exception crashed (0x8000FFFF)");
+	}
+
+	#[test]
+	fn member_coercion() {
+		let greeter = greeter();
+		let greeting = greeter.invoke_method("Greet", &[Variant::from(BString::from("Rust")), Variant::from(BString::from("2"))]).unwrap();
+		assert_eq!(greeting.into::<BStringType>(), "Hi, Rust!Hi, Rust!");
+		let times = 1.0;
+		let greeting = greeter.invoke_method("Greet", &[Variant::from(BString::from("VB")), Variant::from(&times)]).unwrap();
+		assert_eq!(greeting.into::<BStringType>(), "Hi, VB!");
+		greeter.put_property("Greeting", &Variant::from(42)).unwrap();
+		assert_eq!(greeter.get_property("Greeting").unwrap().into::<BStringType>(), "42");
+	}
+
+	#[test]
+	fn member_invoke() {
+		let greeter = greeter();
+		let mut args = [Variant::from(1).into_raw(), Variant::from(2).into_raw()];
+		let mut named = [1, 7];
+		let mut params = DISPPARAMS { rgvarg: args.as_mut_ptr(), rgdispidNamedArgs: named.as_mut_ptr(), cArgs: 2, cNamedArgs: 2 };
+		let mut arg_err = UINT::MAX;
+		unsafe {
+			let hr = com_call!(Invoke(&greeter, 1, &IID_NULL, LOCALE_USER_DEFAULT, DISPATCH_METHOD,
+				&mut params, ptr::null_mut(), ptr::null_mut(), &mut arg_err));
+			assert_eq!(HResult::from(hr), code::DISP_E_PARAMNOTFOUND);
+			assert_eq!(arg_err, 1);
+
+			let hr = com_call!(Invoke(&greeter, 42, &IID_NULL, LOCALE_USER_DEFAULT, DISPATCH_METHOD,
+				&mut params, ptr::null_mut(), ptr::null_mut(), &mut arg_err));
+			assert_eq!(HResult::from(hr), code::DISP_E_MEMBERNOTFOUND);
+
+			// Only property puts take the value as named argument.
+			let mut named = [DISPID_PROPERTYPUT];
+			params.rgdispidNamedArgs = named.as_mut_ptr();
+			params.cNamedArgs = 1;
+			let hr = com_call!(Invoke(&greeter, 1, &IID_NULL, LOCALE_USER_DEFAULT, DISPATCH_METHOD,
+				&mut params, ptr::null_mut(), ptr::null_mut(), &mut arg_err));
+			assert_eq!(HResult::from(hr), code::DISP_E_PARAMNOTFOUND);
+			assert_eq!(arg_err, 0);
+
+			// Without `EXCEPINFO` the exception is reported by its error code.
+			params.cArgs = 0;
+			params.cNamedArgs = 0;
+			let hr = com_call!(Invoke(&greeter, 3, &IID_NULL, LOCALE_USER_DEFAULT, DISPATCH_METHOD,
+				&mut params, ptr::null_mut(), ptr::null_mut(), &mut arg_err));
+			assert_eq!(HResult::from(hr), code::E_ACCESSDENIED);
+		}
+	}
+}
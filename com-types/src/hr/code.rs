@@ -15,3 +15,23 @@ pub const E_ACCESSDENIED: HResult = HResult(0x80070005);
 pub const E_HANDLE: HResult = HResult(0x80070006);
 pub const E_OUTOFMEMORY: HResult = HResult(0x8007000E);
 pub const E_INVALIDARG: HResult = HResult(0x80070057);
+
+pub const DISP_E_UNKNOWNINTERFACE: HResult = HResult(0x80020001);
+pub const DISP_E_MEMBERNOTFOUND: HResult = HResult(0x80020003);
+pub const DISP_E_PARAMNOTFOUND: HResult = HResult(0x80020004);
+pub const DISP_E_TYPEMISMATCH: HResult = HResult(0x80020005);
+pub const DISP_E_UNKNOWNNAME: HResult = HResult(0x80020006);
+pub const DISP_E_NONAMEDARGS: HResult = HResult(0x80020007);
+pub const DISP_E_BADVARTYPE: HResult = HResult(0x80020008);
+pub const DISP_E_EXCEPTION: HResult = HResult(0x80020009);
+pub const DISP_E_OVERFLOW: HResult = HResult(0x8002000A);
+pub const DISP_E_BADINDEX: HResult = HResult(0x8002000B);
+pub const DISP_E_UNKNOWNLCID: HResult = HResult(0x8002000C);
+pub const DISP_E_ARRAYISLOCKED: HResult = HResult(0x8002000D);
+pub const DISP_E_BADPARAMCOUNT: HResult = HResult(0x8002000E);
+pub const DISP_E_PARAMNOTOPTIONAL: HResult = HResult(0x8002000F);
+pub const DISP_E_BADCALLEE: HResult = HResult(0x80020010);
+pub const DISP_E_NOTACOLLECTION: HResult = HResult(0x80020011);
+pub const DISP_E_DIVBYZERO: HResult = HResult(0x80020012);
+
+pub const TYPE_E_FIELDNOTFOUND: HResult = HResult(0x80028017);
@@ -0,0 +1,453 @@
+/*!
+Reference count leak detection for tests.
+
+Only available with the `leak` feature, enable it for the tests of crates using com pointers:
+
+```toml
+[dev-dependencies]
+com-types = { version = "0.1", features = ["leak"] }
+```
+
+[`LeakDetector::track`](struct.LeakDetector.html#method.track) instantiates a Rust object like [`ComBox::new`](../object/struct.ComBox.html#method.new),
+aggregated by an outer object whose `AddRef` and `Release` are instrumented.
+Every reference taken or released on any of the object's interfaces is recorded with its call site,
+the caller of the com pointer method doing so: `clone` of `IUnknownPtr` and `ComRc`,
+the queries of [`ComPtr`](../com/trait.ComPtr.html), `IUnknownPtr` and `ComRc`, and `ComRef::upgrade`.
+The com pointers of [`#[com_interface]`](../com/attr.com_interface.html) derive `Clone`, their clones are attributed to the `#[derive(Clone)]`.
+
+The balance per call site is about acquisitions: dropping a com pointer has no call site,
+only the references released within those methods are, eg. by [`ComPtr::supports`](../com/trait.ComPtr.html#method.supports).
+Neither have the reference counting calls made through the raw virtual functions.
+
+Dropping the detector asserts every reference has been released and reports the balance per object and per call site otherwise.
+
+This verifies the ownership of com pointers without a COM runtime.
+
+# Limitations
+
+The tracked objects are aggregated by the instrumented outer object:
+
+* Releasing more references than were taken is reported instead of destroying the object twice,
+  the objects are only destroyed when the detector is dropped even if all their references were released.
+* Weak references cannot be taken to them, [`ComBox::downgrade`](../object/struct.ComBox.html#method.downgrade) panics
+  and [`weak::get_weak_reference`](../weak/fn.get_weak_reference.html) fails with `E_FAIL`.
+
+# Examples
+
+```
+# #[macro_use] extern crate com_sys; #[macro_use] extern crate com_types; fn main() {
+use ::com_sys::unknown::{IUnknown, IUnknownVtbl};
+use ::com_types::com::{com_interface, ComPtr};
+use ::com_types::leak::LeakDetector;
+use ::com_types::unknown::IUnknownPtr;
+
+#[com_interface("9f4a5b6c-7d8e-4f90-a1b2-c3d4e5f6a7b8")]
+pub trait ISink: IUnknown {}
+
+struct Sink;
+com_object! {
+	impl Sink {
+		ISink(ISinkVtbl {
+			base: IUnknownVtbl,
+		}),
+	}
+}
+
+let detector = LeakDetector::new();
+let sink: ISinkPtr = detector.track(Sink);
+let unknown = sink.try_cast::<IUnknownPtr>().unwrap();
+assert_eq!(detector.balance()[0].refs, 2);
+drop((sink, unknown));
+// Panics if any reference is still held.
+drop(detector);
+# }
+```
+*/
+
+use ::std::{any, fmt, ptr, thread};
+use ::std::cell::Cell;
+use ::std::mem::ManuallyDrop;
+use ::std::panic::Location;
+use ::std::sync::{Arc, Mutex, MutexGuard};
+
+use ::com_sys::ComInterface;
+use ::com_sys::unknown::{IUnknown, IUnknownVtbl};
+use ::winapi::{HRESULT, LPVOID, GUID};
+
+use ::hr::code;
+use ::com::ComPtr;
+use ::object::{ComBox, ComObjectInterface};
+use ::unknown::IUnknownPtr;
+
+/// Tracks the references to Rust objects.
+///
+/// Asserts every reference has been released when dropped, see the [module](index.html) documentation.
+pub struct LeakDetector {
+	state: Arc<Mutex<State>>,
+}
+impl LeakDetector {
+	/// Creates a detector tracking no objects.
+	pub fn new() -> LeakDetector {
+		LeakDetector { state: Arc::new(Mutex::new(State { objects: Vec::new() })) }
+	}
+	/// Moves the value into a new tracked COM object and returns a com pointer to one of its interfaces.
+	///
+	/// The object's identity is the instrumented outer object, its interfaces are implemented by the value.
+	#[track_caller]
+	pub fn track<T, P: ComPtr>(&self, value: T) -> P where T: ComObjectInterface<P::Interface> {
+		let created = Location::caller();
+		let _site = CallSite::enter(created);
+		let outer = Box::into_raw(Box::new(Outer {
+			vtbl: &OUTER_VTBL,
+			state: self.state.clone(),
+			index: 0,
+			inner: None,
+		}));
+		unsafe {
+			let mut state = lock(&self.state);
+			(*outer).index = state.objects.len();
+			state.objects.push(Object {
+				name: any::type_name::<T>(),
+				created,
+				refs: 0,
+				events: Vec::new(),
+				outer: outer as usize,
+			});
+			drop(state);
+
+			let unknown = ManuallyDrop::new(IUnknownPtr::from_ptr(outer as *mut IUnknown));
+			(*outer).inner = Some(ComBox::aggregate(value, &unknown));
+			// Takes the reference handed out to the caller.
+			unknown.try_cast::<P>().expect("tracked object does not implement the interface")
+		}
+	}
+	/// Returns the reference balance of every tracked object.
+	pub fn balance(&self) -> Vec<Balance> {
+		lock(&self.state).objects.iter().map(Object::balance).collect()
+	}
+	/// Returns the tracked objects with outstanding or over-released references.
+	pub fn leaks(&self) -> Vec<Balance> {
+		lock(&self.state).objects.iter().filter(|object| object.refs != 0).map(Object::balance).collect()
+	}
+	/// Panics with a report if any tracked object has outstanding or over-released references.
+	#[track_caller]
+	pub fn assert_released(&self) {
+		let leaks = self.leaks();
+		if !leaks.is_empty() {
+			let mut report = String::from("unbalanced references:\n");
+			for leak in &leaks {
+				report.push_str(&leak.to_string());
+			}
+			panic!("{}", report);
+		}
+	}
+}
+impl Default for LeakDetector {
+	fn default() -> LeakDetector {
+		LeakDetector::new()
+	}
+}
+impl Drop for LeakDetector {
+	fn drop(&mut self) {
+		// Leaked objects are never destroyed, their com pointers may still be around.
+		let balanced = lock(&self.state).objects.iter().all(|object| object.refs == 0);
+		if thread::panicking() || !balanced {
+			if !thread::panicking() {
+				self.assert_released();
+			}
+			return;
+		}
+		let outers: Vec<usize> = lock(&self.state).objects.iter().map(|object| object.outer).collect();
+		for outer in outers {
+			unsafe {
+				let outer = Box::from_raw(outer as *mut Outer);
+				drop(outer);
+			}
+		}
+	}
+}
+impl fmt::Debug for LeakDetector {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_list().entries(self.balance()).finish()
+	}
+}
+
+/// Reference balance of a tracked object.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Balance {
+	/// Type name of the object.
+	pub name: &'static str,
+	/// Where the object was created.
+	pub created: &'static Location<'static>,
+	/// Outstanding references, negative if over-released.
+	pub refs: i64,
+	/// Balance per call site, in order of first use.
+	pub sites: Vec<SiteBalance>,
+}
+impl fmt::Display for Balance {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let state = if self.refs < 0 { "over-released" } else { "outstanding" };
+		writeln!(f, "{} created at {}: {} {}", self.name, self.created, self.refs.abs(), state)?;
+		for site in &self.sites {
+			let balance = site.add_refs as i64 - site.releases as i64;
+			match site.site {
+				Some(location) => writeln!(f, "\t{:+} ({} AddRef, {} Release) at {}", balance, site.add_refs, site.releases, location)?,
+				None => writeln!(f, "\t{:+} ({} AddRef, {} Release) without call site", balance, site.add_refs, site.releases)?,
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Reference balance of a tracked object at a call site.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SiteBalance {
+	/// The caller of the com pointer method, `None` when dropping com pointers and calling the virtual functions.
+	pub site: Option<&'static Location<'static>>,
+	pub add_refs: u32,
+	pub releases: u32,
+}
+
+//----------------------------------------------------------------
+
+struct State {
+	objects: Vec<Object>,
+}
+
+struct Object {
+	name: &'static str,
+	created: &'static Location<'static>,
+	refs: i64,
+	/// Whether it was an `AddRef` and its call site.
+	events: Vec<(bool, Option<&'static Location<'static>>)>,
+	outer: usize,
+}
+impl Object {
+	fn balance(&self) -> Balance {
+		let mut sites: Vec<SiteBalance> = Vec::new();
+		for &(add_ref, site) in &self.events {
+			let index = match sites.iter().position(|balance| balance.site == site) {
+				Some(index) => index,
+				None => {
+					sites.push(SiteBalance { site, add_refs: 0, releases: 0 });
+					sites.len() - 1
+				},
+			};
+			if add_ref {
+				sites[index].add_refs += 1;
+			}
+			else {
+				sites[index].releases += 1;
+			}
+		}
+		Balance { name: self.name, created: self.created, refs: self.refs, sites }
+	}
+}
+
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+	// A failed assertion while locked does not invalidate the counts.
+	state.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+thread_local! {
+	/// Call site of the outermost com pointer method running on this thread.
+	static CALLER: Cell<Option<&'static Location<'static>>> = const { Cell::new(None) };
+}
+
+/// Attributes the references taken and released until dropped to the caller of a com pointer method.
+///
+/// Entered by the `#[track_caller]` com pointer methods, the outermost one is the call site.
+#[doc(hidden)]
+pub struct CallSite(bool);
+impl CallSite {
+	#[inline]
+	pub fn enter(location: &'static Location<'static>) -> CallSite {
+		let entered = CALLER.try_with(|caller| {
+			if caller.get().is_some() {
+				return false;
+			}
+			caller.set(Some(location));
+			true
+		});
+		CallSite(entered.unwrap_or(false))
+	}
+}
+impl Drop for CallSite {
+	#[inline]
+	fn drop(&mut self) {
+		if self.0 {
+			let _ = CALLER.try_with(|caller| caller.set(None));
+		}
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Instrumented controlling unknown of a tracked object.
+///
+/// Starts with its vtbl pointer to pass as an interface.
+#[repr(C)]
+struct Outer {
+	vtbl: &'static IUnknownVtbl<IUnknown>,
+	state: Arc<Mutex<State>>,
+	index: usize,
+	/// Non-delegating unknown of the aggregated object.
+	inner: Option<IUnknownPtr>,
+}
+impl Outer {
+	unsafe fn count(this: *mut IUnknown, add_ref: bool) -> u32 {
+		let outer = this as *mut Outer;
+		let site = CALLER.try_with(Cell::get).unwrap_or(None);
+		let mut state = lock(&(*outer).state);
+		let object = &mut state.objects[(*outer).index];
+		object.refs += if add_ref { 1 } else { -1 };
+		object.events.push((add_ref, site));
+		object.refs.max(0) as u32
+	}
+}
+
+static OUTER_VTBL: IUnknownVtbl<IUnknown> = IUnknownVtbl {
+	QueryInterface: outer_query_interface,
+	AddRef: outer_add_ref,
+	Release: outer_release,
+};
+
+unsafe extern "system" fn outer_query_interface(this: *mut IUnknown, riid: *const GUID, ppv: *mut LPVOID) -> HRESULT {
+	if riid.is_null() || ppv.is_null() {
+		return code::E_POINTER.into();
+	}
+	if IUnknown::matches_iid(&*riid) {
+		Outer::count(this, true);
+		*ppv = this as LPVOID;
+		return code::S_OK.into();
+	}
+	match (*(this as *mut Outer)).inner {
+		Some(ref inner) => com_call!(QueryInterface(inner, riid, ppv)),
+		None => {
+			*ppv = ptr::null_mut();
+			code::E_NOINTERFACE.into()
+		},
+	}
+}
+unsafe extern "system" fn outer_add_ref(this: *mut IUnknown) -> u32 {
+	Outer::count(this, true)
+}
+unsafe extern "system" fn outer_release(this: *mut IUnknown) -> u32 {
+	// The object is kept alive until the detector is dropped.
+	Outer::count(this, false)
+}
+
+#[cfg(test)]
+mod tests {
+	use ::std::mem;
+	use ::std::cell::Cell;
+	use ::std::panic::{self, AssertUnwindSafe};
+	use ::std::rc::Rc;
+	use ::com_sys::HRESULT;
+	use ::com::ComResult;
+	use super::*;
+
+	#[::com::com_interface("9f4a5b6c-7d8e-4f90-a1b2-c3d4e5f6a7c0")]
+	#[derive(Clone)]
+	pub trait ICounter: IUnknown {
+		fn Increment(&self, #[retval] pValue: *mut i32) -> HRESULT;
+	}
+	#[::com::com_interface("9f4a5b6c-7d8e-4f90-a1b2-c3d4e5f6a7c1")]
+	pub trait IOther: IUnknown {}
+
+	struct Counter {
+		count: Cell<i32>,
+		alive: Rc<Cell<bool>>,
+	}
+	impl Drop for Counter {
+		fn drop(&mut self) {
+			self.alive.set(false);
+		}
+	}
+	unsafe extern "system" fn counter_increment(this: *mut ICounter, value: *mut i32) -> HRESULT {
+		let counter = ComBox::<Counter>::from_interface(this);
+		counter.count.set(counter.count.get() + 1);
+		*value = counter.count.get();
+		code::S_OK.into()
+	}
+	com_object! {
+		impl Counter {
+			ICounter(ICounterVtbl {
+				base: IUnknownVtbl,
+				Increment: counter_increment,
+			}),
+			IOther(IOtherVtbl {
+				base: IUnknownVtbl,
+			}),
+		}
+	}
+
+	fn new_counter(alive: &Rc<Cell<bool>>) -> Counter {
+		Counter { count: Cell::new(0), alive: alive.clone() }
+	}
+	fn leak(counter: &ICounterPtr) -> ComResult<i32> {
+		mem::forget(counter.clone());
+		counter.increment()
+	}
+
+	#[test]
+	fn balanced() {
+		let alive = Rc::new(Cell::new(true));
+		let detector = LeakDetector::new();
+		let counter: ICounterPtr = detector.track(new_counter(&alive));
+		assert_eq!(counter.increment(), Ok(1));
+		let other = counter.try_cast::<IOtherPtr>().unwrap();
+		let unknown = other.identity().unwrap();
+		assert_eq!(detector.balance()[0].refs, 3);
+		assert!(counter.is_same_object(&unknown));
+		drop((counter, other, unknown));
+		assert!(detector.leaks().is_empty());
+		// Destroyed with the detector.
+		assert!(alive.get());
+		drop(detector);
+		assert!(!alive.get());
+	}
+
+	#[test]
+	fn leaked() {
+		let alive = Rc::new(Cell::new(true));
+		let detector = LeakDetector::new();
+		let counter: ICounterPtr = detector.track(new_counter(&alive));
+		let other: IOtherPtr = detector.track(new_counter(&alive));
+		assert_eq!(leak(&counter), Ok(1));
+		drop((counter, other));
+
+		let leaks = detector.leaks();
+		assert_eq!(leaks.len(), 1);
+		assert_eq!(leaks[0].refs, 1);
+		assert!(leaks[0].name.ends_with("Counter"));
+		assert_eq!(leaks[0].created.file(), file!());
+		// Taken by `track`, released by `drop` and leaked by `leak`, cloned through the `#[derive(Clone)]` of `ICounter`.
+		let sites: Vec<_> = leaks[0].sites.iter().map(|site| (site.add_refs, site.releases)).collect();
+		assert_eq!(sites, [(1, 0), (1, 0), (0, 1)]);
+		assert_eq!(leaks[0].sites[0].site, Some(leaks[0].created));
+		let cloned = leaks[0].sites[1].site.unwrap();
+		assert_eq!(cloned.file(), file!());
+		assert!(cloned.line() < leaks[0].created.line(), "{}", leaks[0]);
+		assert_eq!(leaks[0].sites[2].site, None);
+
+		let err = panic::catch_unwind(AssertUnwindSafe(|| drop(detector))).unwrap_err();
+		let report = err.downcast_ref::<String>().unwrap();
+		assert!(report.contains("1 outstanding"), "{}", report);
+		// Leaked objects are not destroyed.
+		assert!(alive.get());
+	}
+
+	#[test]
+	fn over_released() {
+		let alive = Rc::new(Cell::new(true));
+		let detector = LeakDetector::new();
+		let counter: ICounterPtr = detector.track(new_counter(&alive));
+		unsafe { com_call!(Release(&counter)); }
+		drop(counter);
+		assert_eq!(detector.leaks()[0].refs, -1);
+		let err = panic::catch_unwind(AssertUnwindSafe(|| drop(detector))).unwrap_err();
+		assert!(err.downcast_ref::<String>().unwrap().contains("1 over-released"));
+		assert!(alive.get());
+	}
+}
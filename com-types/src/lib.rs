@@ -1,15 +1,25 @@
 /*!
+Idiomatic Rust bindings for COM types.
 */
 
-#![cfg(windows)]
+#![allow(clippy::tabs_in_doc_comments)]
 
+#[cfg(windows)]
 extern crate winapi;
+#[cfg(windows)]
 extern crate oleaut32;
+#[cfg(not(windows))]
+mod winapi;
 
+#[cfg_attr(test, macro_use)]
+extern crate com_sys;
+extern crate com_macros;
 #[macro_use]
 extern crate bitflags;
 
-extern crate com_sys;
+// Lets the code generated by `#[com_interface]` refer to this crate in its tests.
+#[cfg(test)]
+extern crate self as com_types;
 
 //----------------------------------------------------------------
 
@@ -17,7 +27,7 @@ extern crate com_sys;
 pub use winapi::{LPVOID, GUID};
 
 #[doc(hidden)]
-pub use com_sys::{ComVtbl, ComInterface, ComClass, ComClassInterface};
+pub use com_sys::{ComVtbl, ComInterface, ComInherit, ComExtends, ComClass, ComClassInterface, Guid};
 
 //----------------------------------------------------------------
 
@@ -25,6 +35,9 @@ pub trait AsInner<T: ?Sized> {
 	fn as_inner(&self) -> &T;
 }
 pub trait AsInnerMut<T: ?Sized>: AsInner<T> {
+	/// # Safety
+	///
+	/// Mutating the inner value must not break the invariants of the wrapper.
 	unsafe fn as_inner_mut(&mut self) -> &mut T;
 }
 
@@ -32,6 +45,9 @@ pub trait IntoInner<T> {
 	fn into_inner(self) -> T;
 }
 pub trait FromInner<T> {
+	/// # Safety
+	///
+	/// The inner value must uphold the invariants of the wrapper.
 	unsafe fn from_inner(inner: T) -> Self;
 }
 
@@ -64,7 +80,17 @@ pub mod com;
 pub mod currency;
 pub mod date;
 pub mod decimal;
+pub mod dispatch;
 pub mod hr;
+#[cfg(any(test, feature = "leak"))]
+pub mod leak;
+pub mod object;
+pub mod rc;
+pub mod record;
 pub mod timeout;
 pub mod variant;
 pub mod unknown;
+pub mod weak;
+
+#[cfg(test)]
+mod tests;
@@ -21,7 +21,7 @@ These help implementing the idiomatic Rust abstractions for the COM FFI.
   com_interface! {
         interface IInterface(IInterfaceVtbl): IUnknown(IUnknownVtbl);
         {0xAAAAAAAA-0xBBBB-0xCCCC-0xDDDD-0xEEEEEEEEEEEE}
-        pub Foo: unsafe extern "stdcall" fn(
+        pub Foo: unsafe extern "system" fn(
             This: *mut IInterface,
         ),
   }
@@ -41,9 +41,26 @@ These help implementing the idiomatic Rust abstractions for the COM FFI.
 
   The first argument should evaulate to an impl `AsComPtr`.
 
+* [`#[com_interface]`](../com/attr.com_interface.html)
+
+  Defines the COM interface struct, its vtbl, its com pointer, shims calling its virtual functions and their safe `ComResult` wrappers from a trait.
+  Replaces `com_interface!` and `com_ptr!` in one go.
+
+* [`com_object!`](../macro.com_object!.html)
+
+  Implements COM interfaces for a Rust type, see the [`object`](../object/index.html) module.
+
+* [`com_dispatch!`](../macro.com_dispatch!.html)
+
+  Exposes members of a Rust type through `IDispatch`, see the [`dispatch`](../dispatch/index.html) module.
+
 */
 
 /// Defines the idiomatic Rust wrapper for a COM interface.
+///
+/// Given the com pointer of the base interface, implements `Deref` to it and [`ComCast`](com/trait.ComCast.html) to every ancestor's com pointer.
+///
+/// The com pointer is `#[repr(transparent)]`, it has the layout of the raw interface pointer.
 #[macro_export]
 macro_rules! com_ptr {
 	(
@@ -51,6 +68,7 @@ macro_rules! com_ptr {
 		pub struct $iface_ptr:ident($iface:ty);
 	) => {
 		$(#[$attr])*
+		#[repr(transparent)]
 		pub struct $iface_ptr(*mut $iface);
 		impl $crate::com::AsComPtr for $iface_ptr {
 			type Interface = $iface;
@@ -67,12 +85,19 @@ macro_rules! com_ptr {
 				$iface_ptr(iface)
 			}
 		}
+		impl $crate::com::ComCast<$iface_ptr> for $iface_ptr {
+			#[inline(always)] fn cast(&self) -> &$iface_ptr { self }
+		}
+		impl $crate::com::ComInterfacePtr for $iface {
+			type Ptr = $iface_ptr;
+		}
 	};
 	(
 		$(#[$attr:meta])*
 		pub struct $iface_ptr:ident($iface:ty): $ibase_ptr:ty;
 	) => {
 		$(#[$attr])*
+		#[repr(transparent)]
 		pub struct $iface_ptr($ibase_ptr);
 		impl $crate::com::AsComPtr for $iface_ptr {
 			type Interface = $iface;
@@ -90,13 +115,22 @@ macro_rules! com_ptr {
 				Self::new(iface as $crate::LPVOID)
 			}
 		}
-		impl $crate::com::ComCast<$ibase_ptr> for $iface_ptr {
-			#[inline(always)] fn cast(&self) -> &$ibase_ptr { &self.0 }
+		impl $crate::com::ComCast<$iface_ptr> for $iface_ptr {
+			#[inline(always)] fn cast(&self) -> &$iface_ptr { self }
+		}
+		// Bounding on the base interface rather than only its com pointer keeps this apart from the identity cast.
+		impl<T: $crate::com::ComPtr> $crate::com::ComCast<T> for $iface_ptr
+			where $ibase_ptr: $crate::com::ComCast<T>, <$ibase_ptr as $crate::com::AsComPtr>::Interface: $crate::ComExtends<T::Interface>
+		{
+			#[inline(always)] fn cast(&self) -> &T { $crate::com::ComCast::cast(&self.0) }
 		}
 		impl ::std::ops::Deref for $iface_ptr {
 			type Target = $ibase_ptr;
 			fn deref(&self) -> &$ibase_ptr { &self.0 }
 		}
+		impl $crate::com::ComInterfacePtr for $iface {
+			type Ptr = $iface_ptr;
+		}
 	}
 }
 
@@ -109,3 +143,190 @@ macro_rules! com_call {
 		(vtbl.$vfn)(this_ptr $(,$args)*)
 	}};
 }
+
+/// Implements COM interfaces for a Rust type.
+///
+/// Lists the interfaces with their vtbl, spelled like a struct literal of the vtbl.
+/// Base vtbls are nested in the `base` field, the innermost `base` is always `IUnknownVtbl` which is filled in automatically.
+/// The remaining fields are the virtual functions, eg. free `unsafe extern "system" fn`s taking the interface as `This` pointer.
+///
+/// The first interface answers queries for `IUnknown`, any interface answers queries for its base interfaces.
+///
+/// Items in an `unsafe` block following the interfaces are added to the `ComObject` implementation, eg. to override its `query_fallback`.
+/// The block is `unsafe` as the items must uphold the safety contract of [`ComObject`](object/trait.ComObject.html):
+/// `query_fallback` must only return interfaces matching the requested iid.
+///
+/// Implements [`ComObject`](object/trait.ComObject.html) and [`ComObjectInterface`](object/trait.ComObjectInterface.html) for every listed interface.
+/// Use [`ComBox`](object/struct.ComBox.html) to instantiate the object.
+///
+/// # Examples
+///
+/// ```ignore
+/// com_object! {
+/// 	impl Object {
+/// 		IInterface(IInterfaceVtbl {
+/// 			base: IUnknownVtbl,
+/// 			Foo: object_foo,
+/// 		}),
+/// 		IDerived(IDerivedVtbl {
+/// 			base: IBaseVtbl {
+/// 				base: IUnknownVtbl,
+/// 				Base: derived_base,
+/// 			},
+/// 			Derived: derived_derived,
+/// 		}),
+/// 	}
+/// 	unsafe {
+/// 		fn query_fallback(&self, outer: &IUnknownPtr, iid: &GUID) -> ComResult<IUnknownPtr> {
+/// 			self.aggregated.query_iid(iid)
+/// 		}
+/// 	}
+/// }
+/// ```
+///
+/// See the [`object`](object/index.html) module for a complete example.
+#[macro_export]
+macro_rules! com_object {
+	(
+		impl $obj:ty {
+			$($iface:ident($($vtbl:tt)*)),+ $(,)*
+		}
+		$(unsafe {
+			$($item:item)*
+		})*
+	) => {
+		unsafe impl $crate::object::ComObject for $obj {
+			type Vtbls = [*const ::std::os::raw::c_void; 0 $(+ com_object!(@one $iface))+];
+			fn vtbls() -> Self::Vtbls {
+				[$({
+					static VTBL: <$iface as $crate::ComInterface>::Vtbl = com_object!(@vtbl $obj, $iface, $($vtbl)*);
+					&VTBL as *const _ as *const ::std::os::raw::c_void
+				}),+]
+			}
+			fn slot(iid: &$crate::GUID) -> Option<usize> {
+				$(if <$iface as $crate::ComInterface>::matches_iid(iid) {
+					return Some(<$obj as $crate::object::ComObjectInterface<$iface>>::SLOT);
+				})+
+				None
+			}
+			$($($item)*)*
+		}
+		com_object!(@slot $obj, 0, $($iface,)+);
+	};
+	(@one $iface:ident) => { 1 };
+	(@slot $obj:ty, $slot:expr, ) => {};
+	(@slot $obj:ty, $slot:expr, $iface:ident, $($tail:ident,)*) => {
+		unsafe impl $crate::object::ComObjectInterface<$iface> for $obj {
+			const SLOT: usize = $slot;
+		}
+		com_object!(@slot $obj, $slot + 1, $($tail,)*);
+	};
+	(@vtbl $obj:ty, $iface:ident, IUnknownVtbl) => {
+		$crate::object::unknown_vtbl::<$obj, $iface>()
+	};
+	(@vtbl $obj:ty, $iface:ident, $vtbl:ident {
+		base: $base:ident $({ $($base_vtbl:tt)* })*
+		$(, $ident:ident: $vfn:expr)* $(,)*
+	}) => {
+		$vtbl {
+			base: com_object!(@vtbl $obj, $iface, $base $({ $($base_vtbl)* })*),
+			$($ident: $vfn,)*
+		}
+	};
+}
+
+/// Implements [`Dispatch`](dispatch/trait.Dispatch.html) for a Rust type.
+///
+/// Lists the members by their name as seen by automation clients, followed by the Rust function implementing them.
+/// The functions take `&self` followed by the arguments unpacked as the [`variant::Type`](variant/trait.Type.html)'s `Owned` type,
+/// and return a `Result` whose error converts into [`DispatchError`](dispatch/enum.DispatchError.html).
+///
+/// * `fn Name(param: Type, ...) -> Type = path;` is a method, the return type is optional.
+/// * `get Name -> Type = path;` gets a property.
+/// * `put Name(value: Type) = path;` puts a property.
+///
+/// # Examples
+///
+/// ```ignore
+/// com_dispatch! {
+/// 	impl Object {
+/// 		fn Add(lhs: I4, rhs: I4) -> I4 = Object::add;
+/// 		fn Reset() = Object::reset;
+/// 		get Name -> BString = Object::name;
+/// 		put Name(value: BString) = Object::set_name;
+/// 	}
+/// }
+/// ```
+///
+/// See the [`dispatch`](dispatch/index.html) module for a complete example.
+#[macro_export]
+macro_rules! com_dispatch {
+	(
+		impl $obj:ty {
+			$($members:tt)*
+		}
+	) => {
+		impl $crate::dispatch::Dispatch for $obj {
+			const MEMBERS: &'static [$crate::dispatch::Member<$obj>] = com_dispatch!(@members $obj, [] $($members)*);
+		}
+	};
+	(@members $obj:ty, [$($done:expr,)*]) => {
+		&[$($done,)*]
+	};
+	(@members $obj:ty, [$($done:expr,)*] fn $name:ident($($param:ident: $ty:ty),* $(,)*) -> $ret:ty = $f:path; $($tail:tt)*) => {
+		com_dispatch!(@members $obj, [$($done,)* $crate::dispatch::Member {
+			name: stringify!($name),
+			params: &[$(stringify!($param)),*],
+			flags: $crate::dispatch::DISPATCH_METHOD,
+			invoke: |this: &$obj, _args: &$crate::dispatch::Args| -> Result<$crate::variant::Variant<'static>, $crate::dispatch::DispatchError> {
+				let ret: $ret = $f(this $(, _args.arg::<$ty>(stringify!($param))?)*)?;
+				Ok($crate::variant::Variant::from(ret))
+			},
+		},] $($tail)*)
+	};
+	(@members $obj:ty, [$($done:expr,)*] fn $name:ident($($param:ident: $ty:ty),* $(,)*) = $f:path; $($tail:tt)*) => {
+		com_dispatch!(@members $obj, [$($done,)* $crate::dispatch::Member {
+			name: stringify!($name),
+			params: &[$(stringify!($param)),*],
+			flags: $crate::dispatch::DISPATCH_METHOD,
+			invoke: |this: &$obj, _args: &$crate::dispatch::Args| -> Result<$crate::variant::Variant<'static>, $crate::dispatch::DispatchError> {
+				$f(this $(, _args.arg::<$ty>(stringify!($param))?)*)?;
+				Ok($crate::variant::Variant::new())
+			},
+		},] $($tail)*)
+	};
+	(@members $obj:ty, [$($done:expr,)*] get $name:ident -> $ret:ty = $f:path; $($tail:tt)*) => {
+		com_dispatch!(@members $obj, [$($done,)* $crate::dispatch::Member {
+			name: stringify!($name),
+			params: &[],
+			flags: $crate::dispatch::DISPATCH_PROPERTYGET,
+			invoke: |this: &$obj, _: &$crate::dispatch::Args| -> Result<$crate::variant::Variant<'static>, $crate::dispatch::DispatchError> {
+				let ret: $ret = $f(this)?;
+				Ok($crate::variant::Variant::from(ret))
+			},
+		},] $($tail)*)
+	};
+	(@members $obj:ty, [$($done:expr,)*] put $name:ident($value:ident: $ty:ty) = $f:path; $($tail:tt)*) => {
+		com_dispatch!(@members $obj, [$($done,)* $crate::dispatch::Member {
+			name: stringify!($name),
+			params: &[],
+			flags: $crate::dispatch::DISPATCH_PROPERTYPUT,
+			invoke: |this: &$obj, args: &$crate::dispatch::Args| -> Result<$crate::variant::Variant<'static>, $crate::dispatch::DispatchError> {
+				$f(this, args.value::<$ty>()?)?;
+				Ok($crate::variant::Variant::new())
+			},
+		},] $($tail)*)
+	};
+}
+
+/// Attributes the references taken until the end of the scope to the caller of the `#[track_caller]` com pointer method, see the `leak` module.
+#[cfg(any(test, feature = "leak"))]
+macro_rules! call_site {
+	() => {
+		let _site = ::leak::CallSite::enter(::std::panic::Location::caller());
+	};
+}
+#[cfg(not(any(test, feature = "leak")))]
+macro_rules! call_site {
+	() => {};
+}
@@ -0,0 +1,872 @@
+/*!
+Implement COM objects in Rust.
+
+A Rust type becomes a COM object by listing the interfaces it implements with [`com_object!`](../macro.com_object!.html).
+The macro builds the static vtbls and wires up the `IUnknown` plumbing, leaving the interface methods to be provided as free functions.
+
+[`ComBox`](struct.ComBox.html) moves the value to the heap next to its vtbl pointers and an atomic reference count,
+and hands out com pointers to any of its interfaces.
+
+Objects can be composed through aggregation and tear-offs, see [`ComBox::aggregate`](struct.ComBox.html#method.aggregate) and [`ComBox::tear_off`](struct.ComBox.html#method.tear_off).
+Both are hooked up by overriding [`ComObject::query_fallback`](trait.ComObject.html#method.query_fallback) in the `unsafe` block of `com_object!`.
+
+[`ComWeak`](struct.ComWeak.html) references an object without keeping it alive, eg. to break cycles between event sinks and their sources.
+
+# Examples
+
+```
+# #[macro_use] extern crate com_sys; #[macro_use] extern crate com_types; fn main() {
+use ::std::cell::Cell;
+use ::com_sys::HRESULT;
+use ::com_sys::unknown::{IUnknown, IUnknownVtbl};
+use ::com_types::com::ComPtr;
+use ::com_types::hr::{code, HResult};
+use ::com_types::object::ComBox;
+use ::com_types::unknown::IUnknownPtr;
+
+com_interface! {
+	interface ICounter(ICounterVtbl): IUnknown(IUnknownVtbl);
+	{0x9b6c2c5e-0x4c0b-0x4b8e-0x9d5f-0x2b1c3a4d5e6f}
+	pub Increment: unsafe extern "system" fn(
+		This: *mut ICounter,
+		pValue: *mut i32,
+	) -> HRESULT,
+}
+
+com_ptr! {
+	pub struct ICounterPtr(ICounter): IUnknownPtr;
+}
+
+// The Rust type implementing the interface.
+struct Counter {
+	count: Cell<i32>,
+}
+
+// The virtual functions receive the interface pointer of the slot they were called through.
+unsafe extern "system" fn counter_increment(this: *mut ICounter, value: *mut i32) -> HRESULT {
+	let counter = ComBox::<Counter>::from_interface(this);
+	counter.count.set(counter.count.get() + 1);
+	*value = counter.count.get();
+	code::S_OK.into()
+}
+
+com_object! {
+	impl Counter {
+		ICounter(ICounterVtbl {
+			base: IUnknownVtbl,
+			Increment: counter_increment,
+		}),
+	}
+}
+
+let unknown: IUnknownPtr = ComBox::new(Counter { count: Cell::new(0) });
+let counter = unknown.try_cast::<ICounterPtr>().unwrap();
+let mut value = 0;
+let hr = unsafe { com_call!(Increment(&counter, &mut value)) };
+assert!(HResult::from(hr).succeeded());
+assert_eq!(value, 1);
+# }
+```
+*/
+
+use ::std::{fmt, mem, ptr};
+use ::std::marker::PhantomData;
+use ::std::mem::ManuallyDrop;
+use ::std::os::raw::c_void;
+use ::std::sync::atomic::{self, AtomicU32, Ordering};
+
+use ::winapi::{HRESULT, LPVOID, GUID};
+use ::com_sys::ComInterface;
+use ::com_sys::unknown::{IUnknown, IUnknownVtbl};
+
+use ::hr::code;
+use ::com::{AsComPtr, ComPtr, ComResult};
+use ::unknown::IUnknownPtr;
+
+/// Rust types exposed as COM objects.
+///
+/// Implemented by [`com_object!`](../macro.com_object!.html).
+///
+/// # Safety
+///
+/// `Vtbls` must be an array of `*const c_void` with a pointer to the vtbl of every implemented interface,
+/// and `slot` must only return indices into this array of interfaces which are compatible with the requested iid.
+/// Likewise `query_fallback` must only return interfaces matching the requested iid.
+pub unsafe trait ComObject: Sized {
+	/// Vtbl pointers of the implemented interfaces, in slot order.
+	type Vtbls: Copy;
+	/// Returns the vtbl pointers of the implemented interfaces.
+	fn vtbls() -> Self::Vtbls;
+	/// Returns the slot of the interface which answers queries for `iid`.
+	fn slot(iid: &GUID) -> Option<usize>;
+	/// Answers queries for iids not implemented by the object itself.
+	///
+	/// Override to forward queries to [aggregated](struct.ComBox.html#method.aggregate) objects or to lazily create [tear-offs](struct.ComBox.html#method.tear_off).
+	/// `outer` is the controlling unknown of the object.
+	///
+	/// The returned pointer is the interface for `iid` seen through its `IUnknown` base.
+	#[allow(unused_variables)]
+	fn query_fallback(&self, outer: &IUnknownPtr, iid: &GUID) -> ComResult<IUnknownPtr> {
+		Err(code::E_NOINTERFACE)
+	}
+}
+
+/// Declares the `ComInterface`s implemented by a `ComObject` and their slot.
+///
+/// Implemented by [`com_object!`](../macro.com_object!.html).
+/// Every object implements `IUnknown` through its first interface.
+///
+/// # Safety
+///
+/// The vtbl at `SLOT` must be the vtbl of `I`.
+pub unsafe trait ComObjectInterface<I: ComInterface>: ComObject {
+	/// Index of the interface's vtbl pointer.
+	const SLOT: usize;
+}
+unsafe impl<T: ComObject> ComObjectInterface<IUnknown> for T {
+	const SLOT: usize = 0;
+}
+
+//----------------------------------------------------------------
+
+/// Heap allocated COM object.
+///
+/// Interface pointers point at one of the vtbl pointers in front of the value.
+/// The object is destroyed when its last reference is released.
+///
+/// Besides standalone objects, a `ComBox` can be [aggregated](#method.aggregate) by an outer object or be a [tear-off](#method.tear_off) of an owner object.
+///
+/// Like `Arc` the value is dropped with the last reference while the allocation lives on until the last [weak reference](struct.ComWeak.html) is gone.
+#[repr(C)]
+pub struct ComBox<T: ComObject> {
+	vtbls: T::Vtbls,
+	inner: &'static IUnknownVtbl<IUnknown>,
+	refs: AtomicU32,
+	/// Weak references, plus one held by all the references together.
+	weak: AtomicU32,
+	value: ManuallyDrop<T>,
+	outer: ManuallyDrop<Outer>,
+}
+
+/// Who is in charge of the object's identity.
+enum Outer {
+	/// The object is its own identity.
+	Standalone,
+	/// Aggregated, delegates everything to the controlling unknown.
+	/// Not reference counted, the controlling unknown owns the inner unknown.
+	Aggregate(*mut IUnknown),
+	/// Tear-off, keeps its owner alive and delegates queries for other interfaces to it.
+	TearOff(IUnknownPtr),
+}
+
+impl<T: ComObject> ComBox<T> {
+	/// Moves the value into a new COM object and returns a com pointer to one of its interfaces.
+	#[allow(clippy::new_ret_no_self)]
+	pub fn new<P: ComPtr>(value: T) -> P where T: ComObjectInterface<P::Interface> {
+		let this = Self::alloc(value, Outer::Standalone);
+		unsafe { P::new(Self::interface(this, <T as ComObjectInterface<P::Interface>>::SLOT)) }
+	}
+	/// Moves the value into a new COM object aggregated by `outer` and returns its non-delegating inner unknown.
+	///
+	/// The interfaces of the object delegate `QueryInterface`, `AddRef` and `Release` to the controlling unknown `outer`, making them part of its identity.
+	/// The outer object holds on to the inner unknown, which controls the lifetime of the aggregated object,
+	/// and forwards queries for the interfaces it does not implement itself, see [`ComObject::query_fallback`](trait.ComObject.html#method.query_fallback).
+	///
+	/// # Safety
+	///
+	/// The controlling unknown is not reference counted to avoid a cycle.
+	/// It must outlive the inner unknown, which is normally the case when the outer object owns it.
+	pub unsafe fn aggregate(value: T, outer: &IUnknownPtr) -> IUnknownPtr {
+		let this = Self::alloc(value, Outer::Aggregate(outer.as_ptr()));
+		IUnknownPtr::new(ptr::addr_of_mut!((*this).inner) as LPVOID)
+	}
+	/// Moves the value into a new tear-off of `owner` and returns a com pointer to one of its interfaces.
+	///
+	/// Tear-offs implement rarely used interfaces of an object in a separate allocation, created lazily when queried for,
+	/// see [`ComObject::query_fallback`](trait.ComObject.html#method.query_fallback).
+	/// They have their own reference count and keep the owner alive.
+	/// Queries for `IUnknown` and any interface not implemented by the tear-off are answered by the owner.
+	///
+	/// `owner` should be the controlling unknown of the owner object, the tear-off shares its identity.
+	pub fn tear_off<P: ComPtr>(value: T, owner: &IUnknownPtr) -> P where T: ComObjectInterface<P::Interface> {
+		let this = Self::alloc(value, Outer::TearOff(owner.clone()));
+		unsafe { P::new(Self::interface(this, <T as ComObjectInterface<P::Interface>>::SLOT)) }
+	}
+	/// Returns the object behind an interface pointer.
+	///
+	/// # Safety
+	///
+	/// The interface pointer must have been handed out by a `ComBox<T>`, eg. the `This` pointer passed to its virtual functions.
+	/// The returned lifetime is unbounded.
+	pub unsafe fn from_interface<'ub, I: ComInterface>(this: *mut I) -> &'ub T where T: ComObjectInterface<I> {
+		&(*Self::from_slot(this as LPVOID, <T as ComObjectInterface<I>>::SLOT)).value
+	}
+	/// Creates a weak reference to the object of the com pointer.
+	///
+	/// # Safety
+	///
+	/// The com pointer must have been handed out by a `ComBox<T>`, see [`from_interface`](#method.from_interface).
+	///
+	/// # Panics
+	///
+	/// Aggregated objects are kept alive by their controlling unknown, take weak references to the outer object instead.
+	pub unsafe fn downgrade<P: ComPtr>(ptr: &P) -> ComWeak<P> where T: ComObjectInterface<P::Interface> {
+		match Self::try_downgrade(ptr) {
+			Some(weak) => weak,
+			None => panic!("weak reference to an aggregated object"),
+		}
+	}
+	/// Creates a weak reference to the object of the com pointer, returns `None` if the object is aggregated.
+	///
+	/// # Safety
+	///
+	/// See [`downgrade`](#method.downgrade).
+	pub unsafe fn try_downgrade<P: ComPtr>(ptr: &P) -> Option<ComWeak<P>> where T: ComObjectInterface<P::Interface> {
+		let this = Self::from_slot(ptr.as_ptr() as LPVOID, <T as ComObjectInterface<P::Interface>>::SLOT);
+		if let Outer::Aggregate(_) = *(*this).outer {
+			return None;
+		}
+		(*this).weak.fetch_add(1, Ordering::Relaxed);
+		Some(ComWeak {
+			iface: ptr.as_ptr(),
+			obj: this as *mut c_void,
+			vtbl: &WeakVtbls::<T>::VTBL,
+			_marker: PhantomData,
+		})
+	}
+	/// Returns the current reference count.
+	///
+	/// Only useful for debugging, other threads may change it at any time.
+	/// Aggregated objects count the references to their inner unknown, their interfaces are counted by the controlling unknown.
+	///
+	/// # Safety
+	///
+	/// See [`from_interface`](#method.from_interface).
+	pub unsafe fn ref_count<I: ComInterface>(this: *mut I) -> u32 where T: ComObjectInterface<I> {
+		(*Self::from_slot(this as LPVOID, <T as ComObjectInterface<I>>::SLOT)).refs.load(Ordering::Relaxed)
+	}
+	fn alloc(value: T, outer: Outer) -> *mut ComBox<T> {
+		Box::into_raw(Box::new(ComBox {
+			vtbls: T::vtbls(),
+			inner: &InnerVtbl::<T>::VTBL,
+			refs: AtomicU32::new(1),
+			weak: AtomicU32::new(1),
+			value: ManuallyDrop::new(value),
+			outer: ManuallyDrop::new(outer),
+		}))
+	}
+	unsafe fn from_slot(this: LPVOID, slot: usize) -> *mut ComBox<T> {
+		(this as *mut *const c_void).sub(slot) as *mut ComBox<T>
+	}
+	unsafe fn from_inner(this: *mut IUnknown) -> *mut ComBox<T> {
+		Self::from_slot(this as LPVOID, Self::slots())
+	}
+	unsafe fn interface(this: *mut ComBox<T>, slot: usize) -> LPVOID {
+		debug_assert!(slot < Self::slots());
+		(this as *mut *const c_void).add(slot) as LPVOID
+	}
+	fn slots() -> usize {
+		mem::size_of::<T::Vtbls>() / mem::size_of::<*const c_void>()
+	}
+	/// The controlling unknown, `IUnknown` identity of the object.
+	unsafe fn controlling(this: *mut ComBox<T>) -> *mut IUnknown {
+		match *(*this).outer {
+			Outer::Standalone => Self::interface(this, 0) as *mut IUnknown,
+			Outer::Aggregate(outer) => outer,
+			Outer::TearOff(ref owner) => owner.as_ptr(),
+		}
+	}
+	/// Answers a query with the object's own interfaces or its fallback.
+	unsafe fn query(this: *mut ComBox<T>, iid: &GUID, ppv: *mut LPVOID) -> HRESULT {
+		let result = match T::slot(iid) {
+			Some(slot) => {
+				Self::add_ref(this);
+				Ok(Self::interface(this, slot))
+			},
+			None => {
+				let outer = borrow_unknown(Self::controlling(this));
+				(*this).value.query_fallback(&outer, iid).map(|iface| {
+					let iface = ManuallyDrop::new(iface);
+					iface.as_ptr() as LPVOID
+				})
+			},
+		};
+		match result {
+			Ok(iface) => {
+				*ppv = iface;
+				code::S_OK.into()
+			},
+			Err(hr) => {
+				*ppv = ptr::null_mut();
+				hr.into()
+			},
+		}
+	}
+	/// Adds a reference through one of the object's interfaces.
+	unsafe fn add_ref(this: *mut ComBox<T>) -> u32 {
+		match *(*this).outer {
+			Outer::Aggregate(outer) => com_call!(AddRef(&*borrow_unknown(outer))),
+			_ => (*this).refs.fetch_add(1, Ordering::Relaxed) + 1,
+		}
+	}
+	/// Releases a reference through one of the object's interfaces.
+	unsafe fn release(this: *mut ComBox<T>) -> u32 {
+		match *(*this).outer {
+			Outer::Aggregate(outer) => com_call!(Release(&*borrow_unknown(outer))),
+			_ => Self::release_inner(this),
+		}
+	}
+	/// Releases a reference to the object itself.
+	unsafe fn release_inner(this: *mut ComBox<T>) -> u32 {
+		let refs = (*this).refs.fetch_sub(1, Ordering::Release) - 1;
+		if refs == 0 {
+			atomic::fence(Ordering::Acquire);
+			// Tear-offs release their owner now, not when the last weak reference is gone.
+			ManuallyDrop::drop(&mut (*this).value);
+			ManuallyDrop::drop(&mut (*this).outer);
+			Self::release_weak(this);
+		}
+		refs
+	}
+	/// Releases a weak reference, freeing the object after the last one.
+	unsafe fn release_weak(this: *mut ComBox<T>) {
+		if (*this).weak.fetch_sub(1, Ordering::Release) == 1 {
+			atomic::fence(Ordering::Acquire);
+			drop(Box::from_raw(this));
+		}
+	}
+	/// Adds a reference unless the object is already destroyed.
+	unsafe fn upgrade(this: *mut ComBox<T>) -> bool {
+		let mut refs = (*this).refs.load(Ordering::Relaxed);
+		while refs != 0 {
+			match (*this).refs.compare_exchange_weak(refs, refs + 1, Ordering::Acquire, Ordering::Relaxed) {
+				Ok(_) => return true,
+				Err(current) => refs = current,
+			}
+		}
+		false
+	}
+}
+
+/// Views a raw `IUnknown` pointer as a com pointer without taking a reference.
+unsafe fn borrow_unknown(unknown: *mut IUnknown) -> ManuallyDrop<IUnknownPtr> {
+	ManuallyDrop::new(IUnknownPtr::from_ptr(unknown))
+}
+
+//----------------------------------------------------------------
+// IUnknown implementation
+
+/// Returns the `IUnknown` part of the vtbl for interface `I` of `T`.
+///
+/// Used by [`com_object!`](../macro.com_object!.html) to fill in the root of every vtbl.
+pub const fn unknown_vtbl<T: ComObjectInterface<I>, I: ComInterface>() -> IUnknownVtbl<I> {
+	IUnknownVtbl {
+		QueryInterface: query_interface::<T, I>,
+		AddRef: add_ref::<T, I>,
+		Release: release::<T, I>,
+	}
+}
+
+unsafe extern "system" fn query_interface<T: ComObjectInterface<I>, I: ComInterface>(this: *mut I, riid: *const GUID, ppv: *mut LPVOID) -> HRESULT {
+	if riid.is_null() || ppv.is_null() {
+		return code::E_POINTER.into();
+	}
+	let obj = ComBox::<T>::from_slot(this as LPVOID, <T as ComObjectInterface<I>>::SLOT);
+	match *(*obj).outer {
+		Outer::Aggregate(outer) => com_call!(QueryInterface(&*borrow_unknown(outer), riid, ppv)),
+		Outer::TearOff(ref owner) if IUnknown::matches_iid(&*riid) || T::slot(&*riid).is_none() => {
+			com_call!(QueryInterface(owner, riid, ppv))
+		},
+		_ => ComBox::query(obj, &*riid, ppv),
+	}
+}
+unsafe extern "system" fn add_ref<T: ComObjectInterface<I>, I: ComInterface>(this: *mut I) -> u32 {
+	ComBox::<T>::add_ref(ComBox::<T>::from_slot(this as LPVOID, <T as ComObjectInterface<I>>::SLOT))
+}
+unsafe extern "system" fn release<T: ComObjectInterface<I>, I: ComInterface>(this: *mut I) -> u32 {
+	ComBox::<T>::release(ComBox::<T>::from_slot(this as LPVOID, <T as ComObjectInterface<I>>::SLOT))
+}
+
+/// Non-delegating `IUnknown` of aggregated objects.
+struct InnerVtbl<T>(PhantomData<T>);
+impl<T: ComObject> InnerVtbl<T> {
+	const VTBL: IUnknownVtbl<IUnknown> = IUnknownVtbl {
+		QueryInterface: inner_query_interface::<T>,
+		AddRef: inner_add_ref::<T>,
+		Release: inner_release::<T>,
+	};
+}
+
+unsafe extern "system" fn inner_query_interface<T: ComObject>(this: *mut IUnknown, riid: *const GUID, ppv: *mut LPVOID) -> HRESULT {
+	if riid.is_null() || ppv.is_null() {
+		return code::E_POINTER.into();
+	}
+	let obj = ComBox::<T>::from_inner(this);
+	if IUnknown::matches_iid(&*riid) {
+		(*obj).refs.fetch_add(1, Ordering::Relaxed);
+		*ppv = this as LPVOID;
+		code::S_OK.into()
+	}
+	else {
+		ComBox::query(obj, &*riid, ppv)
+	}
+}
+unsafe extern "system" fn inner_add_ref<T: ComObject>(this: *mut IUnknown) -> u32 {
+	(*ComBox::<T>::from_inner(this)).refs.fetch_add(1, Ordering::Relaxed) + 1
+}
+unsafe extern "system" fn inner_release<T: ComObject>(this: *mut IUnknown) -> u32 {
+	ComBox::<T>::release_inner(ComBox::<T>::from_inner(this))
+}
+
+//----------------------------------------------------------------
+// Weak references
+
+/// Weak reference to a [`ComBox`](struct.ComBox.html) object.
+///
+/// Does not keep the object alive, [`upgrade`](#method.upgrade) returns a com pointer as long as the object exists.
+/// Created by [`ComBox::downgrade`](struct.ComBox.html#method.downgrade).
+pub struct ComWeak<P: ComPtr> {
+	iface: *mut P::Interface,
+	obj: *mut c_void,
+	vtbl: &'static WeakVtbl,
+	_marker: PhantomData<P>,
+}
+impl<P: ComPtr> ComWeak<P> {
+	/// Returns a com pointer to the object, unless it has been destroyed.
+	pub fn upgrade(&self) -> Option<P> {
+		unsafe {
+			if (self.vtbl.upgrade)(self.obj) {
+				Some(P::from_ptr(self.iface))
+			}
+			else {
+				None
+			}
+		}
+	}
+}
+impl<P: ComPtr> Clone for ComWeak<P> {
+	fn clone(&self) -> ComWeak<P> {
+		unsafe { (self.vtbl.add_weak)(self.obj); }
+		ComWeak { ..*self }
+	}
+}
+impl<P: ComPtr> Drop for ComWeak<P> {
+	fn drop(&mut self) {
+		unsafe { (self.vtbl.release_weak)(self.obj); }
+	}
+}
+impl<P: ComPtr> fmt::Debug for ComWeak<P> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("ComWeak").field(&self.iface).finish()
+	}
+}
+
+/// Type erased access to the counts of a `ComBox<T>`.
+struct WeakVtbl {
+	upgrade: unsafe fn(*mut c_void) -> bool,
+	add_weak: unsafe fn(*mut c_void),
+	release_weak: unsafe fn(*mut c_void),
+}
+impl WeakVtbl {
+	const fn new<T: ComObject>() -> WeakVtbl {
+		unsafe fn upgrade<T: ComObject>(obj: *mut c_void) -> bool {
+			ComBox::<T>::upgrade(obj as *mut ComBox<T>)
+		}
+		unsafe fn add_weak<T: ComObject>(obj: *mut c_void) {
+			(*(obj as *mut ComBox<T>)).weak.fetch_add(1, Ordering::Relaxed);
+		}
+		unsafe fn release_weak<T: ComObject>(obj: *mut c_void) {
+			ComBox::<T>::release_weak(obj as *mut ComBox<T>)
+		}
+		WeakVtbl {
+			upgrade: upgrade::<T>,
+			add_weak: add_weak::<T>,
+			release_weak: release_weak::<T>,
+		}
+	}
+}
+struct WeakVtbls<T>(PhantomData<T>);
+impl<T: ComObject> WeakVtbls<T> {
+	const VTBL: WeakVtbl = WeakVtbl::new::<T>();
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use ::std::cell::{Cell, RefCell};
+	use ::std::rc::Rc;
+	use ::com_sys::HRESULT;
+	use ::com_sys::unknown::{IUnknown, IUnknownVtbl};
+	use ::hr::{code, HResult};
+	use ::com::{AsComPtr, CastError, ComResult};
+	use ::unknown::IUnknownPtr;
+	use super::*;
+
+	com_interface! {
+		interface IGetter(IGetterVtbl): IUnknown(IUnknownVtbl);
+		{0x3a1f5b2c-0x7d4e-0x4f60-0x8a9b-0x0c1d2e3f4a5b}
+		pub Get: unsafe extern "system" fn(
+			This: *mut IGetter,
+			pValue: *mut i32,
+		) -> HRESULT,
+	}
+	com_interface! {
+		interface ISetter(ISetterVtbl): IGetter(IGetterVtbl);
+		{0x3a1f5b2c-0x7d4e-0x4f60-0x8a9b-0x0c1d2e3f4a5c}
+		pub Set: unsafe extern "system" fn(
+			This: *mut ISetter,
+			value: i32,
+		) -> HRESULT,
+	}
+	com_interface! {
+		interface IUnrelated(IUnrelatedVtbl): IUnknown(IUnknownVtbl);
+		{0x3a1f5b2c-0x7d4e-0x4f60-0x8a9b-0x0c1d2e3f4a5d}
+	}
+	com_ptr! {
+		#[derive(Clone)]
+		pub struct IGetterPtr(IGetter): IUnknownPtr;
+	}
+	impl IGetterPtr {
+		fn get(&self) -> ComResult<i32> {
+			let mut value = 0;
+			let hr = unsafe { com_call!(Get(self, &mut value)) };
+			HResult::result(hr, value)
+		}
+	}
+	com_ptr! {
+		#[derive(Clone)]
+		pub struct ISetterPtr(ISetter): IGetterPtr;
+	}
+	impl ISetterPtr {
+		fn set(&self, value: i32) -> ComResult<()> {
+			let hr = unsafe { com_call!(Set(self, value)) };
+			HResult::result(hr, ())
+		}
+	}
+	com_ptr! {
+		pub struct IUnrelatedPtr(IUnrelated): IUnknownPtr;
+	}
+
+	struct Value {
+		value: Cell<i32>,
+		alive: Rc<Cell<bool>>,
+	}
+	impl Drop for Value {
+		fn drop(&mut self) {
+			self.alive.set(false);
+		}
+	}
+	unsafe extern "system" fn getter_get(this: *mut IGetter, value: *mut i32) -> HRESULT {
+		*value = ComBox::<Value>::from_interface(this).value.get();
+		code::S_OK.into()
+	}
+	unsafe extern "system" fn setter_get(this: *mut ISetter, value: *mut i32) -> HRESULT {
+		*value = ComBox::<Value>::from_interface(this).value.get();
+		code::S_OK.into()
+	}
+	unsafe extern "system" fn setter_set(this: *mut ISetter, value: i32) -> HRESULT {
+		ComBox::<Value>::from_interface(this).value.set(value);
+		code::S_OK.into()
+	}
+
+	com_object! {
+		impl Value {
+			IGetter(IGetterVtbl {
+				base: IUnknownVtbl,
+				Get: getter_get,
+			}),
+			ISetter(ISetterVtbl {
+				base: IGetterVtbl {
+					base: IUnknownVtbl,
+					Get: setter_get,
+				},
+				Set: setter_set,
+			}),
+		}
+	}
+
+	#[test]
+	fn units() {
+		let alive = Rc::new(Cell::new(true));
+		let setter: ISetterPtr = ComBox::new(Value { value: Cell::new(13), alive: alive.clone() });
+		unsafe { assert_eq!(ComBox::<Value>::ref_count(setter.as_ptr()), 1); }
+
+		// Querying for a base interface answers with the first interface implementing it.
+		let getter = setter.try_cast::<IGetterPtr>().unwrap();
+		assert_eq!(getter.get(), Ok(13));
+		assert_eq!(setter.set(42), Ok(()));
+		assert_eq!(getter.get(), Ok(42));
+		assert_ne!(getter.as_ptr() as usize, setter.as_ptr() as usize);
+
+		// `IUnknown` identity is the first slot.
+		let unknown1 = getter.try_cast::<IUnknownPtr>().unwrap();
+		let unknown2 = setter.try_cast::<IUnknownPtr>().unwrap();
+		assert_eq!(unknown1.as_ptr(), unknown2.as_ptr());
+		assert_eq!(unknown1.as_ptr() as usize, getter.as_ptr() as usize);
+
+		assert_eq!(setter.try_cast::<IUnrelatedPtr>().err(), Some(CastError::NoInterface));
+
+		unsafe { assert_eq!(ComBox::<Value>::ref_count(setter.as_ptr()), 4); }
+		let clone = setter.clone();
+		unsafe { assert_eq!(ComBox::<Value>::ref_count(setter.as_ptr()), 5); }
+		drop((getter, unknown1, unknown2, setter));
+		assert!(alive.get());
+		drop(clone);
+		assert!(!alive.get());
+	}
+
+	#[test]
+	fn weak() {
+		let alive = Rc::new(Cell::new(true));
+		let setter: ISetterPtr = ComBox::new(Value { value: Cell::new(13), alive: alive.clone() });
+		let weak = unsafe { ComBox::<Value>::downgrade(&setter) };
+		unsafe { assert_eq!(ComBox::<Value>::ref_count(setter.as_ptr()), 1); }
+
+		let upgraded = weak.upgrade().unwrap();
+		assert_eq!(upgraded.as_ptr(), setter.as_ptr());
+		unsafe { assert_eq!(ComBox::<Value>::ref_count(setter.as_ptr()), 2); }
+		let clone = weak.clone();
+		drop((upgraded, setter, weak));
+		// The weak references do not keep the value alive.
+		assert!(!alive.get());
+		assert!(clone.upgrade().is_none());
+
+		// Tear-offs release their owner with their last reference.
+		let value = Rc::new(Cell::new(13));
+		let getter: IGetterPtr = ComBox::new(Owner { value: value.clone() });
+		let setter = getter.try_cast::<ISetterPtr>().unwrap();
+		let weak = unsafe { ComBox::<TearOff>::downgrade(&setter) };
+		drop(setter);
+		unsafe { assert_eq!(ComBox::<Owner>::ref_count(getter.as_ptr()), 1); }
+		assert!(weak.upgrade().is_none());
+		drop(getter);
+		assert_eq!(Rc::strong_count(&value), 1);
+	}
+
+	// Outer object aggregating a `Value` for its getter and setter.
+	struct Aggregate {
+		inner: RefCell<Option<IUnknownPtr>>,
+		alive: Rc<Cell<bool>>,
+	}
+	impl Drop for Aggregate {
+		fn drop(&mut self) {
+			self.alive.set(false);
+		}
+	}
+	com_object! {
+		impl Aggregate {
+			IUnrelated(IUnrelatedVtbl {
+				base: IUnknownVtbl,
+			}),
+		}
+		unsafe {
+			fn query_fallback(&self, _outer: &IUnknownPtr, iid: &GUID) -> ComResult<IUnknownPtr> {
+				match *self.inner.borrow() {
+					Some(ref inner) => inner.query_iid(iid),
+					None => Err(code::E_NOINTERFACE),
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn aggregate() {
+		let outer_alive = Rc::new(Cell::new(true));
+		let inner_alive = Rc::new(Cell::new(true));
+		let outer: IUnknownPtr = ComBox::new(Aggregate { inner: RefCell::new(None), alive: outer_alive.clone() });
+		let inner = unsafe { ComBox::aggregate(Value { value: Cell::new(13), alive: inner_alive.clone() }, &outer) };
+		unsafe { *ComBox::<Aggregate>::from_interface(outer.as_ptr()).inner.borrow_mut() = Some(inner); }
+
+		// The inner object's interfaces are reachable from the outer object and back.
+		let setter = outer.try_cast::<ISetterPtr>().unwrap();
+		assert_eq!(setter.set(42), Ok(()));
+		let getter = setter.try_cast::<IGetterPtr>().unwrap();
+		assert_eq!(getter.get(), Ok(42));
+		let unrelated = getter.try_cast::<IUnrelatedPtr>().unwrap();
+
+		// They share the outer object's identity and reference count.
+		let unknown = setter.try_cast::<IUnknownPtr>().unwrap();
+		assert_eq!(unknown.as_ptr(), outer.as_ptr());
+		unsafe {
+			assert_eq!(ComBox::<Aggregate>::ref_count(outer.as_ptr()), 5);
+			assert_eq!(ComBox::<Value>::ref_count(setter.as_ptr()), 1);
+		}
+
+		drop((outer, setter, getter, unknown));
+		assert!(outer_alive.get() && inner_alive.get());
+		drop(unrelated);
+		assert!(!outer_alive.get() && !inner_alive.get());
+	}
+
+	// Owner object lazily creating a setter tear-off.
+	struct Owner {
+		value: Rc<Cell<i32>>,
+	}
+	com_object! {
+		impl Owner {
+			IGetter(IGetterVtbl {
+				base: IUnknownVtbl,
+				Get: owner_get,
+			}),
+		}
+		unsafe {
+			fn query_fallback(&self, outer: &IUnknownPtr, iid: &GUID) -> ComResult<IUnknownPtr> {
+				if ISetter::matches_iid(iid) {
+					let setter: ISetterPtr = ComBox::tear_off(TearOff { value: self.value.clone() }, outer);
+					Ok(setter.cast::<IGetterPtr>().cast::<IUnknownPtr>().clone())
+				}
+				else {
+					Err(code::E_NOINTERFACE)
+				}
+			}
+		}
+	}
+	unsafe extern "system" fn owner_get(this: *mut IGetter, value: *mut i32) -> HRESULT {
+		*value = ComBox::<Owner>::from_interface(this).value.get();
+		code::S_OK.into()
+	}
+
+	struct TearOff {
+		value: Rc<Cell<i32>>,
+	}
+	com_object! {
+		impl TearOff {
+			ISetter(ISetterVtbl {
+				base: IGetterVtbl {
+					base: IUnknownVtbl,
+					Get: tear_off_get,
+				},
+				Set: tear_off_set,
+			}),
+		}
+	}
+	unsafe extern "system" fn tear_off_get(this: *mut ISetter, value: *mut i32) -> HRESULT {
+		*value = ComBox::<TearOff>::from_interface(this).value.get();
+		code::S_OK.into()
+	}
+	unsafe extern "system" fn tear_off_set(this: *mut ISetter, value: i32) -> HRESULT {
+		ComBox::<TearOff>::from_interface(this).value.set(value);
+		code::S_OK.into()
+	}
+
+	#[test]
+	fn tear_off() {
+		let value = Rc::new(Cell::new(13));
+		let getter: IGetterPtr = ComBox::new(Owner { value: value.clone() });
+		unsafe { assert_eq!(ComBox::<Owner>::ref_count(getter.as_ptr()), 1); }
+
+		// Every query creates a new tear-off which keeps the owner alive.
+		let setter = getter.try_cast::<ISetterPtr>().unwrap();
+		unsafe {
+			assert_eq!(ComBox::<Owner>::ref_count(getter.as_ptr()), 2);
+			assert_eq!(ComBox::<TearOff>::ref_count(setter.as_ptr()), 1);
+		}
+		assert_eq!(setter.set(42), Ok(()));
+		assert_eq!(getter.get(), Ok(42));
+
+		// The tear-off shares the owner's identity and answers for its own interfaces.
+		let unknown = setter.try_cast::<IUnknownPtr>().unwrap();
+		assert_eq!(unknown.as_ptr() as usize, getter.as_ptr() as usize);
+		let setter2 = setter.try_cast::<ISetterPtr>().unwrap();
+		assert_eq!(setter2.as_ptr(), setter.as_ptr());
+		assert_eq!(setter.try_cast::<IUnrelatedPtr>().err(), Some(CastError::NoInterface));
+
+		drop((setter, setter2, unknown));
+		unsafe { assert_eq!(ComBox::<Owner>::ref_count(getter.as_ptr()), 1); }
+		drop(getter);
+		assert_eq!(Rc::strong_count(&value), 1);
+	}
+
+	// Interfaces defined by the attribute inherit from and are implemented like any other.
+	#[::com::com_interface("3a1f5b2c-7d4e-4f60-8a9b-0c1d2e3f4a5e")]
+	#[derive(Clone)]
+	pub trait IAdder: IGetter {
+		fn Add(&self, value: i32) -> HRESULT;
+		fn AddOut(&self, value: i32, pResult: *mut i32,) -> HRESULT;
+	}
+	unsafe extern "system" fn adder_add(this: *mut IAdder, value: i32) -> HRESULT {
+		let cell = &ComBox::<Sum>::from_interface(this).0.value;
+		cell.set(cell.get() + value);
+		code::S_OK.into()
+	}
+	unsafe extern "system" fn adder_add_out(this: *mut IAdder, value: i32, result: *mut i32) -> HRESULT {
+		adder_add(this, value);
+		*result = ComBox::<Sum>::from_interface(this).0.value.get();
+		code::S_OK.into()
+	}
+	unsafe extern "system" fn adder_get(this: *mut IAdder, value: *mut i32) -> HRESULT {
+		*value = ComBox::<Sum>::from_interface(this).0.value.get();
+		code::S_OK.into()
+	}
+	struct Sum(Value);
+	com_object! {
+		impl Sum {
+			IAdder(IAdderVtbl {
+				base: IGetterVtbl {
+					base: IUnknownVtbl,
+					Get: adder_get,
+				},
+				Add: adder_add,
+				AddOut: adder_add_out,
+			}),
+		}
+	}
+
+	#[test]
+	fn attribute() {
+		let alive = Rc::new(Cell::new(true));
+		let adder: IAdderPtr = ComBox::new(Sum(Value { value: Cell::new(1), alive: alive.clone() }));
+		assert_eq!(<IAdder as ComInterface>::IID, "3a1f5b2c-7d4e-4f60-8a9b-0c1d2e3f4a5e".parse().unwrap());
+
+		assert_eq!(HResult::from(adder.Add(2)), code::S_OK);
+		let mut result = 0;
+		assert_eq!(HResult::from(unsafe { adder.AddOut(3, &mut result) }), code::S_OK);
+		assert_eq!(result, 6);
+
+		// Derefs to the base com pointer.
+		assert_eq!(adder.get(), Ok(6));
+		let getter = adder.try_cast::<IGetterPtr>().unwrap();
+		assert_eq!(getter.as_ptr() as usize, adder.as_ptr() as usize);
+		let clone = adder.clone();
+		drop((adder, getter));
+		assert!(alive.get());
+		drop(clone);
+		assert!(!alive.get());
+	}
+
+	com_assert_slots!(ISetter, 5);
+	com_assert_slots!(IAdder, 6);
+
+	#[test]
+	fn vtbl_layout() {
+		use ::std::mem;
+		use ::ComVtbl;
+		assert_eq!(ISetterVtbl::<ISetter>::slot_names(), ["QueryInterface", "AddRef", "Release", "Get", "Set"]);
+		// Vtbls defined by the attribute list their slots like the macro defined ones.
+		assert_eq!(IAdderVtbl::<IAdder>::METHODS, ["Add", "AddOut"]);
+		assert_eq!(IAdderVtbl::<IAdder>::SLOTS, 6);
+		assert_eq!(IAdderVtbl::<IAdder>::slot("AddOut"), Some(5));
+		assert_eq!(IAdderVtbl::<IAdder>::offset_of("Get"), Some(3 * mem::size_of::<usize>()));
+		assert_eq!(IAdderVtbl::<IAdder>::slot("Set"), None);
+	}
+
+	#[test]
+	fn upcasts() {
+		use ::com::{ComCast, ComPtr};
+		// Accepts any com pointer deriving from `IUnknown`, like `Com::set_proxy_blanket`.
+		fn unknown<P: ComCast<IUnknownPtr>>(ptr: P) -> usize {
+			ptr.cast().as_ptr() as usize
+		}
+		let alive = Rc::new(Cell::new(true));
+		let setter: ISetterPtr = ComBox::new(Value { value: Cell::new(2), alive: alive.clone() });
+		assert_eq!(ComPtr::cast::<IGetterPtr>(&setter).get(), Ok(2));
+		assert_eq!(ComPtr::cast::<ISetterPtr>(&setter).get(), Ok(2));
+		assert_eq!(unknown(&setter), setter.as_ptr() as usize);
+		assert_eq!(unknown(ComPtr::cast::<IUnknownPtr>(&setter)), setter.as_ptr() as usize);
+
+		let adder: IAdderPtr = ComBox::new(Sum(Value { value: Cell::new(1), alive: alive.clone() }));
+		assert_eq!(ComPtr::cast::<IGetterPtr>(&adder).get(), Ok(1));
+		assert_eq!(unknown(&adder), adder.as_ptr() as usize);
+	}
+}
@@ -0,0 +1,403 @@
+/*!
+Generic owning interface pointer.
+
+[`ComRc<I>`](struct.ComRc.html) owns a reference to any interface: it calls `AddRef` when cloned and `Release` when dropped.
+It derefs to the interface struct, whose vtbl is reached through [`ComInterface::vtbl`](../trait.ComInterface.html#tymethod.vtbl).
+Interfaces defined with [`#[com_interface]`](../com/attr.com_interface.html) also have their shims and wrappers on the interface struct.
+Like the vtbl, the interface struct does not deref to its base interface: the base methods are reached after upcasting.
+
+No per interface newtype is needed, `com_ptr!` remains for adding methods to a named com pointer.
+It can be replaced by an alias, eg. `pub type IInterfacePtr = ComRc<IInterface>;`.
+
+Upcasting to the base interface is free, the pointer is reinterpreted as its [`ComInherit::Super`](../trait.ComInherit.html).
+
+[`ComRef<'a, I>`](struct.ComRef.html) borrows an interface pointer without touching its reference count,
+eg. one received as a callback argument. It is upgraded to a `ComRc<I>` when the reference must outlive the borrow.
+
+# Examples
+
+```
+# #[macro_use] extern crate com_sys; #[macro_use] extern crate com_types; fn main() {
+use ::std::cell::Cell;
+use ::com_sys::HRESULT;
+use ::com_sys::unknown::{IUnknown, IUnknownVtbl};
+use ::com_types::com::{com_interface, ComPtr};
+use ::com_types::hr::code;
+use ::com_types::object::ComBox;
+use ::com_types::rc::ComRc;
+
+#[com_interface("0f8e1c2a-3b4d-4e5f-8a6b-7c8d9e0f1a2b")]
+pub trait ICounter: IUnknown {
+	fn Increment(&self, #[retval] pValue: *mut i32) -> HRESULT;
+}
+
+struct Counter(Cell<i32>);
+unsafe extern "system" fn counter_increment(this: *mut ICounter, value: *mut i32) -> HRESULT {
+	let counter = ComBox::<Counter>::from_interface(this);
+	counter.0.set(counter.0.get() + 1);
+	*value = counter.0.get();
+	code::S_OK.into()
+}
+com_object! {
+	impl Counter {
+		ICounter(ICounterVtbl {
+			base: IUnknownVtbl,
+			Increment: counter_increment,
+		}),
+	}
+}
+
+let counter: ComRc<ICounter> = ComBox::new(Counter(Cell::new(0)));
+assert_eq!(counter.increment(), Ok(1));
+let unknown: ComRc<IUnknown> = counter.clone().upcast();
+let counter = unknown.try_cast::<ComRc<ICounter>>().unwrap();
+assert_eq!(counter.increment(), Ok(2));
+# }
+```
+*/
+
+use ::std::{fmt, mem};
+use ::std::marker::PhantomData;
+use ::std::ops::Deref;
+use ::std::ptr::NonNull;
+
+use ::com_sys::{ComInherit, ComInterface};
+use ::com_sys::unknown::IUnknown;
+
+use ::com::{self, AsComPtr, CastError, ComInterfacePtr, ComPtr};
+
+/// Owning pointer to a COM interface.
+///
+/// Has the same layout as a raw interface pointer and is never null.
+#[repr(transparent)]
+pub struct ComRc<I: ComInterface> {
+	ptr: NonNull<I>,
+}
+impl<I: ComInterface> ComRc<I> {
+	/// Takes ownership of a raw interface pointer.
+	///
+	/// # Safety
+	///
+	/// Must be a valid interface pointer whose reference is transferred, returns `None` if null.
+	#[inline]
+	pub unsafe fn from_raw(iface: *mut I) -> Option<ComRc<I>> {
+		NonNull::new(iface).map(|ptr| ComRc { ptr })
+	}
+	/// Releases ownership of the interface pointer without calling `Release`.
+	#[inline]
+	pub fn into_raw(self) -> *mut I {
+		self.into_raw_nonnull().as_ptr()
+	}
+	/// Converts to the named com pointer of the interface, keeping the reference.
+	#[inline]
+	pub fn into_ptr(self) -> I::Ptr where I: ComInterfacePtr {
+		unsafe { ComPtr::from_ptr(self.into_raw()) }
+	}
+	/// Converts to the base interface.
+	#[inline]
+	pub fn upcast(self) -> ComRc<I::Super> where I: ComInherit {
+		ComRc { ptr: self.into_raw_nonnull().cast() }
+	}
+	/// Borrows as the base interface.
+	#[inline]
+	pub fn as_super(&self) -> &ComRc<I::Super> where I: ComInherit {
+		// Every interface pointer is a valid pointer to its base interface and both have the same layout.
+		unsafe { &*(self as *const ComRc<I> as *const ComRc<I::Super>) }
+	}
+
+	fn into_raw_nonnull(self) -> NonNull<I> {
+		let ptr = self.ptr;
+		mem::forget(self);
+		ptr
+	}
+	fn unknown(&self) -> *mut IUnknown {
+		unknown(self.ptr)
+	}
+}
+impl<I: ComInterface> Clone for ComRc<I> {
+	#[inline]
+	#[cfg_attr(any(test, feature = "leak"), track_caller)]
+	fn clone(&self) -> ComRc<I> {
+		call_site!();
+		unsafe {
+			let unknown = self.unknown();
+			((*unknown).vtbl().AddRef)(unknown);
+		}
+		ComRc { ptr: self.ptr }
+	}
+}
+impl<I: ComInterface> Drop for ComRc<I> {
+	#[inline]
+	fn drop(&mut self) {
+		unsafe {
+			let unknown = self.unknown();
+			((*unknown).vtbl().Release)(unknown);
+		}
+	}
+}
+impl<I: ComInterface> Deref for ComRc<I> {
+	type Target = I;
+	#[inline(always)]
+	fn deref(&self) -> &I {
+		unsafe { self.ptr.as_ref() }
+	}
+}
+impl<I: ComInterface> AsComPtr for ComRc<I> {
+	type Interface = I;
+	#[inline(always)]
+	fn as_ptr(&self) -> *mut I {
+		self.ptr.as_ptr()
+	}
+}
+impl<I: ComInterface> ComPtr for ComRc<I> {
+	#[inline(always)]
+	unsafe fn new(iface: ::LPVOID) -> ComRc<I> {
+		<ComRc<I> as ComPtr>::from_ptr(iface as *mut I)
+	}
+	#[inline(always)]
+	unsafe fn from_ptr(iface: *mut I) -> ComRc<I> {
+		debug_assert!(!iface.is_null());
+		ComRc { ptr: NonNull::new_unchecked(iface) }
+	}
+}
+impl<I: ComInterface> fmt::Debug for ComRc<I> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("ComRc").field(&self.ptr).finish()
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Borrowed reference to a COM interface.
+///
+/// Copying and dropping does not call `AddRef` or `Release`, the lifetime ties it to the owner of the reference.
+#[repr(transparent)]
+pub struct ComRef<'a, I: ComInterface> {
+	ptr: NonNull<I>,
+	_marker: PhantomData<&'a I>,
+}
+impl<'a, I: ComInterface> ComRef<'a, I> {
+	/// Borrows the interface pointer of the com pointer.
+	///
+	/// Borrow a `ComRc<I>` safely with `ComRef::from`.
+	///
+	/// # Safety
+	///
+	/// `AsComPtr` is a safe trait, the com pointer must return a valid interface pointer which remains alive while it is borrowed.
+	/// The pointer must not be null, which is checked in debug builds.
+	#[inline]
+	pub unsafe fn new<P: AsComPtr<Interface = I>>(ptr: &'a P) -> ComRef<'a, I> {
+		let ptr = ptr.as_ptr();
+		debug_assert!(!ptr.is_null(), "null interface pointer");
+		ComRef { ptr: NonNull::new_unchecked(ptr), _marker: PhantomData }
+	}
+	/// Borrows a raw interface pointer, returns `None` if null.
+	///
+	/// # Safety
+	///
+	/// Must be a valid interface pointer which remains alive for the lifetime `'a`.
+	#[inline]
+	pub unsafe fn from_raw(iface: *mut I) -> Option<ComRef<'a, I>> {
+		NonNull::new(iface).map(|ptr| ComRef { ptr, _marker: PhantomData })
+	}
+	/// Takes a new reference to the interface.
+	#[inline]
+	#[cfg_attr(any(test, feature = "leak"), track_caller)]
+	pub fn upgrade(self) -> ComRc<I> {
+		call_site!();
+		unsafe {
+			let unknown = unknown(self.ptr);
+			((*unknown).vtbl().AddRef)(unknown);
+		}
+		ComRc { ptr: self.ptr }
+	}
+	/// Borrows as the base interface.
+	#[inline]
+	pub fn upcast(self) -> ComRef<'a, I::Super> where I: ComInherit {
+		ComRef { ptr: self.ptr.cast(), _marker: PhantomData }
+	}
+	/// Queries the object for another interface, see [`ComPtr::try_cast`](../com/trait.ComPtr.html#method.try_cast).
+	#[inline]
+	#[cfg_attr(any(test, feature = "leak"), track_caller)]
+	pub fn try_cast<T: ComPtr>(self) -> Result<T, CastError> {
+		call_site!();
+		unsafe { com::query_interface(unknown(self.ptr), <T::Interface as ComInterface>::iid()).map(|ppv| T::new(ppv)) }
+	}
+}
+impl<'a, I: ComInterface> Copy for ComRef<'a, I> {}
+impl<'a, I: ComInterface> Clone for ComRef<'a, I> {
+	#[inline(always)]
+	fn clone(&self) -> ComRef<'a, I> {
+		*self
+	}
+}
+impl<'a, I: ComInterface> Deref for ComRef<'a, I> {
+	type Target = I;
+	#[inline(always)]
+	fn deref(&self) -> &I {
+		unsafe { self.ptr.as_ref() }
+	}
+}
+impl<'a, I: ComInterface> AsComPtr for ComRef<'a, I> {
+	type Interface = I;
+	#[inline(always)]
+	fn as_ptr(&self) -> *mut I {
+		self.ptr.as_ptr()
+	}
+}
+impl<'a, I: ComInterface> From<&'a ComRc<I>> for ComRef<'a, I> {
+	#[inline]
+	fn from(rc: &'a ComRc<I>) -> ComRef<'a, I> {
+		ComRef { ptr: rc.ptr, _marker: PhantomData }
+	}
+}
+impl<'a, I: ComInterface> fmt::Debug for ComRef<'a, I> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("ComRef").field(&self.ptr).finish()
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Every interface starts with the `IUnknown` vtbl.
+fn unknown<I: ComInterface>(ptr: NonNull<I>) -> *mut IUnknown {
+	ptr.as_ptr() as *mut IUnknown
+}
+
+#[cfg(test)]
+mod tests {
+	use ::std::ptr;
+	use ::std::cell::Cell;
+	use ::std::rc::Rc;
+	use ::com_sys::HRESULT;
+	use ::com_sys::unknown::{IUnknown, IUnknownVtbl};
+	use ::hr::{code, HResult};
+	use ::object::ComBox;
+	use super::*;
+
+	#[::com::com_interface("5d2e8f3a-1b4c-4d6e-9f70-8a1b2c3d4e5f")]
+	pub trait IValue: IUnknown {
+		fn Get(&self, #[retval] pValue: *mut i32) -> HRESULT;
+	}
+	#[::com::com_interface("5d2e8f3a-1b4c-4d6e-9f70-8a1b2c3d4e60")]
+	pub trait IValue2: IValue {
+		fn Set(&self, value: i32) -> HRESULT;
+	}
+
+	struct Value {
+		value: Cell<i32>,
+		alive: Rc<Cell<bool>>,
+	}
+	impl Drop for Value {
+		fn drop(&mut self) {
+			self.alive.set(false);
+		}
+	}
+	unsafe extern "system" fn value_get(this: *mut IValue2, value: *mut i32) -> HRESULT {
+		*value = ComBox::<Value>::from_interface(this).value.get();
+		code::S_OK.into()
+	}
+	unsafe extern "system" fn value_set(this: *mut IValue2, value: i32) -> HRESULT {
+		ComBox::<Value>::from_interface(this).value.set(value);
+		code::S_OK.into()
+	}
+	com_object! {
+		impl Value {
+			IValue2(IValue2Vtbl {
+				base: IValueVtbl {
+					base: IUnknownVtbl,
+					Get: value_get,
+				},
+				Set: value_set,
+			}),
+		}
+	}
+
+	#[test]
+	fn refcount() {
+		let alive = Rc::new(Cell::new(true));
+		let value: ComRc<IValue2> = ComBox::new(Value { value: Cell::new(1), alive: alive.clone() });
+		unsafe { assert_eq!(ComBox::<Value>::ref_count(value.as_ptr()), 1); }
+		let clone = value.clone();
+		unsafe { assert_eq!(ComBox::<Value>::ref_count(value.as_ptr()), 2); }
+		assert_eq!(clone.as_ptr(), value.as_ptr());
+		drop(value);
+		assert!(alive.get());
+
+		let raw = clone.into_raw();
+		let clone = unsafe { ComRc::from_raw(raw) }.unwrap();
+		drop(clone);
+		assert!(!alive.get());
+		assert!(unsafe { ComRc::<IValue>::from_raw(ptr::null_mut()) }.is_none());
+	}
+	#[test]
+	fn methods() {
+		let alive = Rc::new(Cell::new(true));
+		let value: ComRc<IValue2> = ComBox::new(Value { value: Cell::new(1), alive: alive.clone() });
+		// The shims and wrappers are reached through `Deref`, the inherited ones after upcasting.
+		assert_eq!(value.set(5), Ok(()));
+		assert_eq!(value.as_super().get(), Ok(5));
+		assert_eq!(HResult::from(value.Set(6)), code::S_OK);
+		let mut result = 0;
+		assert_eq!(HResult::from(unsafe { com_call!(Get(&value, &mut result)) }), code::S_OK);
+		assert_eq!(result, 6);
+
+		let base: ComRc<IValue> = value.clone().upcast();
+		assert_eq!(base.get(), Ok(6));
+		let unknown = base.upcast();
+		let value2 = unknown.try_cast::<ComRc<IValue2>>().unwrap();
+		assert_eq!(value2.as_ptr(), value.as_ptr());
+		assert!(unknown.try_cast::<IValuePtr>().is_ok());
+
+		// Converts to the named com pointer.
+		let ptr = value2.into_ptr();
+		assert_eq!(ptr.get(), Ok(6));
+		drop((value, unknown, ptr));
+		assert!(!alive.get());
+	}
+	#[test]
+	fn borrowed() {
+		// Receives a borrowed interface pointer, as passed to a callback.
+		unsafe fn callback(this: *mut IValue2) -> i32 {
+			let value = ComRef::from_raw(this).unwrap();
+			assert_eq!(ComBox::<Value>::ref_count(value.as_ptr()), 1);
+			let unknown = value.upcast().upcast();
+			let base = unknown.try_cast::<ComRc<IValue>>().unwrap();
+			assert_eq!(ComBox::<Value>::ref_count(value.as_ptr()), 2);
+			drop(base);
+			value.upcast().get().unwrap()
+		}
+
+		let alive = Rc::new(Cell::new(true));
+		let value: ComRc<IValue2> = ComBox::new(Value { value: Cell::new(3), alive: alive.clone() });
+		assert_eq!(unsafe { callback(value.as_ptr()) }, 3);
+		unsafe { assert_eq!(ComBox::<Value>::ref_count(value.as_ptr()), 1); }
+
+		let named = value.clone().into_ptr();
+		let borrowed = unsafe { ComRef::new(&named) };
+		assert_eq!(borrowed.as_ptr(), value.as_ptr());
+		assert_eq!(ComRef::from(&value).as_ptr(), value.as_ptr());
+		assert_eq!(borrowed.set(4), Ok(()));
+		let owned = borrowed.upgrade();
+		unsafe { assert_eq!(ComBox::<Value>::ref_count(value.as_ptr()), 3); }
+		drop((value, named));
+		assert_eq!(owned.as_super().get(), Ok(4));
+		assert!(alive.get());
+		drop(owned);
+		assert!(!alive.get());
+		assert!(unsafe { ComRef::<IValue>::from_raw(ptr::null_mut()) }.is_none());
+	}
+	#[cfg(debug_assertions)]
+	#[test]
+	#[should_panic(expected = "null interface pointer")]
+	fn borrow_null() {
+		struct Null;
+		impl AsComPtr for Null {
+			type Interface = IValue;
+			fn as_ptr(&self) -> *mut IValue {
+				ptr::null_mut()
+			}
+		}
+		unsafe { ComRef::new(&Null); }
+	}
+}
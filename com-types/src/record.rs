@@ -0,0 +1,655 @@
+/*!
+User defined types, COM records.
+
+A record is a `#[repr(C)]` struct described by its `IRecordInfo`, which creates, copies and destroys instances and accesses their fields by name.
+Variants hold a record as `VT_RECORD` together with its record info, see [`Record`](struct.Record.html).
+Safe arrays store records in place, the record info is attached to the array.
+
+Rust structs become records with `#[derive(RecordType)]` which implements [`RecordType`](trait.RecordType.html) from the list of fields,
+the record info is then implemented by this crate.
+The fields must be variant [`Type`](../variant/trait.Type.html)s whose `Owned` type is the field's type.
+
+# Examples
+
+```
+extern crate com_types;
+use ::com_types::record::{Record, RecordType};
+use ::com_types::variant::Variant;
+use ::com_types::variant::types::{self, I4};
+
+#[derive(Clone, Debug, Default, PartialEq, RecordType)]
+#[repr(C)]
+#[record("3c4e8f02-7d1a-4b5e-9f60-2a8b1c7d9e34")]
+struct Point {
+	x: i32,
+	y: i32,
+}
+
+# fn main() {
+let var = Variant::from(Record::new(Point { x: 1, y: 2 }));
+let mut record = var.into::<types::Record>();
+assert_eq!(record.get_field("x").unwrap().into::<I4>(), 1);
+
+record.put_field("y", &Variant::from(3)).unwrap();
+assert_eq!(record.downcast_ref::<Point>(), Some(&Point { x: 1, y: 3 }));
+# }
+```
+*/
+
+use ::std::{fmt, mem, ptr, slice};
+use ::std::any::TypeId;
+use ::std::marker::PhantomData;
+use ::std::mem::ManuallyDrop;
+use ::std::os::raw::c_void;
+
+use ::com_sys::{ComInterface, Guid};
+use ::com_sys::dispatch::{IRecordInfo, IRecordInfoVtbl, BRECORD, INVOKE_PROPERTYPUT};
+use ::winapi::{GUID, HRESULT, BOOL, ULONG, BSTR, LPCOLESTR, VARIANT};
+
+use ::bstr::BString;
+use ::com::{AsComPtr, ComPtr, ComResult};
+use ::hr::{code, HResult};
+use ::object::ComBox;
+use ::unknown::IUnknownPtr;
+use ::variant::Variant;
+
+pub use com_macros::RecordType;
+
+com_ptr! {
+	#[derive(Clone, Debug)]
+	pub struct RecordInfoPtr(IRecordInfo): IUnknownPtr;
+}
+impl RecordInfoPtr {
+	/// Returns the GUID identifying the record's type.
+	pub fn guid(&self) -> ComResult<Guid> {
+		let mut guid = GUID { Data1: 0, Data2: 0, Data3: 0, Data4: [0; 8] };
+		unsafe {
+			let hr = com_call!(GetGuid(self, &mut guid));
+			HResult::result_of(hr, || Guid::from_guid(guid))
+		}
+	}
+	/// Returns the name of the record's type.
+	pub fn name(&self) -> ComResult<BString> {
+		let mut name: BSTR = ptr::null_mut();
+		unsafe {
+			let hr = com_call!(GetName(self, &mut name));
+			HResult::result_of(hr, || BString::from_raw(name))
+		}
+	}
+	/// Returns the size of a record in bytes.
+	pub fn size(&self) -> ComResult<usize> {
+		let mut size: ULONG = 0;
+		unsafe {
+			let hr = com_call!(GetSize(self, &mut size));
+			HResult::result(hr, size as usize)
+		}
+	}
+	/// Returns the names of the fields.
+	pub fn field_names(&self) -> ComResult<Vec<BString>> {
+		let mut count: ULONG = 0;
+		unsafe {
+			HResult::result(com_call!(GetFieldNames(self, &mut count, ptr::null_mut())), ())?;
+			let mut names: Vec<BSTR> = vec![ptr::null_mut(); count as usize];
+			let hr = com_call!(GetFieldNames(self, &mut count, names.as_mut_ptr()));
+			names.truncate(count as usize);
+			let names = names.into_iter().map(|name| BString::from_raw(name)).collect();
+			HResult::result(hr, names)
+		}
+	}
+	/// Returns if both record infos describe the same type.
+	pub fn is_matching_type(&self, other: &RecordInfoPtr) -> bool {
+		unsafe { com_call!(IsMatchingType(self, other.as_ptr())) != 0 }
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Record held by a variant, its data together with the record info describing it.
+///
+/// The record owns its data, which is created, copied and destroyed through the record info.
+#[repr(C)]
+pub struct Record {
+	data: *mut c_void,
+	info: RecordInfoPtr,
+}
+impl Record {
+	/// Creates a record from the Rust value, described by its derived record info.
+	pub fn new<T: RecordType>(value: T) -> Record {
+		Record {
+			// Destroyed by the derived record info.
+			data: Box::into_raw(Box::new(value)) as *mut c_void,
+			info: T::record_info(),
+		}
+	}
+	/// Creates a default initialized record of the type described by the record info.
+	pub fn create(info: RecordInfoPtr) -> ComResult<Record> {
+		let data = unsafe { com_call!(RecordCreate(&info)) };
+		if data.is_null() {
+			return Err(code::E_OUTOFMEMORY);
+		}
+		Ok(Record { data, info })
+	}
+	/// Takes ownership of a raw record.
+	///
+	/// # Safety
+	///
+	/// The record info must not be null, the data must have been created by it and not be owned by anything else.
+	pub unsafe fn from_raw(raw: BRECORD) -> Record {
+		Record {
+			data: raw.pvRecord,
+			info: RecordInfoPtr::from_ptr(raw.pRecInfo),
+		}
+	}
+	/// Surrenders ownership of the record for FFI.
+	///
+	/// The caller is responsible for destroying the record and releasing its record info.
+	pub fn into_raw(self) -> BRECORD {
+		let raw = BRECORD {
+			pvRecord: self.data,
+			pRecInfo: self.info.as_ptr(),
+		};
+		mem::forget(self);
+		raw
+	}
+	/// Returns the record info describing the record.
+	pub fn info(&self) -> &RecordInfoPtr {
+		&self.info
+	}
+	/// Returns a raw pointer to the record's data for FFI.
+	pub fn as_ptr(&self) -> *const c_void {
+		self.data
+	}
+	/// Returns an unsafe mutable pointer to the record's data for FFI.
+	pub fn as_mut_ptr(&mut self) -> *mut c_void {
+		self.data
+	}
+	/// Tries to create a copy of the record.
+	pub fn try_clone(&self) -> ComResult<Record> {
+		let mut data = ptr::null_mut();
+		unsafe {
+			let hr = com_call!(RecordCreateCopy(&self.info, self.data, &mut data));
+			HResult::result_of(hr, || Record { data, info: self.info.clone() })
+		}
+	}
+	/// Returns if the record is of the Rust type `T`, its record info was derived from `T`.
+	///
+	/// Records described by other record infos are never of a Rust type, even if they have the GUID and size of `T`.
+	pub fn is<T: RecordType>(&self) -> bool {
+		match unsafe { derived_layout(self.info.as_ptr()) } {
+			Some(layout) => layout.type_id() == TypeId::of::<T>(),
+			None => false,
+		}
+	}
+	/// Returns a reference to the record as `T` if it is of that type.
+	pub fn downcast_ref<T: RecordType>(&self) -> Option<&T> {
+		if self.is::<T>() {
+			Some(unsafe { &*(self.data as *const T) })
+		}
+		else {
+			None
+		}
+	}
+	/// Returns a mutable reference to the record as `T` if it is of that type.
+	pub fn downcast_mut<T: RecordType>(&mut self) -> Option<&mut T> {
+		if self.is::<T>() {
+			Some(unsafe { &mut *(self.data as *mut T) })
+		}
+		else {
+			None
+		}
+	}
+	/// Returns a copy of the field's value.
+	pub fn get_field(&self, name: &str) -> ComResult<Variant<'static>> {
+		let name = wide(name);
+		let mut value = Variant::init();
+		unsafe {
+			let hr = com_call!(GetField(&self.info, self.data, name.as_ptr(), value.as_mut_ptr()));
+			HResult::result(hr, value)
+		}
+	}
+	/// Puts a copy of the value into the field.
+	pub fn put_field(&mut self, name: &str, value: &Variant) -> ComResult<()> {
+		let name = wide(name);
+		unsafe {
+			let hr = com_call!(PutField(&self.info, INVOKE_PROPERTYPUT, self.data, name.as_ptr(), value.as_ptr() as *mut VARIANT));
+			HResult::result(hr, ())
+		}
+	}
+}
+impl Drop for Record {
+	fn drop(&mut self) {
+		unsafe {
+			let _ = com_call!(RecordDestroy(&self.info, self.data));
+		}
+	}
+}
+impl fmt::Debug for Record {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Record")
+			.field("name", &self.info.name().ok())
+			.field("data", &self.data)
+			.finish()
+	}
+}
+
+/// Null terminated wide string for FFI.
+fn wide(s: &str) -> Vec<u16> {
+	s.encode_utf16().chain(Some(0)).collect()
+}
+
+//----------------------------------------------------------------
+
+/// Rust type exposed as a COM record.
+///
+/// Implement with `#[derive(RecordType)]`, the GUID is given by the `#[record("...")]` attribute and the name is the struct's name.
+///
+/// Records are created with `Default` and copied with `Clone`.
+///
+/// # Safety
+///
+/// The type must be `#[repr(C)]` so that other languages agree on its layout, and the GUID must identify this layout.
+pub unsafe trait RecordType: 'static + Sized + Clone + Default {
+	/// Identifies the record's type.
+	const GUID: Guid;
+	/// Name of the record's type.
+	const NAME: &'static str;
+	/// The fields accessible by name.
+	const FIELDS: &'static [Field<Self>];
+
+	/// Returns a new record info describing the type.
+	fn record_info() -> RecordInfoPtr {
+		ComBox::new(RecordInfo(Box::new(Of::<Self>(PhantomData))))
+	}
+}
+
+/// Field of a [`RecordType`](trait.RecordType.html).
+pub struct Field<T> {
+	/// Name of the field, looked up matching case.
+	pub name: &'static str,
+	/// Returns a copy of the field's value.
+	pub get: fn(&T) -> Variant<'static>,
+	/// Puts the value into the field, the value is returned if its type does not match.
+	pub put: fn(&mut T, Variant<'static>) -> Result<(), Variant<'static>>,
+}
+
+/// Type erased `RecordType` so that a single `RecordInfo` object describes any record.
+trait Layout {
+	fn type_id(&self) -> TypeId;
+	fn guid(&self) -> Guid;
+	fn name(&self) -> &'static str;
+	fn size(&self) -> usize;
+	fn field_names(&self) -> Vec<&'static str>;
+	unsafe fn init(&self, data: *mut c_void);
+	unsafe fn clear(&self, data: *mut c_void);
+	unsafe fn copy(&self, src: *const c_void, dest: *mut c_void);
+	fn create(&self) -> *mut c_void;
+	unsafe fn create_copy(&self, src: *const c_void) -> *mut c_void;
+	unsafe fn destroy(&self, data: *mut c_void);
+	fn field(&self, name: &str) -> Option<usize>;
+	unsafe fn get_field(&self, data: *const c_void, index: usize) -> Variant<'static>;
+	unsafe fn put_field(&self, data: *mut c_void, index: usize, value: Variant<'static>) -> Result<(), Variant<'static>>;
+}
+
+struct Of<T>(PhantomData<T>);
+impl<T: RecordType> Layout for Of<T> {
+	fn type_id(&self) -> TypeId {
+		TypeId::of::<T>()
+	}
+	fn guid(&self) -> Guid {
+		T::GUID
+	}
+	fn name(&self) -> &'static str {
+		T::NAME
+	}
+	fn size(&self) -> usize {
+		mem::size_of::<T>()
+	}
+	fn field_names(&self) -> Vec<&'static str> {
+		T::FIELDS.iter().map(|field| field.name).collect()
+	}
+	unsafe fn init(&self, data: *mut c_void) {
+		ptr::write(data as *mut T, T::default());
+	}
+	unsafe fn clear(&self, data: *mut c_void) {
+		// Left default initialized so that the record can be cleared or destroyed again.
+		ptr::drop_in_place(data as *mut T);
+		ptr::write(data as *mut T, T::default());
+	}
+	unsafe fn copy(&self, src: *const c_void, dest: *mut c_void) {
+		// The destination is overwritten like by `init`, it is not dropped.
+		ptr::write(dest as *mut T, (*(src as *const T)).clone());
+	}
+	fn create(&self) -> *mut c_void {
+		Box::into_raw(Box::<T>::default()) as *mut c_void
+	}
+	unsafe fn create_copy(&self, src: *const c_void) -> *mut c_void {
+		Box::into_raw(Box::new((*(src as *const T)).clone())) as *mut c_void
+	}
+	unsafe fn destroy(&self, data: *mut c_void) {
+		drop(Box::from_raw(data as *mut T));
+	}
+	fn field(&self, name: &str) -> Option<usize> {
+		T::FIELDS.iter().position(|field| field.name == name)
+	}
+	unsafe fn get_field(&self, data: *const c_void, index: usize) -> Variant<'static> {
+		(T::FIELDS[index].get)(&*(data as *const T))
+	}
+	unsafe fn put_field(&self, data: *mut c_void, index: usize, value: Variant<'static>) -> Result<(), Variant<'static>> {
+		(T::FIELDS[index].put)(&mut *(data as *mut T), value)
+	}
+}
+
+/// Record info implemented from a `RecordType`.
+///
+/// Records created by `RecordCreate` are boxed, records in memory provided by the caller are initialized in place.
+struct RecordInfo(Box<dyn Layout>);
+
+com_object! {
+	impl RecordInfo {
+		IRecordInfo(IRecordInfoVtbl {
+			base: IUnknownVtbl,
+			RecordInit: record_init,
+			RecordClear: record_clear,
+			RecordCopy: record_copy,
+			GetGuid: get_guid,
+			GetName: get_name,
+			GetSize: get_size,
+			GetTypeInfo: get_type_info,
+			GetField: get_field,
+			GetFieldNoCopy: get_field_no_copy,
+			PutField: put_field,
+			PutFieldNoCopy: put_field_no_copy,
+			GetFieldNames: get_field_names,
+			IsMatchingType: is_matching_type,
+			RecordCreate: record_create,
+			RecordCreateCopy: record_create_copy,
+			RecordDestroy: record_destroy,
+		}),
+	}
+}
+
+unsafe fn layout<'ub>(this: *mut IRecordInfo) -> &'ub dyn Layout {
+	&*ComBox::<RecordInfo>::from_interface(this).0
+}
+/// Returns the layout of a record info derived from a `RecordType`, recognized by its virtual functions.
+unsafe fn derived_layout<'ub>(info: *mut IRecordInfo) -> Option<&'ub dyn Layout> {
+	let create: unsafe extern "system" fn(*mut IRecordInfo) -> *mut c_void = record_create;
+	if (*info).vtbl().RecordCreate as usize == create as usize {
+		Some(layout(info))
+	}
+	else {
+		None
+	}
+}
+unsafe fn field_name(name: LPCOLESTR) -> String {
+	let len = (0..).take_while(|&i| *name.add(i) != 0).count();
+	String::from_utf16_lossy(slice::from_raw_parts(name, len))
+}
+
+unsafe extern "system" fn record_init(this: *mut IRecordInfo, data: *mut c_void) -> HRESULT {
+	if data.is_null() {
+		return code::E_INVALIDARG.into();
+	}
+	layout(this).init(data);
+	code::S_OK.into()
+}
+unsafe extern "system" fn record_clear(this: *mut IRecordInfo, data: *mut c_void) -> HRESULT {
+	if data.is_null() {
+		return code::E_INVALIDARG.into();
+	}
+	layout(this).clear(data);
+	code::S_OK.into()
+}
+unsafe extern "system" fn record_copy(this: *mut IRecordInfo, src: *mut c_void, dest: *mut c_void) -> HRESULT {
+	if src.is_null() || dest.is_null() {
+		return code::E_INVALIDARG.into();
+	}
+	layout(this).copy(src, dest);
+	code::S_OK.into()
+}
+unsafe extern "system" fn get_guid(this: *mut IRecordInfo, guid: *mut GUID) -> HRESULT {
+	if guid.is_null() {
+		return code::E_INVALIDARG.into();
+	}
+	*guid = layout(this).guid().into();
+	code::S_OK.into()
+}
+unsafe extern "system" fn get_name(this: *mut IRecordInfo, name: *mut BSTR) -> HRESULT {
+	if name.is_null() {
+		return code::E_INVALIDARG.into();
+	}
+	*name = BString::from(layout(this).name()).into_raw();
+	code::S_OK.into()
+}
+unsafe extern "system" fn get_size(this: *mut IRecordInfo, size: *mut ULONG) -> HRESULT {
+	if size.is_null() {
+		return code::E_INVALIDARG.into();
+	}
+	*size = layout(this).size() as ULONG;
+	code::S_OK.into()
+}
+/// Records derived from Rust types provide no type information.
+unsafe extern "system" fn get_type_info(_this: *mut IRecordInfo, type_info: *mut *mut ::com_sys::unknown::IUnknown) -> HRESULT {
+	if !type_info.is_null() {
+		*type_info = ptr::null_mut();
+	}
+	code::E_NOTIMPL.into()
+}
+unsafe extern "system" fn get_field(this: *mut IRecordInfo, data: *mut c_void, name: LPCOLESTR, value: *mut VARIANT) -> HRESULT {
+	if data.is_null() || name.is_null() || value.is_null() {
+		return code::E_INVALIDARG.into();
+	}
+	let layout = layout(this);
+	match layout.field(&field_name(name)) {
+		Some(index) => {
+			// Clears the previous value.
+			*(value as *mut Variant) = layout.get_field(data, index);
+			code::S_OK.into()
+		},
+		None => code::TYPE_E_FIELDNOTFOUND.into(),
+	}
+}
+/// Fields are not exposed by reference, the caller would not know when to stop using them.
+unsafe extern "system" fn get_field_no_copy(_this: *mut IRecordInfo, _data: *mut c_void, _name: LPCOLESTR, _value: *mut VARIANT, _data_array: *mut *mut c_void) -> HRESULT {
+	code::E_NOTIMPL.into()
+}
+unsafe extern "system" fn put_field(this: *mut IRecordInfo, _flags: ULONG, data: *mut c_void, name: LPCOLESTR, value: *mut VARIANT) -> HRESULT {
+	if data.is_null() || name.is_null() || value.is_null() {
+		return code::E_INVALIDARG.into();
+	}
+	let layout = layout(this);
+	let index = match layout.field(&field_name(name)) {
+		Some(index) => index,
+		None => return code::TYPE_E_FIELDNOTFOUND.into(),
+	};
+	let field = match (*(value as *const Variant)).try_clone_ind() {
+		Ok(field) => field,
+		Err(hr) => return hr.into(),
+	};
+	match layout.put_field(data, index, field) {
+		Ok(()) => code::S_OK.into(),
+		Err(_) => code::DISP_E_TYPEMISMATCH.into(),
+	}
+}
+/// Moves the value into the field, the caller's variant is left empty on success.
+unsafe extern "system" fn put_field_no_copy(this: *mut IRecordInfo, _flags: ULONG, data: *mut c_void, name: LPCOLESTR, value: *mut VARIANT) -> HRESULT {
+	if data.is_null() || name.is_null() || value.is_null() {
+		return code::E_INVALIDARG.into();
+	}
+	let layout = layout(this);
+	let index = match layout.field(&field_name(name)) {
+		Some(index) => index,
+		None => return code::TYPE_E_FIELDNOTFOUND.into(),
+	};
+	let field = Variant::from_raw(ptr::replace(value, Variant::new().into_raw()));
+	match layout.put_field(data, index, field) {
+		Ok(()) => code::S_OK.into(),
+		Err(field) => {
+			// Hands the value back to the caller.
+			ptr::write(value, field.into_raw());
+			code::DISP_E_TYPEMISMATCH.into()
+		},
+	}
+}
+unsafe extern "system" fn get_field_names(this: *mut IRecordInfo, count: *mut ULONG, names: *mut BSTR) -> HRESULT {
+	if count.is_null() {
+		return code::E_INVALIDARG.into();
+	}
+	let fields = layout(this).field_names();
+	if !names.is_null() {
+		// Fills in as many names as fit.
+		let names = slice::from_raw_parts_mut(names, *count as usize);
+		for (name, field) in names.iter_mut().zip(&fields) {
+			*name = BString::from(field).into_raw();
+		}
+		*count = names.len().min(fields.len()) as ULONG;
+	}
+	else {
+		*count = fields.len() as ULONG;
+	}
+	code::S_OK.into()
+}
+unsafe extern "system" fn is_matching_type(this: *mut IRecordInfo, other: *mut IRecordInfo) -> BOOL {
+	if other.is_null() {
+		return 0;
+	}
+	let other = ManuallyDrop::new(RecordInfoPtr::from_ptr(other));
+	(other.guid().ok() == Some(layout(this).guid())) as BOOL
+}
+unsafe extern "system" fn record_create(this: *mut IRecordInfo) -> *mut c_void {
+	layout(this).create()
+}
+unsafe extern "system" fn record_create_copy(this: *mut IRecordInfo, src: *mut c_void, dest: *mut *mut c_void) -> HRESULT {
+	if src.is_null() || dest.is_null() {
+		return code::E_INVALIDARG.into();
+	}
+	*dest = layout(this).create_copy(src);
+	code::S_OK.into()
+}
+unsafe extern "system" fn record_destroy(this: *mut IRecordInfo, data: *mut c_void) -> HRESULT {
+	if !data.is_null() {
+		layout(this).destroy(data);
+	}
+	code::S_OK.into()
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use ::leak::LeakDetector;
+	use ::variant::{types, vt};
+	use super::*;
+
+	#[derive(Clone, Debug, Default, PartialEq, RecordType)]
+	#[repr(C)]
+	#[record("5b1f6a3e-92c4-4d7b-8e05-c3a91f2d6b70")]
+	struct Person {
+		name: BString,
+		age: i32,
+	}
+
+	fn person(name: &str, age: i32) -> Person {
+		Person { name: BString::from(name), age }
+	}
+
+	#[test]
+	fn record_info() {
+		let info = Person::record_info();
+		assert_eq!(info.guid(), Ok(Person::GUID));
+		assert_eq!(info.name().unwrap(), "Person");
+		assert_eq!(info.size(), Ok(mem::size_of::<Person>()));
+		assert_eq!(info.field_names().unwrap(), [BString::from("name"), BString::from("age")]);
+		assert!(info.is_matching_type(&Person::record_info()));
+	}
+
+	#[test]
+	fn fields() {
+		let mut record = Record::new(person("Ada", 36));
+		assert_eq!(record.get_field("name").unwrap().into::<types::BString>(), "Ada");
+		assert_eq!(record.get_field("age").unwrap().into::<types::I4>(), 36);
+
+		record.put_field("age", &Variant::from(37)).unwrap();
+		// Fields are put from references too.
+		let name = BString::from("Grace");
+		record.put_field("name", &Variant::from(&name)).unwrap();
+		assert_eq!(record.downcast_ref::<Person>(), Some(&person("Grace", 37)));
+
+		assert_eq!(record.put_field("age", &Variant::from(1.5)).unwrap_err(), code::DISP_E_TYPEMISMATCH);
+		assert_eq!(record.get_field("Age").unwrap_err(), code::TYPE_E_FIELDNOTFOUND);
+		assert_eq!(record.put_field("height", &Variant::from(1)).unwrap_err(), code::TYPE_E_FIELDNOTFOUND);
+	}
+
+	#[test]
+	fn put_field_no_copy() {
+		let mut record = Record::new(person("Ada", 36));
+		let name = wide("name");
+		unsafe {
+			let mut value = Variant::from(BString::from("Grace")).into_raw();
+			let hr = com_call!(PutFieldNoCopy(record.info(), INVOKE_PROPERTYPUT, record.as_mut_ptr(), name.as_ptr(), &mut value));
+			assert_eq!(HResult::from(hr), code::S_OK);
+			assert!(Variant::from_raw(value).is::<types::Empty>());
+
+			// The rejected value is handed back.
+			let mut value = Variant::from(1).into_raw();
+			let hr = com_call!(PutFieldNoCopy(record.info(), INVOKE_PROPERTYPUT, record.as_mut_ptr(), name.as_ptr(), &mut value));
+			assert_eq!(HResult::from(hr), code::DISP_E_TYPEMISMATCH);
+			assert_eq!(Variant::from_raw(value).into::<types::I4>(), 1);
+		}
+		assert_eq!(record.downcast_ref::<Person>(), Some(&person("Grace", 36)));
+	}
+
+	#[test]
+	fn variants() {
+		let detector = LeakDetector::new();
+		let info: RecordInfoPtr = detector.track(RecordInfo(Box::new(Of::<Person>(PhantomData))));
+		let mut record = Record::create(info).unwrap();
+		*record.downcast_mut::<Person>().unwrap() = person("Ada", 36);
+
+		let var = Variant::from(record);
+		assert_eq!(var.tag(), vt::VT_RECORD);
+		assert!(format!("{:?}", var).starts_with("Variant(Record { name: Some(\"Person\")"));
+
+		// Copies are independent.
+		let copy = var.try_clone_ind().unwrap();
+		let mut record = var.into::<types::Record>();
+		record.downcast_mut::<Person>().unwrap().age += 1;
+		assert_eq!(copy.borrow::<types::Record>().downcast_ref::<Person>(), Some(&person("Ada", 36)));
+		assert_eq!(record.try_clone().unwrap().downcast_ref::<Person>(), Some(&person("Ada", 37)));
+
+		// Records by reference are copied when dereferenced.
+		unsafe {
+			let mut byref = Variant::from(record).into_raw();
+			ptr::write(&mut byref.data0 as *mut _ as *mut ::variant::Tag, vt::VT_BYREF_RECORD);
+			let deref = Variant::from_raw(byref).try_clone_ind().unwrap();
+			assert_eq!(deref.borrow::<types::Record>().downcast_ref::<Person>(), Some(&person("Ada", 37)));
+			ptr::write(&mut byref.data0 as *mut _ as *mut ::variant::Tag, vt::VT_RECORD);
+			drop(Variant::from_raw(byref));
+		}
+
+		assert!(!Record::new(Person::default()).is::<Other>());
+		// Only the type the record info was derived from matches, not its GUID and size.
+		let impostor = Record::new(Impostor::default());
+		assert_eq!(impostor.info().guid(), Ok(Person::GUID));
+		assert_eq!(impostor.info().size(), Ok(mem::size_of::<Person>()));
+		assert!(impostor.downcast_ref::<Person>().is_none());
+		assert!(impostor.is::<Impostor>());
+		drop(copy);
+		detector.assert_released();
+	}
+
+	#[derive(Clone, Default, RecordType)]
+	#[repr(C)]
+	#[record("0d9e4c1b-6f2a-4b83-a7e5-19c0b8d2f461")]
+	struct Other {
+		value: f64,
+	}
+
+	#[derive(Clone, Default, RecordType)]
+	#[repr(C)]
+	#[record("5b1f6a3e-92c4-4d7b-8e05-c3a91f2d6b70")]
+	struct Impostor {
+		name: BString,
+		age: u32,
+	}
+}
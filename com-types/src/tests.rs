@@ -0,0 +1,43 @@
+/*!
+Objects shared by the tests of several modules.
+*/
+
+use ::std::cell::Cell;
+use ::std::rc::Rc;
+use ::com_sys::unknown::{IUnknown, IUnknownVtbl};
+use ::com_sys::weakref::{IWeakReferenceSource, IWeakReferenceSourceVtbl};
+use ::weak::get_weak_reference;
+
+#[::com::com_interface("7d2e3f4a-5b6c-4d7e-8f90-a1b2c3d4e5f0")]
+#[derive(Clone)]
+pub trait ISink: IUnknown {}
+#[::com::com_interface("7d2e3f4a-5b6c-4d7e-8f90-a1b2c3d4e5f1")]
+#[derive(Clone)]
+pub trait IOtherSink: IUnknown {}
+
+/// Implements `ISink`, `IOtherSink` and `IWeakReferenceSource`, clears its flag when dropped.
+pub struct Sink(pub Rc<Cell<bool>>);
+impl Sink {
+	pub fn new() -> Sink {
+		Sink(Rc::new(Cell::new(true)))
+	}
+}
+impl Drop for Sink {
+	fn drop(&mut self) {
+		self.0.set(false);
+	}
+}
+com_object! {
+	impl Sink {
+		ISink(ISinkVtbl {
+			base: IUnknownVtbl,
+		}),
+		IOtherSink(IOtherSinkVtbl {
+			base: IUnknownVtbl,
+		}),
+		IWeakReferenceSource(IWeakReferenceSourceVtbl {
+			base: IUnknownVtbl,
+			GetWeakReference: get_weak_reference::<Sink>,
+		}),
+	}
+}
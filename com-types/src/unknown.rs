@@ -2,17 +2,21 @@
 Implements `IUnknown`.
 */
 
-use ::hr::HResult;
-use ::com::{ComResult, ComPtr};
+use ::std::{fmt, hash};
+use ::std::ops::Deref;
+
+use ::com::{AsComPtr, ComResult, ComPtr, query_interface};
 use ::com_sys::unknown::IUnknown;
-use ::com_sys::ComInterface;
+use ::winapi::GUID;
 
 com_ptr! {
 	#[derive(Debug)]
 	pub struct IUnknownPtr(IUnknown);
 }
 impl Clone for IUnknownPtr {
+	#[cfg_attr(any(test, feature = "leak"), track_caller)]
 	fn clone(&self) -> IUnknownPtr {
+		call_site!();
 		unsafe {
 			com_call!(AddRef(self));
 			IUnknownPtr(self.0)
@@ -27,11 +31,100 @@ impl Drop for IUnknownPtr {
 	}
 }
 impl IUnknownPtr {
-	pub fn query_interface<T: ComPtr>(&self) -> ComResult<T> {
+	/// Queries for an interface by its iid.
+	///
+	/// The returned pointer is the requested interface seen through its `IUnknown` base.
+	#[cfg_attr(any(test, feature = "leak"), track_caller)]
+	pub fn query_iid(&self, iid: &GUID) -> ComResult<IUnknownPtr> {
+		call_site!();
 		unsafe {
-			let mut ppv = ::std::ptr::null_mut();
-			let hr = com_call!(QueryInterface(self, <T::Interface as ComInterface>::iid(), &mut ppv));
-			HResult::result_of(hr, || T::new(ppv))
+			let ppv = query_interface(self.0, iid)?;
+			Ok(IUnknownPtr::new(ppv))
 		}
 	}
 }
+
+//----------------------------------------------------------------
+
+/// Com pointer compared and hashed by the identity of its object.
+///
+/// Holds on to the canonical `IUnknown` of the object, see [`ComPtr::identity`](../com/trait.ComPtr.html#method.identity).
+/// Use it as key in a `HashSet` or `HashMap` to tell objects apart regardless of the interface they are referenced through,
+/// eg. to deduplicate event sinks.
+#[derive(Clone)]
+pub struct ComIdentity<P: ComPtr> {
+	ptr: P,
+	unknown: IUnknownPtr,
+}
+impl<P: ComPtr> ComIdentity<P> {
+	/// Queries the identity of the com pointer's object.
+	pub fn new(ptr: P) -> ComResult<ComIdentity<P>> {
+		let unknown = ptr.identity()?;
+		Ok(ComIdentity { ptr, unknown })
+	}
+	/// Returns the canonical `IUnknown` of the object.
+	pub fn unknown(&self) -> &IUnknownPtr {
+		&self.unknown
+	}
+	/// Returns the com pointer.
+	pub fn into_inner(self) -> P {
+		self.ptr
+	}
+}
+impl<P: ComPtr> Deref for ComIdentity<P> {
+	type Target = P;
+	fn deref(&self) -> &P {
+		&self.ptr
+	}
+}
+impl<P: ComPtr, Q: ComPtr> PartialEq<ComIdentity<Q>> for ComIdentity<P> {
+	fn eq(&self, other: &ComIdentity<Q>) -> bool {
+		self.unknown.as_ptr() == other.unknown.as_ptr()
+	}
+}
+impl<P: ComPtr> Eq for ComIdentity<P> {}
+impl<P: ComPtr> hash::Hash for ComIdentity<P> {
+	fn hash<H: hash::Hasher>(&self, state: &mut H) {
+		self.unknown.as_ptr().hash(state);
+	}
+}
+impl<P: ComPtr> fmt::Debug for ComIdentity<P> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("ComIdentity").field(&self.unknown.as_ptr()).finish()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use ::std::collections::{HashMap, HashSet};
+	use ::object::ComBox;
+	use ::tests::{IOtherSinkPtr, ISinkPtr, Sink};
+	use super::*;
+
+	#[test]
+	fn identity() {
+		let sink: ISinkPtr = ComBox::new(Sink::new());
+		let other = sink.try_cast::<IOtherSinkPtr>().unwrap();
+		let unrelated: ISinkPtr = ComBox::new(Sink::new());
+		// Different interfaces of the same object have different pointers but the same identity.
+		assert_ne!(sink.as_ptr() as usize, other.as_ptr() as usize);
+		assert!(sink.is_same_object(&other));
+		assert!(other.is_same_object(&sink.identity().unwrap()));
+		assert!(!sink.is_same_object(&unrelated));
+
+		let mut sinks = HashSet::new();
+		assert!(sinks.insert(ComIdentity::new(sink.clone()).unwrap()));
+		assert!(sinks.insert(ComIdentity::new(unrelated.clone()).unwrap()));
+		assert!(!sinks.insert(ComIdentity::new(other.try_cast::<ISinkPtr>().unwrap()).unwrap()));
+		assert_eq!(sinks.len(), 2);
+		assert_eq!(ComIdentity::new(sink.clone()).unwrap(), ComIdentity::new(other.clone()).unwrap());
+
+		let mut names = HashMap::new();
+		names.insert(ComIdentity::new(other.identity().unwrap()).unwrap(), "sink");
+		assert_eq!(names.get(&ComIdentity::new(sink.identity().unwrap()).unwrap()), Some(&"sink"));
+		assert_eq!(names.get(&ComIdentity::new(unrelated.identity().unwrap()).unwrap()), None);
+
+		drop((sinks, names));
+		unsafe { assert_eq!(ComBox::<Sink>::ref_count(sink.as_ptr()), 2); }
+	}
+}
@@ -0,0 +1,491 @@
+/*!
+Portable implementation of the variant coercions for targets without oleaut32.
+
+Follows the rules of `VariantChangeType` for the invariant locale: numbers are parsed and formatted with `.` as decimal separator and `,` as thousands separator,
+integers are rounded half to even and objects are coerced through the value of their default property.
+
+Dates are only coerced to and from numbers and decimals are not supported, those conversions fail with `DISP_E_TYPEMISMATCH` and `DISP_E_BADVARTYPE`.
+*/
+
+use ::std::{fmt, f32, ptr};
+
+use ::com_sys::dispatch::{DISPID_VALUE, DISPATCH_PROPERTYGET};
+
+use ::com::ComPtr;
+use ::dispatch::DispatchPtr;
+use ::hr::{code, HResult};
+use ::unknown::IUnknownPtr;
+use ::{AsInner, FromInner};
+
+use super::{types, vt, ChangeTypeFlags, Tag, Variant};
+
+/// Range of the `DATE` type, from January 1, 100 up to but excluding January 1, 10000.
+const MIN_DATE: f64 = -657434.0;
+const MAX_DATE: f64 = 2958466.0;
+
+/// Digits kept after the decimal point when parsing numbers.
+const MAX_SCALE: u32 = 28;
+
+/// Coerces the source variant to the type of the tag.
+pub fn change_type(src: &Variant, tag: Tag, flags: ChangeTypeFlags) -> Result<Variant<'static>, HResult> {
+	if src.tag().0 & vt::VT_BYREF != 0 {
+		return change_type(&src.try_clone_ind()?, tag, flags);
+	}
+	if src.tag() == tag {
+		return src.try_clone_ind();
+	}
+	match tag {
+		vt::VT_EMPTY => return Ok(Variant::new()),
+		vt::VT_UNKNOWN => return to_com_ptr::<IUnknownPtr>(src, tag),
+		vt::VT_DISPATCH => return to_com_ptr::<DispatchPtr>(src, tag),
+		vt::VT_NULL | vt::VT_BOOL | vt::VT_BSTR | vt::VT_R4 | vt::VT_R8 | vt::VT_CY | vt::VT_DATE => (),
+		_ if int_range(tag).is_none() => return Err(code::DISP_E_BADVARTYPE),
+		_ => (),
+	}
+	if src.tag() == vt::VT_DISPATCH {
+		return value_property(src, tag, flags);
+	}
+	convert(value(src)?, tag, flags)
+}
+
+/// Queries the interface of the com pointer tagged as `VT_UNKNOWN` or `VT_DISPATCH`.
+fn to_com_ptr<P: ComPtr>(src: &Variant, tag: Tag) -> Result<Variant<'static>, HResult> {
+	match src.to_interface::<P>() {
+		Ok(Some(ptr)) => Ok(Variant::from_com_ptr(ptr)),
+		Ok(None) => {
+			// Null interface pointers stay null.
+			let mut var = Variant::new();
+			unsafe { ptr::write(var.as_mut_ptr() as *mut Tag, tag); }
+			Ok(var)
+		},
+		Err(err) => Err(err.hresult()),
+	}
+}
+
+/// Coerces the value of the object's default property, unless forbidden by `VARIANT_NOVALUEPROP`.
+fn value_property(src: &Variant, tag: Tag, flags: ChangeTypeFlags) -> Result<Variant<'static>, HResult> {
+	if flags.contains(ChangeTypeFlags::VARIANT_NOVALUEPROP) {
+		return Err(code::DISP_E_TYPEMISMATCH);
+	}
+	let dispatch = match src.to_interface::<DispatchPtr>() {
+		Ok(Some(dispatch)) => dispatch,
+		Ok(None) => return Err(code::DISP_E_TYPEMISMATCH),
+		Err(err) => return Err(err.hresult()),
+	};
+	let value = dispatch.invoke(DISPID_VALUE, DISPATCH_PROPERTYGET, &[], &[])?;
+	// The value may not be an object with a value of its own.
+	change_type(&value, tag, flags | ChangeTypeFlags::VARIANT_NOVALUEPROP)
+}
+
+//----------------------------------------------------------------
+
+/// Value of the source variant.
+enum Value {
+	Empty,
+	Null,
+	Bool(bool),
+	Number(Number),
+	Date(f64),
+	Str(String),
+}
+
+fn value(src: &Variant) -> Result<Value, HResult> {
+	let int = |i: i64| Value::Number(Number::Int(i));
+	Ok(match src.tag() {
+		vt::VT_EMPTY => Value::Empty,
+		vt::VT_NULL => Value::Null,
+		vt::VT_I1 => int(*src.borrow::<types::I1>() as i64),
+		vt::VT_UI1 => int(*src.borrow::<types::UI1>() as i64),
+		vt::VT_I2 => int(*src.borrow::<types::I2>() as i64),
+		vt::VT_UI2 => int(*src.borrow::<types::UI2>() as i64),
+		vt::VT_I4 => int(*src.borrow::<types::I4>() as i64),
+		vt::VT_UI4 => int(*src.borrow::<types::UI4>() as i64),
+		vt::VT_INT => int(*src.borrow::<types::Int>().as_inner() as i64),
+		vt::VT_UINT => int(*src.borrow::<types::UInt>().as_inner() as i64),
+		vt::VT_R4 => Value::Number(Number::R4(*src.borrow::<types::R4>())),
+		vt::VT_R8 => Value::Number(Number::R8(*src.borrow::<types::R8>())),
+		vt::VT_CY => Value::Number(Number::Fixed(Fixed { scale: 4, ..Fixed::from(*src.borrow::<types::Currency>().as_ref()) })),
+		vt::VT_DATE => Value::Date(src.borrow::<types::Date>().0),
+		vt::VT_BOOL => Value::Bool(bool::from(*src.borrow::<types::Bool>())),
+		vt::VT_BSTR => Value::Str(String::from_utf16_lossy(src.borrow::<types::BString>())),
+		_ => return Err(code::DISP_E_TYPEMISMATCH),
+	})
+}
+
+fn convert(value: Value, tag: Tag, flags: ChangeTypeFlags) -> Result<Variant<'static>, HResult> {
+	match (value, tag) {
+		(Value::Empty, vt::VT_NULL) | (Value::Null, vt::VT_NULL) => Ok(Variant::from(types::Null)),
+		(Value::Null, _) | (_, vt::VT_NULL) => Err(code::DISP_E_TYPEMISMATCH),
+		(Value::Empty, vt::VT_BSTR) => Ok(Variant::from(types::BString::new())),
+		(Value::Bool(b), vt::VT_BSTR) => {
+			let s = if flags.intersects(ChangeTypeFlags::VARIANT_ALPHABOOL | ChangeTypeFlags::VARIANT_LOCALBOOL) {
+				if b { "True" } else { "False" }
+			}
+			else {
+				if b { "-1" } else { "0" }
+			};
+			Ok(Variant::from(types::BString::from(s)))
+		},
+		(Value::Number(number), vt::VT_BSTR) => Ok(Variant::from(types::BString::from(number.to_string()))),
+		(Value::Date(_), vt::VT_BSTR) | (Value::Str(_), vt::VT_DATE) => Err(code::DISP_E_TYPEMISMATCH),
+		(Value::Str(ref s), vt::VT_BOOL) if s.trim().eq_ignore_ascii_case("true") => Ok(Variant::from(types::Bool::from(true))),
+		(Value::Str(ref s), vt::VT_BOOL) if s.trim().eq_ignore_ascii_case("false") => Ok(Variant::from(types::Bool::from(false))),
+		(value, vt::VT_BOOL) => Ok(Variant::from(types::Bool::from(!number(value, tag)?.is_zero()))),
+		(value, tag) => number(value, tag)?.to_variant(tag),
+	}
+}
+
+/// Converts the value to a number for the numeric tag.
+fn number(value: Value, tag: Tag) -> Result<Number, HResult> {
+	Ok(match value {
+		Value::Empty | Value::Bool(false) => Number::Int(0),
+		// True has all bits set, also for unsigned integers.
+		Value::Bool(true) => match int_range(tag) {
+			Some((0, max)) => Number::Int(max),
+			_ => Number::Int(-1),
+		},
+		Value::Number(number) => number,
+		Value::Date(date) => Number::R8(date),
+		Value::Str(s) => parse(&s)?,
+		Value::Null => return Err(code::DISP_E_TYPEMISMATCH),
+	})
+}
+
+/// Range of the integer types.
+fn int_range(tag: Tag) -> Option<(i64, i64)> {
+	Some(match tag {
+		vt::VT_I1 => (i8::MIN as i64, i8::MAX as i64),
+		vt::VT_UI1 => (0, u8::MAX as i64),
+		vt::VT_I2 => (i16::MIN as i64, i16::MAX as i64),
+		vt::VT_UI2 => (0, u16::MAX as i64),
+		vt::VT_I4 | vt::VT_INT => (i32::MIN as i64, i32::MAX as i64),
+		vt::VT_UI4 | vt::VT_UINT => (0, u32::MAX as i64),
+		_ => return None,
+	})
+}
+
+//----------------------------------------------------------------
+
+/// Numeric value of the source variant.
+enum Number {
+	Int(i64),
+	R4(f32),
+	R8(f64),
+	Fixed(Fixed),
+}
+
+impl Number {
+	fn is_zero(&self) -> bool {
+		match *self {
+			Number::Int(i) => i == 0,
+			Number::R4(f) => f == 0.0,
+			Number::R8(f) => f == 0.0,
+			Number::Fixed(ref fixed) => fixed.mantissa == 0,
+		}
+	}
+	fn to_f64(&self) -> f64 {
+		match *self {
+			Number::Int(i) => i as f64,
+			Number::R4(f) => f as f64,
+			Number::R8(f) => f,
+			Number::Fixed(ref fixed) => fixed.to_f64(),
+		}
+	}
+	/// Rounds half to even, `None` if out of range.
+	fn to_i64(&self) -> Option<i64> {
+		match *self {
+			Number::Int(i) => Some(i),
+			Number::R4(f) => float_to_i64(f as f64),
+			Number::R8(f) => float_to_i64(f),
+			Number::Fixed(ref fixed) => fixed.scaled(0).unscaled(),
+		}
+	}
+	/// Converts to fixed point, `None` if out of range.
+	fn to_fixed(&self) -> Option<Fixed> {
+		match *self {
+			Number::Int(i) => Some(Fixed::from(i)),
+			Number::R4(f) => Fixed::from_float(f as f64, 7),
+			Number::R8(f) => Fixed::from_float(f, 15),
+			Number::Fixed(ref fixed) => Some(fixed.clone()),
+		}
+	}
+	fn to_variant(&self, tag: Tag) -> Result<Variant<'static>, HResult> {
+		Ok(match tag {
+			vt::VT_R4 => {
+				let f = self.to_f64();
+				if f.is_finite() && f.abs() > f32::MAX as f64 {
+					return Err(code::DISP_E_OVERFLOW);
+				}
+				Variant::from(f as f32)
+			},
+			vt::VT_R8 => Variant::from(self.to_f64()),
+			vt::VT_DATE => {
+				let f = self.to_f64();
+				if !(MIN_DATE..MAX_DATE).contains(&f) {
+					return Err(code::DISP_E_OVERFLOW);
+				}
+				Variant::from(types::Date::from(f))
+			},
+			vt::VT_CY => {
+				let cy = self.to_fixed().and_then(|fixed| fixed.scaled(4).unscaled()).ok_or(code::DISP_E_OVERFLOW)?;
+				Variant::from(types::Currency::from(cy))
+			},
+			_ => {
+				let (min, max) = int_range(tag).ok_or(code::DISP_E_BADVARTYPE)?;
+				let i = match self.to_i64() {
+					Some(i) if i >= min && i <= max => i,
+					_ => return Err(code::DISP_E_OVERFLOW),
+				};
+				match tag {
+					vt::VT_I1 => Variant::from(i as i8),
+					vt::VT_UI1 => Variant::from(i as u8),
+					vt::VT_I2 => Variant::from(i as i16),
+					vt::VT_UI2 => Variant::from(i as u16),
+					vt::VT_I4 => Variant::from(i as i32),
+					vt::VT_UI4 => Variant::from(i as u32),
+					vt::VT_INT => Variant::from(unsafe { types::Int::from_inner(i as i32) }),
+					_ => Variant::from(unsafe { types::UInt::from_inner(i as u32) }),
+				}
+			},
+		})
+	}
+}
+
+impl fmt::Display for Number {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Number::Int(i) => i.fmt(f),
+			Number::R4(r4) => f.write_str(&format_float(r4 as f64, 7)),
+			Number::R8(r8) => f.write_str(&format_float(r8, 15)),
+			Number::Fixed(ref fixed) => fixed.fmt(f),
+		}
+	}
+}
+
+/// Rounds half to even, `None` if out of range.
+fn float_to_i64(f: f64) -> Option<i64> {
+	let rounded = if (f - f.trunc()).abs() == 0.5 { 2.0 * (f / 2.0).round() } else { f.round() };
+	if (-9223372036854775808.0..9223372036854775808.0).contains(&rounded) {
+		Some(rounded as i64)
+	}
+	else {
+		None
+	}
+}
+
+/// Formats like `%G` with the number of significant digits.
+fn format_float(f: f64, digits: usize) -> String {
+	if f == 0.0 || !f.is_finite() {
+		return f.to_string();
+	}
+	let sci = format!("{:.*e}", digits - 1, f);
+	let (mantissa, exp) = sci.split_at(sci.find('e').unwrap());
+	let exp: i32 = exp[1..].parse().unwrap();
+	if exp < -5 || exp >= digits as i32 {
+		format!("{}E{}{:02}", trim_fraction(mantissa), if exp < 0 { '-' } else { '+' }, exp.abs())
+	}
+	else {
+		trim_fraction(&format!("{:.*}", (digits as i32 - 1 - exp) as usize, f)).to_owned()
+	}
+}
+
+fn trim_fraction(s: &str) -> &str {
+	if s.contains('.') { s.trim_end_matches('0').trim_end_matches('.') } else { s }
+}
+
+/// Parses a number, either hexadecimal `&H` and octal `&O` integers or decimals with optional thousands separators and exponent.
+fn parse(s: &str) -> Result<Number, HResult> {
+	let s = s.trim();
+	let radix = match s.get(..2) {
+		Some(prefix) if prefix.eq_ignore_ascii_case("&H") => 16,
+		Some(prefix) if prefix.eq_ignore_ascii_case("&O") => 8,
+		_ => 10,
+	};
+	if radix != 10 {
+		let digits = &s[2..];
+		if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+			return Err(code::DISP_E_TYPEMISMATCH);
+		}
+		return i64::from_str_radix(digits, radix).map(Number::Int).map_err(|_| code::DISP_E_OVERFLOW);
+	}
+	let s: String = s.chars().filter(|&c| c != ',').collect();
+	if !is_decimal(&s) {
+		return Err(code::DISP_E_TYPEMISMATCH);
+	}
+	match Fixed::parse(&s) {
+		Some(fixed) => Ok(match fixed.unscaled() {
+			Some(i) if fixed.scale == 0 => Number::Int(i),
+			_ => Number::Fixed(fixed),
+		}),
+		// Too many digits for fixed point.
+		None => match s.parse::<f64>() {
+			Ok(f) if f.is_finite() => Ok(Number::R8(f)),
+			_ => Err(code::DISP_E_OVERFLOW),
+		},
+	}
+}
+
+/// Returns if the string is an optionally signed decimal with optional exponent.
+fn is_decimal(s: &str) -> bool {
+	let s = s.strip_prefix(['+', '-']).unwrap_or(s);
+	let (digits, exp) = match s.find(['e', 'E']) {
+		Some(i) => (&s[..i], Some(&s[i + 1..])),
+		None => (s, None),
+	};
+	let mut parts = digits.splitn(2, '.');
+	let int = parts.next().unwrap_or("");
+	let fraction = parts.next().unwrap_or("");
+	let all_digits = |s: &str| s.bytes().all(|c| c.is_ascii_digit());
+	!(int.is_empty() && fraction.is_empty())
+		&& all_digits(int) && all_digits(fraction)
+		&& match exp {
+			Some(exp) => {
+				let exp = exp.strip_prefix(['+', '-']).unwrap_or(exp);
+				!exp.is_empty() && all_digits(exp)
+			},
+			None => true,
+		}
+}
+
+//----------------------------------------------------------------
+
+/// Exact decimal fixed point number.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Fixed {
+	negative: bool,
+	mantissa: u128,
+	scale: u32,
+}
+
+impl From<i64> for Fixed {
+	fn from(i: i64) -> Fixed {
+		Fixed { negative: i < 0, mantissa: i.unsigned_abs() as u128, scale: 0 }
+	}
+}
+
+impl Fixed {
+	/// Parses a decimal, `None` if it does not fit.
+	///
+	/// The string must be validated with `is_decimal`.
+	fn parse(s: &str) -> Option<Fixed> {
+		let (negative, s) = match s.as_bytes()[0] {
+			b'-' => (true, &s[1..]),
+			b'+' => (false, &s[1..]),
+			_ => (false, s),
+		};
+		let (digits, exp) = match s.find(['e', 'E']) {
+			Some(i) => (&s[..i], s[i + 1..].parse::<i32>().ok()?),
+			None => (s, 0),
+		};
+		let mut mantissa = 0u128;
+		let mut scale = 0i64;
+		let mut fraction = false;
+		for c in digits.bytes() {
+			if c == b'.' {
+				fraction = true;
+				continue;
+			}
+			mantissa = mantissa.checked_mul(10)?.checked_add((c - b'0') as u128)?;
+			if fraction {
+				scale += 1;
+			}
+		}
+		scale -= exp as i64;
+		if scale < 0 {
+			mantissa = mantissa.checked_mul(10u128.checked_pow((-scale) as u32)?)?;
+			scale = 0;
+		}
+		let fixed = Fixed { negative, mantissa, scale: scale as u32 };
+		Some(if fixed.scale > MAX_SCALE { fixed.scaled(MAX_SCALE) } else { fixed })
+	}
+	/// Converts the float rounded to significant digits, `None` if it does not fit.
+	fn from_float(f: f64, digits: usize) -> Option<Fixed> {
+		if !f.is_finite() {
+			return None;
+		}
+		Fixed::parse(&format!("{:.*e}", digits - 1, f))
+	}
+	/// Changes the scale, rounding half to even when it decreases.
+	///
+	/// The mantissa saturates if it does not fit when the scale increases.
+	fn scaled(&self, scale: u32) -> Fixed {
+		let mantissa = if scale >= self.scale {
+			10u128.checked_pow(scale - self.scale).and_then(|factor| self.mantissa.checked_mul(factor)).unwrap_or(u128::MAX)
+		}
+		else {
+			match 10u128.checked_pow(self.scale - scale) {
+				Some(divisor) => {
+					let (quotient, remainder) = (self.mantissa / divisor, self.mantissa % divisor);
+					let half = divisor / 2;
+					if remainder > half || remainder == half && quotient % 2 == 1 { quotient + 1 } else { quotient }
+				},
+				// The divisor exceeds any mantissa by more than half.
+				None => 0,
+			}
+		};
+		Fixed { negative: self.negative, mantissa, scale }
+	}
+	/// Returns the mantissa with its sign, `None` if it does not fit.
+	fn unscaled(&self) -> Option<i64> {
+		if self.negative {
+			if self.mantissa <= 1u128 << 63 { Some((self.mantissa as i128).wrapping_neg() as i64) } else { None }
+		}
+		else {
+			if self.mantissa <= i64::MAX as u128 { Some(self.mantissa as i64) } else { None }
+		}
+	}
+	fn to_f64(&self) -> f64 {
+		// The formatted number always parses.
+		self.to_string().parse().unwrap_or(0.0)
+	}
+}
+
+impl fmt::Display for Fixed {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let digits = format!("{:01$}", self.mantissa, self.scale as usize + 1);
+		let (int, fraction) = digits.split_at(digits.len() - self.scale as usize);
+		let fraction = fraction.trim_end_matches('0');
+		if self.negative && self.mantissa != 0 {
+			f.write_str("-")?;
+		}
+		f.write_str(int)?;
+		if !fraction.is_empty() {
+			write!(f, ".{}", fraction)?;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn fixed() {
+		assert_eq!(Fixed::parse("-12.50e1"), Some(Fixed { negative: true, mantissa: 1250, scale: 1 }));
+		assert_eq!(Fixed::parse("1.25").unwrap().scaled(1).to_string(), "1.2");
+		assert_eq!(Fixed::parse("1.35").unwrap().scaled(1).to_string(), "1.4");
+		assert_eq!(Fixed::parse("-0.5").unwrap().scaled(0).unscaled(), Some(0));
+		assert_eq!(Fixed::parse("1e-40").unwrap().to_string(), "0");
+		assert_eq!(Fixed::parse("1e40"), None);
+		assert_eq!(Fixed::from(i64::MIN).unscaled(), Some(i64::MIN));
+		assert_eq!(Fixed::from_float(0.1f32 as f64, 7).unwrap().to_string(), "0.1");
+	}
+	#[test]
+	fn floats() {
+		assert_eq!(format_float(1.5, 15), "1.5");
+		assert_eq!(format_float(-0.1, 15), "-0.1");
+		assert_eq!(format_float(1e20, 15), "1E+20");
+		assert_eq!(format_float(1.25e-7, 15), "1.25E-07");
+		assert_eq!(format_float(123456789.0, 7), "1.234568E+08");
+		assert_eq!(format_float(0.1f32 as f64, 7), "0.1");
+		assert_eq!(float_to_i64(2.5), Some(2));
+		assert_eq!(float_to_i64(-3.5), Some(-4));
+		assert_eq!(float_to_i64(1e19), None);
+	}
+	#[test]
+	fn decimals() {
+		assert!(is_decimal("1") && is_decimal("-1.") && is_decimal(".5e+3"));
+		assert!(!is_decimal("") && !is_decimal(".") && !is_decimal("1e") && !is_decimal("--1") && !is_decimal("inf") && !is_decimal("1.2.3"));
+	}
+}
@@ -117,3 +117,50 @@ macro_rules! impl_type_ref_native {
 		}
 	}
 }
+
+/// Implement `Type` for com pointers, the variant holds a reference to the possibly null interface pointer.
+macro_rules! impl_type_com_ptr {
+	($vt:ident, $ptr:ty, $iface:ty) => {
+		unsafe impl<'a> Type<'a> for $ptr {
+			fn variant_tag() -> Tag { vt::$vt }
+			type Raw = *mut $iface;
+			type Borrowed = Option<::rc::ComRc<$iface>>;
+			type Owned = Option<$ptr>;
+			unsafe fn variant_borrow<'s>(var: &'s Variant<'a>) -> &'s Option<::rc::ComRc<$iface>> {
+				&*(Self::variant_raw_borrow(var) as *const _ as *const Option<::rc::ComRc<$iface>>)
+			}
+			unsafe fn variant_into(var: Variant<'a>) -> Option<$ptr> {
+				let raw = Self::variant_raw_into(var);
+				if raw.is_null() { None } else { Some(<$ptr as ::com::ComPtr>::from_ptr(raw)) }
+			}
+			unsafe fn variant_from(self) -> Variant<'a> {
+				let raw = ::com::AsComPtr::as_ptr(&self);
+				::std::mem::forget(self);
+				Self::variant_raw_from(raw)
+			}
+		}
+	}
+}
+
+macro_rules! impl_type_ref_com_ptr {
+	($ptr:ty, $iface:ty) => {
+		unsafe impl<'a> Type<'a> for &'a $ptr {
+			fn variant_tag() -> Tag {
+				Tag(vt::VT_BYREF | <$ptr as Type>::variant_tag().0)
+			}
+			type Raw = *const *mut $iface;
+			type Borrowed = Option<::rc::ComRc<$iface>>;
+			type Owned = &'a Option<::rc::ComRc<$iface>>;
+			unsafe fn variant_borrow<'s>(var: &'s Variant<'a>) -> &'s Option<::rc::ComRc<$iface>> {
+				&*(*Self::variant_raw_borrow(var) as *const Option<::rc::ComRc<$iface>>)
+			}
+			unsafe fn variant_into(var: Variant<'a>) -> &'a Option<::rc::ComRc<$iface>> {
+				&*(Self::variant_raw_into(var) as *const Option<::rc::ComRc<$iface>>)
+			}
+			unsafe fn variant_from(self) -> Variant<'a> {
+				// Com pointers are `#[repr(transparent)]` raw interface pointers.
+				Self::variant_raw_from(self as *const $ptr as *const *mut $iface)
+			}
+		}
+	}
+}
@@ -4,10 +4,16 @@ Idiomatic Rust bindings for COM `VARIANT`.
 
 use ::std::{fmt, ptr, mem};
 use ::std::marker::PhantomData;
+use ::std::mem::ManuallyDrop;
 
-use ::winapi::{VARIANT};
+use ::com_sys::ComInterface;
+use ::com_sys::dispatch::IDispatch;
+use ::com_sys::unknown::IUnknown;
+use ::winapi::{VARIANT, LCID};
 
-use ::hr::HResult;
+use ::com::{query_interface, AsComPtr, CastError, ComPtr};
+use ::hr::{code, HResult};
+use ::rc::ComRc;
 
 #[macro_use]
 mod macros;
@@ -15,7 +21,10 @@ mod macros;
 pub mod types;
 
 pub mod sys;
-use self::sys::{VariantInit, VariantClear, VariantCopy, VariantCopyInd};
+use self::sys::{VariantInit, VariantClear, VariantCopy, VariantCopyInd, VariantChangeType, VariantChangeTypeEx};
+
+#[cfg(not(windows))]
+mod coerce;
 
 //----------------------------------------------------------------
 
@@ -34,9 +43,32 @@ impl From<ElemTag> for Tag {
 	}
 }
 
+bitflags! {
+	/// Flags controlling the coercions of [`Variant::change_type_ex`](struct.Variant.html#method.change_type_ex).
+	pub struct ChangeTypeFlags: u16 {
+		/// Fails to coerce objects instead of coercing the value of their default property.
+		const VARIANT_NOVALUEPROP = 0x01;
+		/// Converts booleans to the strings `"True"` and `"False"` rather than `"-1"` and `"0"`.
+		const VARIANT_ALPHABOOL = 0x02;
+		/// Ignores the user's overrides of the locale's settings when converting to and from strings.
+		const VARIANT_NOUSEROVERRIDE = 0x04;
+		/// Uses the Hijri calendar for dates.
+		const VARIANT_CALENDAR_HIJRI = 0x08;
+		/// Converts booleans to strings in the language of the locale.
+		const VARIANT_LOCALBOOL = 0x10;
+		/// Uses the Thai calendar for dates.
+		const VARIANT_CALENDAR_THAI = 0x20;
+		/// Uses the Gregorian calendar for dates.
+		const VARIANT_CALENDAR_GREGORIAN = 0x40;
+		/// Uses the number formatting of the locale when converting to strings.
+		const VARIANT_USE_NLS = 0x80;
+	}
+}
+
 //----------------------------------------------------------------
 
 /// COM `VARIANT`.
+#[repr(transparent)]
 pub struct Variant<'v>(VARIANT, PhantomData<&'v ()>);
 impl<'v> Variant<'v> {
 	/// Returns a new `Empty` variant.
@@ -48,12 +80,16 @@ impl<'v> Variant<'v> {
 	/// See [MSDN](https://msdn.microsoft.com/en-us/library/windows/desktop/ms221402.aspx).
 	pub fn init() -> Variant<'v> {
 		unsafe {
-			let mut var = Variant(mem::uninitialized(), PhantomData);
+			let mut var = Variant(mem::zeroed(), PhantomData);
 			VariantInit(&mut var.0);
 			var
 		}
 	}
 	/// Takes ownership of a raw `VARIANT`.
+	///
+	/// # Safety
+	///
+	/// The variant must be initialized and its contents not owned by anything else.
 	pub unsafe fn from_raw<'ub>(raw: VARIANT) -> Variant<'ub> {
 		Variant(raw, PhantomData)
 	}
@@ -85,7 +121,7 @@ impl<'v> Variant<'v> {
 	/// Tries to create a copy of the variant.
 	///
 	/// See [MSDN](https://msdn.microsoft.com/en-us/library/windows/desktop/ms221697.aspx).
-	pub fn try_clone(&self) -> Result<Variant, HResult> {
+	pub fn try_clone(&self) -> Result<Variant<'_>, HResult> {
 		unsafe {
 			let mut dest = Variant::init().into_raw();
 			let hr = VariantCopy(&mut dest, &self.0);
@@ -140,6 +176,7 @@ impl<'v> Variant<'v> {
 	/// Returns a reference as the specified type to the underlying data.
 	///
 	/// Panics if the variant's tag is not correct for the type.
+	#[allow(clippy::should_implement_trait)]
 	pub fn borrow<T: Type<'v>>(&self) -> &T::Borrowed {
 		assert_eq!(T::variant_tag(), self.tag());
 		unsafe { T::variant_borrow(self) }
@@ -160,6 +197,68 @@ impl<'v> Variant<'v> {
 		assert_eq!(T::variant_tag(), self.tag());
 		unsafe { T::variant_into(self) }
 	}
+	/// Creates a variant holding the com pointer.
+	///
+	/// Tagged `VT_DISPATCH` if its interface derives from `IDispatch`, otherwise `VT_UNKNOWN`.
+	pub fn from_com_ptr<P: ComPtr>(ptr: P) -> Variant<'v> {
+		let raw = ManuallyDrop::new(ptr).as_ptr();
+		unsafe {
+			if <P::Interface as ComInterface>::matches_iid(IDispatch::iid()) {
+				<types::Dispatch as Type>::variant_raw_from(raw as *mut IDispatch)
+			}
+			else {
+				<types::Unknown as Type>::variant_raw_from(raw as *mut IUnknown)
+			}
+		}
+	}
+	/// Queries the interface pointer held by the variant for the com pointer `P`.
+	///
+	/// The variant must be tagged `VT_UNKNOWN` or `VT_DISPATCH`, or their by reference forms, otherwise fails with `DISP_E_TYPEMISMATCH`.
+	/// Returns `Ok(None)` if the interface pointer is null.
+	fn to_interface<P: ComPtr>(&self) -> Result<Option<P>, CastError> {
+		let unknown = match self.tag() {
+			vt::VT_UNKNOWN => interface(self.borrow::<types::Unknown>()),
+			vt::VT_DISPATCH => interface(self.borrow::<types::Dispatch>()) as *mut IUnknown,
+			vt::VT_BYREF_UNKNOWN => interface(self.borrow::<&types::Unknown>()),
+			vt::VT_BYREF_DISPATCH => interface(self.borrow::<&types::Dispatch>()) as *mut IUnknown,
+			_ => return Err(CastError::Failed(code::DISP_E_TYPEMISMATCH)),
+		};
+		if unknown.is_null() {
+			return Ok(None);
+		}
+		unsafe { query_interface(unknown, <P::Interface as ComInterface>::iid()).map(|ppv| Some(P::new(ppv))) }
+	}
+	/// Tries to coerce the variant to the type of the tag, dereferencing if necessary.
+	///
+	/// Coerces like COM servers do, eg. parsing numbers from strings and getting the value of objects.
+	/// Fails with `DISP_E_TYPEMISMATCH` if the types cannot be coerced and with `DISP_E_OVERFLOW` if the value does not fit.
+	///
+	/// See [MSDN](https://msdn.microsoft.com/en-us/library/windows/desktop/ms221258.aspx).
+	pub fn change_type(&self, tag: Tag) -> Result<Variant<'static>, HResult> {
+		unsafe {
+			let mut dest = Variant::init().into_raw();
+			let hr = VariantChangeType(&mut dest, &self.0, 0, tag.0);
+			HResult::result_of(hr, || Variant::from_raw(dest))
+		}
+	}
+	/// Tries to coerce the variant to the type of the tag, converting to and from strings for the locale.
+	///
+	/// See [MSDN](https://msdn.microsoft.com/en-us/library/windows/desktop/ms221634.aspx).
+	pub fn change_type_ex(&self, tag: Tag, lcid: LCID, flags: ChangeTypeFlags) -> Result<Variant<'static>, HResult> {
+		unsafe {
+			let mut dest = Variant::init().into_raw();
+			let hr = VariantChangeTypeEx(&mut dest, &self.0, lcid, flags.bits(), tag.0);
+			HResult::result_of(hr, || Variant::from_raw(dest))
+		}
+	}
+	/// Tries to coerce the variant to the specified `T`ype, see [`change_type`](#method.change_type).
+	pub fn to<T: Type<'static>>(&self) -> Result<T::Owned, HResult> {
+		self.change_type(T::variant_tag()).map(Variant::into::<T>)
+	}
+	/// Tries to coerce the variant to the specified `T`ype for the locale, see [`change_type_ex`](#method.change_type_ex).
+	pub fn to_ex<T: Type<'static>>(&self, lcid: LCID, flags: ChangeTypeFlags) -> Result<T::Owned, HResult> {
+		self.change_type_ex(T::variant_tag(), lcid, flags).map(Variant::into::<T>)
+	}
 }
 impl<'v> Drop for Variant<'v> {
 	fn drop(&mut self) {
@@ -188,10 +287,10 @@ impl<'v> fmt::Debug for Variant<'v> {
 			vt::VT_CY => write!(f, "Variant({:?})", self.borrow::<types::Currency>()),
 			vt::VT_DATE => write!(f, "Variant({:?})", self.borrow::<types::Date>()),
 			vt::VT_BSTR => write!(f, "Variant({:?})", &self.borrow::<types::BString>()),
-			// vt::VT_DISPATCH => write!(f, "Variant(Dispatch({:?}))", ),
+			vt::VT_DISPATCH => write!(f, "Variant(Dispatch({:?}))", interface(self.borrow::<types::Dispatch>())),
 			vt::VT_ERROR => write!(f, "Variant(Error({:?}))", self.borrow::<types::Error>()),
 			vt::VT_BOOL => write!(f, "Variant({:?})", self.borrow::<types::Bool>()),
-			// vt::VT_UNKNOWN => write!(f, "Variant(Unknown({:?}))", ),
+			vt::VT_UNKNOWN => write!(f, "Variant(Unknown({:?}))", interface(self.borrow::<types::Unknown>())),
 			vt::VT_DECIMAL => write!(f, "Variant({:?})", self.borrow::<types::Decimal>()),
 			vt::VT_I1 => write!(f, "Variant(I1({:?}))", self.borrow::<types::I1>()),
 			vt::VT_UI1 => write!(f, "Variant(UI1({:?}))", self.borrow::<types::UI1>()),
@@ -199,7 +298,7 @@ impl<'v> fmt::Debug for Variant<'v> {
 			vt::VT_UI4 => write!(f, "Variant(UI4({:?}))", self.borrow::<types::UI4>()),
 			vt::VT_INT => write!(f, "Variant(Int({:?}))", self.borrow::<types::Int>()),
 			vt::VT_UINT => write!(f, "Variant(UInt({:?}))", self.borrow::<types::UInt>()),
-			// vt::VT_RECORD => f.field("record", ),
+			vt::VT_RECORD => write!(f, "Variant({:?})", self.borrow::<types::Record>()),
 			vt::VT_BYREF_I2 => write!(f, "Variant(&I2({:?}))", self.borrow::<&types::I2>()),
 			vt::VT_BYREF_I4 => write!(f, "Variant(&I4({:?}))", self.borrow::<&types::I4>()),
 			vt::VT_BYREF_R4 => write!(f, "Variant(&R4({:?}))", self.borrow::<&types::R4>()),
@@ -207,11 +306,11 @@ impl<'v> fmt::Debug for Variant<'v> {
 			vt::VT_BYREF_CY => write!(f, "Variant(&{:?})", self.borrow::<&types::Currency>()),
 			vt::VT_BYREF_DATE => write!(f, "Variant(&{:?})", self.borrow::<&types::Date>()),
 			vt::VT_BYREF_BSTR => write!(f, "Variant(&{:?})", &self.borrow::<&types::BString>()),
-			// vt::VT_BYREF_DISPATCH => f.field("&dispatch", ),
+			vt::VT_BYREF_DISPATCH => write!(f, "Variant(&Dispatch({:?}))", interface(self.borrow::<&types::Dispatch>())),
 			vt::VT_BYREF_ERROR => write!(f, "Variant(&Error({:?}))", self.borrow::<&types::Error>()),
 			vt::VT_BYREF_BOOL => write!(f, "Variant(&{:?})", self.borrow::<types::Bool>()),
-			// vt::VT_BYREF_VARIANT => f.field("&variant", self.borrow<&types::Variant>()),
-			// vt::VT_BYREF_UNKNOWN => f.field("&unknown", ),
+			vt::VT_BYREF_VARIANT => write!(f, "Variant(&{:?})", self.borrow::<&Variant>()),
+			vt::VT_BYREF_UNKNOWN => write!(f, "Variant(&Unknown({:?}))", interface(self.borrow::<&types::Unknown>())),
 			vt::VT_BYREF_DECIMAL => write!(f, "Variant(&{:?})", self.borrow::<&types::Decimal>()),
 			vt::VT_BYREF_I1 => write!(f, "Variant(&I1({:?}))", self.borrow::<&types::I1>()),
 			vt::VT_BYREF_UI1 => write!(f, "Variant(&UI1({:?}))", self.borrow::<&types::UI1>()),
@@ -220,14 +319,28 @@ impl<'v> fmt::Debug for Variant<'v> {
 			vt::VT_BYREF_INT => write!(f, "Variant(&Int({:?}))", self.borrow::<&types::Int>()),
 			vt::VT_BYREF_UINT => write!(f, "Variant(&UInt({:?}))", self.borrow::<&types::UInt>()),
 			// vt::VT_BYREF_RECORD => f.field("&record", ),
+			#[cfg(windows)]
+			vt::VT_ARRAY_VARIANT => write!(f, "Variant({:?})", self.borrow::<::array::TypedArray<Variant>>().try_as_slice()),
 			_ => write!(f, "Variant({:?})", self.tag()),
 		}
 	}
 }
 
+/// Returns the raw pointer of a possibly null interface for formatting.
+fn interface<I: ComInterface>(rc: &Option<ComRc<I>>) -> *mut I {
+	rc.as_ref().map_or(ptr::null_mut(), AsComPtr::as_ptr)
+}
+
 //----------------------------------------------------------------
 
 /// Legal `Variant` type.
+///
+/// # Safety
+///
+/// The `Raw` type must match the layout of the data stored in a variant tagged with `variant_tag()`.
+///
+/// The unsafe methods require the variant to be tagged with `variant_tag()` and its data to be valid.
+#[allow(clippy::missing_safety_doc)]
 pub unsafe trait Type<'a>: 'a + Sized {
 	/// Returns the type's variant tag.
 	fn variant_tag() -> Tag;
@@ -274,4 +387,252 @@ mod tests {
 		let empty = Variant::new();
 		assert_eq!(empty.into::<types::Empty>(), types::Empty);
 	}
+	#[test]
+	fn bstr() {
+		let var = Variant::from(types::BString::from("Hello, Variant!"));
+		assert!(var.is::<types::BString>());
+		assert_eq!(var.borrow::<types::BString>(), "Hello, Variant!");
+		assert_eq!(format!("{:?}", var), "Variant(\"Hello, Variant!\")");
+
+		let copy = var.try_clone().unwrap();
+		assert_eq!(copy.into::<types::BString>(), "Hello, Variant!");
+
+		let bstring = var.into::<types::BString>();
+		let byref = Variant::from(&bstring);
+		assert_eq!(byref.borrow::<&types::BString>(), "Hello, Variant!");
+		let deref = byref.try_clone_ind().unwrap();
+		assert_eq!(deref.tag(), vt::VT_BSTR);
+		assert_eq!(deref.into::<types::BString>(), bstring);
+	}
+
+	use ::com_sys::dispatch::{IDispatchVtbl, DISPID_VALUE, DISPATCH_PROPERTYGET, LOCALE_USER_DEFAULT};
+	use ::winapi::{GUID, HRESULT, UINT, WORD, DISPID, DISPPARAMS, EXCEPINFO};
+	use ::dispatch::{self, DispatchPtr};
+	use ::object::ComBox;
+	use ::unknown::IUnknownPtr;
+
+	use ::tests::{ISinkPtr, Sink};
+
+	struct Script;
+	com_dispatch! {
+		impl Script {}
+	}
+	com_object! {
+		impl Script {
+			IDispatch(IDispatchVtbl {
+				base: IUnknownVtbl,
+				GetTypeInfoCount: dispatch::get_type_info_count,
+				GetTypeInfo: dispatch::get_type_info,
+				GetIDsOfNames: dispatch::get_ids_of_names::<Script>,
+				Invoke: dispatch::invoke::<Script>,
+			}),
+		}
+	}
+
+	#[test]
+	fn byref_variant() {
+		let inner = Variant::from(types::BString::from("nested"));
+		let byref = Variant::from(&inner);
+		assert_eq!(byref.tag(), vt::VT_BYREF_VARIANT);
+		assert!(byref.is::<&Variant>());
+		assert_eq!(byref.borrow::<&Variant>().as_ptr(), inner.as_ptr());
+		assert_eq!(format!("{:?}", byref), "Variant(&Variant(\"nested\"))");
+
+		let deref = byref.try_clone_ind().unwrap();
+		assert_eq!(deref.into::<types::BString>(), "nested");
+		// Clearing the reference leaves the referenced variant alone.
+		drop(byref);
+		assert_eq!(inner.borrow::<types::BString>(), "nested");
+
+		let value = 5;
+		let inner = Variant::from(&value);
+		let byref = Variant::from(&inner);
+		assert_eq!(format!("{:?}", byref), "Variant(&Variant(&I4(5)))");
+		assert_eq!(byref.try_clone_ind().unwrap().into::<types::I4>(), 5);
+		// A variant may not reference a variant by reference.
+		assert_eq!(Variant::from(&byref).try_clone_ind().err(), Some(code::E_INVALIDARG));
+	}
+	#[test]
+	fn variant_elems() {
+		use ::array::{ArrSlice, ElemType};
+		use ::FromInner;
+		assert_eq!(Tag::from(<Variant as ElemType>::elem_tag()), vt::VT_ARRAY_VARIANT);
+		let raw = [Variant::from(1).into_raw(), Variant::from(types::BString::from("two")).into_raw()];
+		{
+			let slice: &ArrSlice<Variant> = unsafe { FromInner::from_inner(&raw[..]) };
+			assert_eq!(slice[0].borrow::<types::I4>(), &1);
+			assert_eq!(format!("{:?}", slice), "[Variant(I4(1)), Variant(\"two\")]");
+		}
+		for &raw in &raw {
+			drop(unsafe { Variant::from_raw(raw) });
+		}
+	}
+	#[cfg(windows)]
+	#[test]
+	fn variant_array() {
+		use ::array::TypedArray;
+		let sink: IUnknownPtr = ComBox::new(Sink::new());
+		let ref_count = || unsafe { ComBox::<Sink>::ref_count(sink.as_ptr()) };
+
+		let name = Variant::from(types::BString::from("two"));
+		let array = TypedArray::from_variants(&[Variant::from(1), Variant::from(&name), Variant::from(sink.clone())]).unwrap();
+		assert_eq!(ref_count(), 2);
+		let var = Variant::from(array);
+		assert_eq!(var.tag(), vt::VT_ARRAY_VARIANT);
+		assert_eq!(format!("{:?}", var.borrow::<TypedArray<Variant>>().try_as_slice().map(|slice| &slice[..2])), "Some([Variant(I4(1)), Variant(\"two\")])");
+
+		let copy = var.try_clone().unwrap();
+		assert_eq!(ref_count(), 3);
+		let array = copy.into::<TypedArray<Variant>>();
+		let slice = array.as_slice();
+		assert_eq!(slice[0].borrow::<types::I4>(), &1);
+		assert_eq!(slice[1].borrow::<types::BString>(), "two");
+		assert_eq!(slice[2].to_interface::<IUnknownPtr>().unwrap().map(|unknown| unknown.as_ptr()), Some(sink.as_ptr()));
+
+		// Clearing the variant and dropping the array release the elements.
+		drop(var);
+		assert_eq!(ref_count(), 2);
+		drop(array);
+		assert_eq!(ref_count(), 1);
+	}
+	#[test]
+	fn unknown() {
+		let sink: IUnknownPtr = ComBox::new(Sink::new());
+		let ref_count = || unsafe { ComBox::<Sink>::ref_count(sink.as_ptr()) };
+
+		let var = Variant::from(sink.clone());
+		assert!(var.is::<types::Unknown>());
+		assert_eq!(ref_count(), 2);
+		assert_eq!(var.borrow::<types::Unknown>().as_ref().map(|rc| rc.as_ptr()), Some(sink.as_ptr()));
+		assert_eq!(format!("{:?}", var), format!("Variant(Unknown({:?}))", sink.as_ptr()));
+
+		let copy = var.try_clone().unwrap();
+		assert_eq!(ref_count(), 3);
+		drop(copy);
+		assert_eq!(ref_count(), 2);
+
+		let unknown = var.into::<types::Unknown>().unwrap();
+		assert_eq!(ref_count(), 2);
+		drop(unknown);
+		assert_eq!(ref_count(), 1);
+
+		let nothing = unsafe { <types::Unknown as Type>::variant_raw_from(ptr::null_mut()) };
+		assert_eq!(format!("{:?}", nothing), "Variant(Unknown(0x0))");
+		assert!(nothing.to_interface::<ISinkPtr>().unwrap().is_none());
+		assert!(nothing.into::<types::Unknown>().is_none());
+	}
+	#[test]
+	fn unknown_byref() {
+		let sink: IUnknownPtr = ComBox::new(Sink::new());
+		let ref_count = || unsafe { ComBox::<Sink>::ref_count(sink.as_ptr()) };
+
+		let byref = Variant::from(&sink);
+		assert_eq!(byref.tag(), vt::VT_BYREF_UNKNOWN);
+		assert_eq!(ref_count(), 1);
+		assert_eq!(byref.borrow::<&types::Unknown>().as_ref().map(|rc| rc.as_ptr()), Some(sink.as_ptr()));
+		assert_eq!(format!("{:?}", byref), format!("Variant(&Unknown({:?}))", sink.as_ptr()));
+
+		let deref = byref.try_clone_ind().unwrap();
+		assert_eq!(deref.tag(), vt::VT_UNKNOWN);
+		assert_eq!(ref_count(), 2);
+		drop((byref, deref));
+		assert_eq!(ref_count(), 1);
+	}
+	#[test]
+	fn com_ptrs() {
+		let sink: ISinkPtr = ComBox::new(Sink::new());
+		let var = Variant::from_com_ptr(sink.clone());
+		assert_eq!(var.tag(), vt::VT_UNKNOWN);
+		assert!(var.to_interface::<ISinkPtr>().unwrap().unwrap().is_same_object(&sink));
+		assert_eq!(var.to_interface::<DispatchPtr>().err(), Some(CastError::NoInterface));
+		assert_eq!(Variant::from(1).to_interface::<ISinkPtr>().err(), Some(CastError::Failed(code::DISP_E_TYPEMISMATCH)));
+		drop(var);
+		unsafe { assert_eq!(ComBox::<Sink>::ref_count(sink.as_ptr()), 1); }
+
+		let script: DispatchPtr = ComBox::new(Script);
+		let var = Variant::from_com_ptr(script.clone());
+		assert_eq!(var.tag(), vt::VT_DISPATCH);
+		assert!(format!("{:?}", var).starts_with("Variant(Dispatch(0x"));
+		let byref = Variant::from(&script);
+		assert_eq!(byref.tag(), vt::VT_BYREF_DISPATCH);
+		assert!(byref.to_interface::<IUnknownPtr>().unwrap().unwrap().is_same_object(&script));
+		let dispatch = var.into::<types::Dispatch>().unwrap();
+		assert_eq!(dispatch.as_ptr(), script.as_ptr());
+	}
+
+	struct Valued;
+	com_dispatch! {
+		impl Valued {}
+	}
+	com_object! {
+		impl Valued {
+			IDispatch(IDispatchVtbl {
+				base: IUnknownVtbl,
+				GetTypeInfoCount: dispatch::get_type_info_count,
+				GetTypeInfo: dispatch::get_type_info,
+				GetIDsOfNames: dispatch::get_ids_of_names::<Valued>,
+				Invoke: invoke_value,
+			}),
+		}
+	}
+	/// Answers only the default property, with a string.
+	#[allow(clippy::too_many_arguments)]
+	unsafe extern "system" fn invoke_value(_this: *mut IDispatch, member: DISPID, _riid: *const GUID, _lcid: LCID, flags: WORD,
+		_params: *mut DISPPARAMS, result: *mut VARIANT, _excep_info: *mut EXCEPINFO, _arg_err: *mut UINT) -> HRESULT
+	{
+		if member != DISPID_VALUE || flags & DISPATCH_PROPERTYGET == 0 {
+			return code::DISP_E_MEMBERNOTFOUND.into();
+		}
+		*result = Variant::from(types::BString::from("42")).into_raw();
+		code::S_OK.into()
+	}
+
+	#[test]
+	fn change_type() {
+		let number = Variant::from(types::BString::from(" 1,234.5 "));
+		assert_eq!(number.to::<types::I4>(), Ok(1234));
+		assert_eq!(number.to::<types::R8>(), Ok(1234.5));
+		assert_eq!(Variant::from(types::BString::from("&HFF")).to::<types::UI1>(), Ok(255));
+		assert_eq!(Variant::from(types::BString::from("2.5e1")).to::<types::I2>(), Ok(25));
+		assert_eq!(Variant::from(3.5).to::<types::I4>(), Ok(4));
+		assert_eq!(Variant::from(1e20).to::<types::BString>().unwrap(), "1E+20");
+		assert_eq!(Variant::from(0.1f32).to::<types::BString>().unwrap(), "0.1");
+		assert_eq!(i64::from(Variant::from(types::BString::from("1.23456")).to::<types::Currency>().unwrap()), 12346);
+		assert_eq!(Variant::from(types::Currency::from(15000)).to::<types::BString>().unwrap(), "1.5");
+		assert_eq!(Variant::new().to::<types::I4>(), Ok(0));
+
+		assert_eq!(Variant::from(300).to::<types::UI1>(), Err(code::DISP_E_OVERFLOW));
+		assert_eq!(Variant::from(types::BString::from("twelve")).to::<types::I4>(), Err(code::DISP_E_TYPEMISMATCH));
+		assert_eq!(Variant::from(types::Null).to::<types::I4>(), Err(code::DISP_E_TYPEMISMATCH));
+
+		let value = 7;
+		let coerced = Variant::from(&value).change_type(vt::VT_I2).unwrap();
+		assert_eq!(coerced.tag(), vt::VT_I2);
+		assert_eq!(coerced.into::<types::I2>(), 7);
+		assert_eq!(Variant::from(1).change_type(vt::VT_BYREF_I4).err(), Some(code::DISP_E_BADVARTYPE));
+	}
+	#[test]
+	fn change_type_bools() {
+		let yes = Variant::from(types::Bool::from(true));
+		assert_eq!(yes.to::<types::BString>().unwrap(), "-1");
+		assert_eq!(yes.to_ex::<types::BString>(LOCALE_USER_DEFAULT, ChangeTypeFlags::VARIANT_ALPHABOOL).unwrap(), "True");
+		assert_eq!(yes.to::<types::I2>(), Ok(-1));
+		assert_eq!(yes.to::<types::UI2>(), Ok(0xFFFF));
+		assert_eq!(Variant::from(types::BString::from("False")).to::<types::Bool>(), Ok(types::Bool::from(false)));
+		assert_eq!(Variant::from(types::BString::from("0.5")).to::<types::Bool>(), Ok(types::Bool::from(true)));
+	}
+	#[test]
+	fn change_type_objects() {
+		let valued: DispatchPtr = ComBox::new(Valued);
+		let var = Variant::from_com_ptr(valued.clone());
+		assert_eq!(var.to::<types::I4>(), Ok(42));
+		assert_eq!(var.to_ex::<types::I4>(LOCALE_USER_DEFAULT, ChangeTypeFlags::VARIANT_NOVALUEPROP), Err(code::DISP_E_TYPEMISMATCH));
+
+		let unknown = var.change_type(vt::VT_UNKNOWN).unwrap();
+		assert_eq!(unknown.tag(), vt::VT_UNKNOWN);
+		assert!(unknown.to_interface::<IUnknownPtr>().unwrap().unwrap().is_same_object(&valued));
+		assert_eq!(unknown.change_type(vt::VT_DISPATCH).unwrap().tag(), vt::VT_DISPATCH);
+		drop((var, unknown));
+		unsafe { assert_eq!(ComBox::<Valued>::ref_count(valued.as_ptr()), 1); }
+	}
 }
@@ -1,6 +1,8 @@
 
+#[cfg(windows)]
 use ::winapi::{HRESULT, VARIANTARG, USHORT, VARTYPE, LCID};
 
+#[cfg(windows)]
 extern "system" {
 	pub fn VariantChangeType(pvargDest: *mut VARIANTARG, pvargSrc: *const VARIANTARG, wFlags: USHORT, vt: VARTYPE) -> HRESULT;
 	pub fn VariantChangeTypeEx(pvargDest: *mut VARIANTARG, pvargSrc: *const VARIANTARG, lcid: LCID, wFlags: USHORT, vt: VARTYPE) -> HRESULT;
@@ -9,3 +11,254 @@ extern "system" {
 	pub fn VariantCopyInd(pvargDest: *mut VARIANTARG, pvargSrc: *const VARIANTARG) -> HRESULT;
 	pub fn VariantInit(pvarg: *mut VARIANTARG);
 }
+
+#[cfg(not(windows))]
+pub use self::rust::{VariantChangeType, VariantChangeTypeEx, VariantClear, VariantCopy, VariantCopyInd, VariantInit};
+
+/// Pure Rust implementation of the variant lifecycle functions for targets without oleaut32.
+///
+/// Supports the types which can be represented without a COM runtime, interface pointers and records, others fail with `DISP_E_BADVARTYPE`.
+/// The coercions are implemented by the `coerce` module.
+#[cfg(not(windows))]
+#[allow(non_snake_case)]
+mod rust {
+	use ::std::ptr;
+
+	use ::winapi::{BSTR, HRESULT, VARIANTARG, USHORT, VARTYPE, LCID};
+	use ::com_sys::ComInterface;
+	use ::com_sys::dispatch::{BRECORD, LOCALE_USER_DEFAULT};
+	use ::com_sys::unknown::IUnknown;
+
+	use ::bstr::sys::{SysAllocStringLen, SysStringLen, SysFreeString};
+
+	use super::super::{vt, Tag, Variant, ChangeTypeFlags};
+	use super::super::coerce::change_type;
+
+	const S_OK: HRESULT = 0;
+	const E_OUTOFMEMORY: HRESULT = 0x8007000Eu32 as HRESULT;
+	const E_INVALIDARG: HRESULT = 0x80070057u32 as HRESULT;
+	const DISP_E_BADVARTYPE: HRESULT = 0x80020008u32 as HRESULT;
+
+	unsafe fn tag(pvarg: *const VARIANTARG) -> Tag {
+		ptr::read(pvarg as *const Tag)
+	}
+	unsafe fn set_tag(pvarg: *mut VARIANTARG, tag: Tag) {
+		ptr::write(pvarg as *mut Tag, tag);
+	}
+	unsafe fn data(pvarg: *const VARIANTARG) -> *const u8 {
+		&(*pvarg).data1 as *const _ as *const u8
+	}
+	unsafe fn data_mut(pvarg: *mut VARIANTARG) -> *mut u8 {
+		&mut (*pvarg).data1 as *mut _ as *mut u8
+	}
+
+	/// Size of the data for types without ownership semantics.
+	fn plain_size(tag: Tag) -> Option<usize> {
+		match tag {
+			vt::VT_EMPTY | vt::VT_NULL => Some(0),
+			vt::VT_I1 | vt::VT_UI1 => Some(1),
+			vt::VT_I2 | vt::VT_UI2 | vt::VT_BOOL => Some(2),
+			vt::VT_I4 | vt::VT_UI4 | vt::VT_R4 | vt::VT_INT | vt::VT_UINT | vt::VT_ERROR => Some(4),
+			vt::VT_R8 | vt::VT_CY | vt::VT_DATE => Some(8),
+			_ => None,
+		}
+	}
+
+	/// Returns if the variant holds an interface pointer.
+	fn is_interface(tag: Tag) -> bool {
+		tag == vt::VT_UNKNOWN || tag == vt::VT_DISPATCH
+	}
+	unsafe fn interface(pvarg: *const VARIANTARG) -> *mut IUnknown {
+		ptr::read(data(pvarg) as *const *mut IUnknown)
+	}
+
+	/// Replaces the record borrowed from the source by a copy and adds a reference to its record info.
+	unsafe fn copy_record(pvarg: *mut VARIANTARG) -> HRESULT {
+		let record = data_mut(pvarg) as *mut BRECORD;
+		let info = (*record).pRecInfo;
+		if info.is_null() {
+			return S_OK;
+		}
+		if !(*record).pvRecord.is_null() {
+			let mut copy = ptr::null_mut();
+			let hr = ((*info).vtbl().RecordCreateCopy)(info, (*record).pvRecord, &mut copy);
+			if hr < 0 {
+				return hr;
+			}
+			(*record).pvRecord = copy;
+		}
+		((*info).vtbl().AddRef)(info);
+		S_OK
+	}
+
+	/// Initializes the variant as `VT_EMPTY`.
+	///
+	/// # Safety
+	///
+	/// `pvarg` must be valid for writes.
+	pub unsafe fn VariantInit(pvarg: *mut VARIANTARG) {
+		set_tag(pvarg, vt::VT_EMPTY);
+	}
+
+	/// Frees the variant's contents and resets it to `VT_EMPTY`.
+	///
+	/// # Safety
+	///
+	/// `pvarg` must point to an initialized variant.
+	pub unsafe fn VariantClear(pvarg: *mut VARIANTARG) -> HRESULT {
+		let tag = tag(pvarg);
+		if tag.0 & vt::VT_BYREF == 0 {
+			match tag {
+				vt::VT_BSTR => SysFreeString(ptr::read(data(pvarg) as *const BSTR)),
+				vt::VT_DECIMAL => (),
+				vt::VT_RECORD => {
+					let record = ptr::read(data(pvarg) as *const BRECORD);
+					let info = record.pRecInfo;
+					if !info.is_null() {
+						if !record.pvRecord.is_null() {
+							((*info).vtbl().RecordDestroy)(info, record.pvRecord);
+						}
+						((*info).vtbl().Release)(info);
+					}
+				},
+				_ if is_interface(tag) => {
+					let unknown = interface(pvarg);
+					if !unknown.is_null() {
+						((*unknown).vtbl().Release)(unknown);
+					}
+				},
+				_ if plain_size(tag).is_some() => (),
+				_ => return DISP_E_BADVARTYPE,
+			}
+		}
+		set_tag(pvarg, vt::VT_EMPTY);
+		S_OK
+	}
+
+	/// Clears the destination and copies the source into it, duplicating owned strings and records and adding references to interfaces.
+	///
+	/// # Safety
+	///
+	/// Both pointers must point to initialized variants.
+	pub unsafe fn VariantCopy(pvargDest: *mut VARIANTARG, pvargSrc: *const VARIANTARG) -> HRESULT {
+		if ptr::eq(pvargDest, pvargSrc) {
+			return S_OK;
+		}
+		let tag = tag(pvargSrc);
+		let copy = if tag.0 & vt::VT_BYREF != 0 || tag == vt::VT_DECIMAL || plain_size(tag).is_some() {
+			None
+		}
+		else if tag == vt::VT_BSTR {
+			let bstr = ptr::read(data(pvargSrc) as *const BSTR);
+			if bstr.is_null() {
+				Some(bstr)
+			}
+			else {
+				let copy = SysAllocStringLen(bstr, SysStringLen(bstr));
+				if copy.is_null() {
+					return E_OUTOFMEMORY;
+				}
+				Some(copy)
+			}
+		}
+		else if is_interface(tag) || tag == vt::VT_RECORD {
+			None
+		}
+		else {
+			return DISP_E_BADVARTYPE;
+		};
+		let hr = VariantClear(pvargDest);
+		if hr < 0 {
+			if let Some(copy) = copy {
+				SysFreeString(copy);
+			}
+			return hr;
+		}
+		ptr::copy_nonoverlapping(pvargSrc, pvargDest, 1);
+		if let Some(copy) = copy {
+			ptr::write(data_mut(pvargDest) as *mut BSTR, copy);
+		}
+		if is_interface(tag) {
+			let unknown = interface(pvargDest);
+			if !unknown.is_null() {
+				((*unknown).vtbl().AddRef)(unknown);
+			}
+		}
+		if tag == vt::VT_RECORD {
+			let hr = copy_record(pvargDest);
+			if hr < 0 {
+				set_tag(pvargDest, vt::VT_EMPTY);
+				return hr;
+			}
+		}
+		S_OK
+	}
+
+	/// Copies the source into the destination, dereferencing `VT_BYREF` variants.
+	///
+	/// # Safety
+	///
+	/// Both pointers must point to initialized variants, by reference variants must point to valid data.
+	pub unsafe fn VariantCopyInd(pvargDest: *mut VARIANTARG, pvargSrc: *const VARIANTARG) -> HRESULT {
+		let tag = tag(pvargSrc);
+		if tag.0 & vt::VT_BYREF == 0 {
+			return VariantCopy(pvargDest, pvargSrc);
+		}
+		let pointee = Tag(tag.0 & !vt::VT_BYREF);
+		if pointee == vt::VT_VARIANT {
+			// Copies the referenced variant, which may not reference another variant.
+			let referenced = ptr::read(data(pvargSrc) as *const *const VARIANTARG);
+			if self::tag(referenced) == vt::VT_BYREF_VARIANT {
+				return E_INVALIDARG;
+			}
+			return VariantCopyInd(pvargDest, referenced);
+		}
+		if pointee == vt::VT_RECORD {
+			// Records by reference are stored like records, without owning them.
+			let mut deref = ptr::read(pvargSrc);
+			set_tag(&mut deref, pointee);
+			return VariantCopy(pvargDest, &deref);
+		}
+		let size = match pointee {
+			vt::VT_BSTR => ::std::mem::size_of::<BSTR>(),
+			_ if is_interface(pointee) => ::std::mem::size_of::<*mut IUnknown>(),
+			_ => match plain_size(pointee) {
+				Some(size) => size,
+				None => return DISP_E_BADVARTYPE,
+			},
+		};
+		// Dereference into a temporary which borrows any string or interface, `VariantCopy` then duplicates it.
+		let mut deref: VARIANTARG = ::std::mem::zeroed();
+		set_tag(&mut deref, pointee);
+		let src = ptr::read(data(pvargSrc) as *const *const u8);
+		ptr::copy_nonoverlapping(src, data_mut(&mut deref), size);
+		VariantCopy(pvargDest, &deref)
+	}
+
+	/// Coerces the source to the type and stores it in the destination, see [`VariantChangeTypeEx`](fn.VariantChangeTypeEx.html).
+	///
+	/// # Safety
+	///
+	/// Both pointers must point to initialized variants, they may point to the same variant.
+	pub unsafe fn VariantChangeType(pvargDest: *mut VARIANTARG, pvargSrc: *const VARIANTARG, wFlags: USHORT, vt: VARTYPE) -> HRESULT {
+		VariantChangeTypeEx(pvargDest, pvargSrc, LOCALE_USER_DEFAULT, wFlags, vt)
+	}
+
+	/// Coerces the source to the type and stores it in the destination, numbers are converted to and from strings for the invariant locale.
+	///
+	/// # Safety
+	///
+	/// Both pointers must point to initialized variants, they may point to the same variant.
+	pub unsafe fn VariantChangeTypeEx(pvargDest: *mut VARIANTARG, pvargSrc: *const VARIANTARG, _lcid: LCID, wFlags: USHORT, vt: VARTYPE) -> HRESULT {
+		let value = match change_type(&*(pvargSrc as *const Variant), Tag(vt), ChangeTypeFlags::from_bits_truncate(wFlags)) {
+			Ok(value) => value,
+			Err(hr) => return hr.into(),
+		};
+		let hr = VariantClear(pvargDest);
+		if hr < 0 {
+			return hr;
+		}
+		ptr::write(pvargDest, value.into_raw());
+		S_OK
+	}
+}
@@ -2,7 +2,9 @@
 Variant types.
 */
 
-use ::winapi::{CY, DATE, SCODE, BSTR, DECIMAL, SAFEARRAY};
+use ::winapi::{CY, DATE, SCODE, BSTR, DECIMAL, VARIANT};
+#[cfg(windows)]
+use ::winapi::{SAFEARRAY};
 
 use super::{vt, Type, Variant, Tag};
 
@@ -131,6 +133,78 @@ pub type Decimal = ::decimal::Decimal;
 impl_type_reprc!(VT_DECIMAL, DECIMAL, ::decimal::Decimal);
 impl_type_ref_reprc!(Decimal);
 
+//----------------------------------------------------------------
+// Interface pointers
+
+/// Interface pointer variant, `Nothing` when null.
+pub type Unknown = ::unknown::IUnknownPtr;
+/// Automation object variant, `Nothing` when null.
+pub type Dispatch = ::dispatch::DispatchPtr;
+
+use ::com_sys::unknown::IUnknown;
+use ::com_sys::dispatch::IDispatch;
+
+impl_type_com_ptr!(VT_UNKNOWN, Unknown, IUnknown);
+impl_type_ref_com_ptr!(Unknown, IUnknown);
+
+impl_type_com_ptr!(VT_DISPATCH, Dispatch, IDispatch);
+impl_type_ref_com_ptr!(Dispatch, IDispatch);
+
+//----------------------------------------------------------------
+// Records
+
+/// User defined type variant, see the [`record`](../../record/index.html) module.
+pub type Record = ::record::Record;
+
+use ::com_sys::dispatch::BRECORD;
+
+unsafe impl<'a> Type<'a> for Record {
+	fn variant_tag() -> Tag {
+		vt::VT_RECORD
+	}
+	type Raw = BRECORD;
+	unsafe fn variant_raw_into(var: Variant<'a>) -> BRECORD {
+		// Spans both data words, read through the borrow.
+		let raw = *Self::variant_raw_borrow(&var);
+		::std::mem::forget(var);
+		raw
+	}
+	type Borrowed = Record;
+	type Owned = Record;
+	unsafe fn variant_borrow<'s>(var: &'s Variant<'a>) -> &'s Record {
+		// `Record` has the layout of `BRECORD`.
+		&*(Self::variant_raw_borrow(var) as *const BRECORD as *const Record)
+	}
+	unsafe fn variant_into(var: Variant<'a>) -> Record {
+		Record::from_raw(Self::variant_raw_into(var))
+	}
+	unsafe fn variant_from(self) -> Variant<'a> {
+		Self::variant_raw_from(self.into_raw())
+	}
+}
+
+//----------------------------------------------------------------
+// Nested variants
+
+/// Variants are nested by reference, the referenced variant is owned by the caller and not cleared with this one.
+unsafe impl<'a> Type<'a> for &'a Variant<'a> {
+	fn variant_tag() -> Tag {
+		vt::VT_BYREF_VARIANT
+	}
+	type Raw = *const VARIANT;
+	type Borrowed = Variant<'a>;
+	type Owned = &'a Variant<'a>;
+	unsafe fn variant_borrow<'s>(var: &'s Variant<'a>) -> &'s Variant<'a> {
+		&*(*Self::variant_raw_borrow(var) as *const Variant<'a>)
+	}
+	unsafe fn variant_into(var: Variant<'a>) -> &'a Variant<'a> {
+		&*(Self::variant_raw_into(var) as *const Variant<'a>)
+	}
+	unsafe fn variant_from(self) -> Variant<'a> {
+		Self::variant_raw_from(self.as_ptr())
+	}
+}
+
 //----------------------------------------------------------------
 
 /// Signed byte variant.
@@ -174,8 +248,10 @@ impl_type_ref_reprc!(UInt);
 
 //----------------------------------------------------------------
 
+#[cfg(windows)]
 use ::array::{ElemType, TypedArr, TypedArray};
 
+#[cfg(windows)]
 unsafe impl<'a, T: 'a + ElemType> Type<'a> for TypedArray<T> {
 	fn variant_tag() -> Tag {
 		use ::IntoInner;
@@ -196,6 +272,7 @@ unsafe impl<'a, T: 'a + ElemType> Type<'a> for TypedArray<T> {
 		Self::variant_raw_from(self.into_raw())
 	}
 }
+#[cfg(windows)]
 unsafe impl<'a, T: 'a + ElemType> Type<'a> for &'a TypedArray<T> {
 	fn variant_tag() -> Tag {
 		use ::IntoInner;
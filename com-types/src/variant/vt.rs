@@ -16,6 +16,8 @@ pub const VT_BSTR: Tag = Tag(8);
 pub const VT_DISPATCH: Tag = Tag(9);
 pub const VT_ERROR: Tag = Tag(10);
 pub const VT_BOOL: Tag = Tag(11);
+/// Only valid by reference or as array element type.
+pub const VT_VARIANT: Tag = Tag(12);
 pub const VT_UNKNOWN: Tag = Tag(13);
 pub const VT_DECIMAL: Tag = Tag(14);
 pub const VT_I1: Tag = Tag(16);
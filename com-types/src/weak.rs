@@ -0,0 +1,172 @@
+/*!
+Bridges weak references to `IWeakReferenceSource`.
+
+Rust objects implement `IWeakReferenceSource` by listing it in [`com_object!`](../macro.com_object!.html) with [`get_weak_reference`](fn.get_weak_reference.html).
+The `IWeakReference` it hands out is backed by a [`ComWeak`](../object/struct.ComWeak.html) and resolves to the object for as long as it exists.
+
+# Examples
+
+```
+# #[macro_use] extern crate com_sys; #[macro_use] extern crate com_types; fn main() {
+use ::com_sys::unknown::IUnknown;
+use ::com_sys::weakref::{IWeakReferenceSource, IWeakReferenceSourceVtbl};
+use ::com_types::object::ComBox;
+use ::com_types::unknown::IUnknownPtr;
+use ::com_types::weak::{self, IWeakReferenceSourcePtr};
+
+struct Sink;
+com_object! {
+	impl Sink {
+		IWeakReferenceSource(IWeakReferenceSourceVtbl {
+			base: IUnknownVtbl,
+			GetWeakReference: weak::get_weak_reference::<Sink>,
+		}),
+	}
+}
+
+let source: IWeakReferenceSourcePtr = ComBox::new(Sink);
+let weak = source.get_weak_reference().unwrap();
+assert!(weak.resolve::<IUnknownPtr>().unwrap().is_some());
+drop(source);
+assert!(weak.resolve::<IUnknownPtr>().unwrap().is_none());
+# }
+```
+*/
+
+use ::std::mem::ManuallyDrop;
+use ::std::ptr;
+
+use ::com_sys::ComInterface;
+use ::com_sys::unknown::IUnknown;
+use ::com_sys::weakref::{IWeakReference, IWeakReferenceVtbl, IWeakReferenceSource};
+use ::winapi::{HRESULT, GUID};
+
+use ::hr::{code, HResult};
+use ::com::{AsComPtr, ComPtr, ComResult};
+use ::object::{ComBox, ComObjectInterface, ComWeak};
+use ::unknown::IUnknownPtr;
+
+com_ptr! {
+	#[derive(Clone, Debug)]
+	pub struct IWeakReferencePtr(IWeakReference): IUnknownPtr;
+}
+impl IWeakReferencePtr {
+	/// Resolves the weak reference to the interface of the com pointer `T`.
+	///
+	/// Returns `Ok(None)` if the object no longer exists.
+	pub fn resolve<T: ComPtr>(&self) -> ComResult<Option<T>> {
+		unsafe {
+			let mut unknown = ptr::null_mut();
+			let hr = com_call!(Resolve(self, <T::Interface as ComInterface>::iid(), &mut unknown));
+			HResult::result_of(hr, || if unknown.is_null() { None } else { Some(T::new(unknown as ::LPVOID)) })
+		}
+	}
+}
+
+com_ptr! {
+	#[derive(Clone, Debug)]
+	pub struct IWeakReferenceSourcePtr(IWeakReferenceSource): IUnknownPtr;
+}
+impl IWeakReferenceSourcePtr {
+	/// Returns a weak reference to the object.
+	pub fn get_weak_reference(&self) -> ComResult<IWeakReferencePtr> {
+		unsafe {
+			let mut weak = ptr::null_mut();
+			let hr = com_call!(GetWeakReference(self, &mut weak));
+			HResult::result_of(hr, || IWeakReferencePtr::from_ptr(weak))
+		}
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Implements `IWeakReferenceSource::GetWeakReference` for the `ComBox<T>` object.
+///
+/// # Safety
+///
+/// Only to be listed in the `IWeakReferenceSource` vtbl of `T` in `com_object!`.
+/// Aggregated objects fail with `E_FAIL`, see [`ComBox::downgrade`](../object/struct.ComBox.html#method.downgrade).
+pub unsafe extern "system" fn get_weak_reference<T>(this: *mut IWeakReferenceSource, weak_reference: *mut *mut IWeakReference) -> HRESULT
+	where T: ComObjectInterface<IWeakReferenceSource>
+{
+	if weak_reference.is_null() {
+		return code::E_POINTER.into();
+	}
+	// Borrows the `This` pointer, the caller holds its reference.
+	let source = ManuallyDrop::new(IWeakReferenceSourcePtr::from_ptr(this));
+	let weak = match ComBox::<T>::try_downgrade(&*source) {
+		Some(weak) => weak,
+		None => {
+			*weak_reference = ptr::null_mut();
+			return code::E_FAIL.into();
+		},
+	};
+	let weak: IWeakReferencePtr = ComBox::new(WeakReference(weak));
+	*weak_reference = ManuallyDrop::new(weak).as_ptr();
+	code::S_OK.into()
+}
+
+/// The `IWeakReference` handed out by `get_weak_reference`.
+struct WeakReference(ComWeak<IWeakReferenceSourcePtr>);
+
+unsafe extern "system" fn weak_reference_resolve(this: *mut IWeakReference, riid: *const GUID, object: *mut *mut IUnknown) -> HRESULT {
+	if riid.is_null() || object.is_null() {
+		return code::E_POINTER.into();
+	}
+	*object = ptr::null_mut();
+	let source = match ComBox::<WeakReference>::from_interface(this).0.upgrade() {
+		Some(source) => source,
+		// Resolving a weak reference to a destroyed object succeeds with null.
+		None => return code::S_OK.into(),
+	};
+	match source.query_iid(&*riid) {
+		Ok(iface) => {
+			*object = ManuallyDrop::new(iface).as_ptr();
+			code::S_OK.into()
+		},
+		Err(hr) => hr.into(),
+	}
+}
+
+com_object! {
+	impl WeakReference {
+		IWeakReference(IWeakReferenceVtbl {
+			base: IUnknownVtbl,
+			Resolve: weak_reference_resolve,
+		}),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use ::std::cell::Cell;
+	use ::std::rc::Rc;
+	use ::tests::{ISinkPtr, Sink};
+	use super::*;
+
+	#[test]
+	fn weak_reference() {
+		let alive = Rc::new(Cell::new(true));
+		let sink: ISinkPtr = ComBox::new(Sink(alive.clone()));
+		let source = sink.try_cast::<IWeakReferenceSourcePtr>().unwrap();
+		let weak = source.get_weak_reference().unwrap();
+		drop(source);
+		unsafe { assert_eq!(ComBox::<Sink>::ref_count(sink.as_ptr()), 1); }
+
+		let resolved = weak.resolve::<ISinkPtr>().unwrap().unwrap();
+		assert_eq!(resolved.as_ptr(), sink.as_ptr());
+		assert_eq!(weak.resolve::<IWeakReferencePtr>().err(), Some(code::E_NOINTERFACE));
+		drop((sink, resolved));
+		assert!(!alive.get());
+		assert!(weak.resolve::<ISinkPtr>().unwrap().is_none());
+	}
+	#[test]
+	fn aggregated() {
+		// Weak references are taken to the outer object, the inner one fails rather than panicking.
+		let outer: IUnknownPtr = ComBox::new(Sink::new());
+		let inner = unsafe { ComBox::aggregate(Sink::new(), &outer) };
+		let source = inner.try_cast::<IWeakReferenceSourcePtr>().unwrap();
+		assert_eq!(source.get_weak_reference().err(), Some(code::E_FAIL));
+		drop((source, inner));
+	}
+}
@@ -0,0 +1,45 @@
+/*!
+Portable stand-ins for the `winapi` types used by this crate.
+
+The `winapi` crate only builds on Windows, on other targets these definitions take its place.
+They have the same layout as their Windows counterparts.
+*/
+
+#![allow(non_camel_case_types, non_snake_case, clippy::upper_case_acronyms)]
+
+pub use ::com_sys::{GUID, HRESULT, LPVOID, VARIANT, VARIANTARG, DISPPARAMS, EXCEPINFO};
+
+pub type WORD = u16;
+pub type USHORT = u16;
+pub type DWORD = u32;
+pub type ULONG = u32;
+pub type LONG = i32;
+pub type UINT = u32;
+pub type BOOL = i32;
+pub type SCODE = LONG;
+pub type ULONGLONG = u64;
+pub type DATE = f64;
+pub type LCID = DWORD;
+pub type VARTYPE = USHORT;
+pub type DISPID = LONG;
+
+pub type OLECHAR = u16;
+pub type LPOLESTR = *mut OLECHAR;
+pub type LPCOLESTR = *const OLECHAR;
+pub type BSTR = *mut OLECHAR;
+
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct CY {
+	pub int64: i64,
+}
+
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct DECIMAL {
+	pub wReserved: u16,
+	pub scale: u8,
+	pub sign: u8,
+	pub Hi32: u32,
+	pub Lo64: ULONGLONG,
+}